@@ -0,0 +1,401 @@
+//! Arweave 存储后端：文件被签名为 ANS-104 数据项，通过 Irys 打包节点上传
+
+use crate::config::{HttpOptions, RetryPolicy};
+use crate::pinata::HttpError;
+use crate::storage::{StorageBackend, content_type};
+use crate::upload::retry_with_timeout;
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::header::CONTENT_TYPE;
+use rsa::pss::SigningKey;
+use rsa::signature::{RandomizedSigner, SignatureEncoding};
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, RsaPrivateKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+// --- 钱包 ---
+/// Arweave 钱包使用 4096 位 RSA 密钥，公钥模数即数据项中的 owner 字段
+const OWNER_LENGTH: usize = 512;
+/// ANS-104 签名类型 1：Arweave RSA-PSS
+const SIGNATURE_TYPE_ARWEAVE: u16 = 1;
+/// Arweave 使用固定 32 字节盐长度的 RSA-PSS 签名
+const PSS_SALT_LENGTH: usize = 32;
+
+/// Arweave 钱包文件（JWK 格式，所有字段为 base64url）
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    n: String,
+    e: String,
+    d: String,
+    p: String,
+    q: String,
+}
+
+/// 用于签名数据项的 Arweave 钱包
+pub struct ArweaveWallet {
+    key: RsaPrivateKey,
+}
+
+impl ArweaveWallet {
+    /// 从 Arweave 钱包 JWK 文件加载私钥
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Arweave wallet {:?}", path))?;
+        let jwk: Jwk = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid Arweave wallet {:?}", path))?;
+        if jwk.kty != "RSA" {
+            return Err(anyhow!(
+                "Arweave wallet {:?} must be an RSA key, got kty={}",
+                path,
+                jwk.kty
+            ));
+        }
+
+        let decode = |field: &str, value: &str| -> Result<BigUint> {
+            let bytes = URL_SAFE_NO_PAD
+                .decode(value)
+                .with_context(|| format!("Invalid `{}` in Arweave wallet {:?}", field, path))?;
+            Ok(BigUint::from_bytes_be(&bytes))
+        };
+        let key = RsaPrivateKey::from_components(
+            decode("n", &jwk.n)?,
+            decode("e", &jwk.e)?,
+            decode("d", &jwk.d)?,
+            vec![decode("p", &jwk.p)?, decode("q", &jwk.q)?],
+        )
+        .with_context(|| format!("Invalid RSA key in Arweave wallet {:?}", path))?;
+
+        if key.size() != OWNER_LENGTH {
+            return Err(anyhow!(
+                "Arweave wallet {:?} must hold a 4096-bit RSA key, got {} bits",
+                path,
+                key.size() * 8
+            ));
+        }
+        Ok(ArweaveWallet { key })
+    }
+
+    /// 钱包地址：公钥模数的 SHA-256（base64url）
+    pub fn address(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.owner()))
+    }
+
+    fn owner(&self) -> Vec<u8> {
+        self.key.n().to_bytes_be()
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let signing_key =
+            SigningKey::<Sha256>::new_with_salt_len(self.key.clone(), PSS_SALT_LENGTH);
+        signing_key
+            .sign_with_rng(&mut rand::thread_rng(), message)
+            .to_vec()
+    }
+}
+
+// --- ANS-104 数据项 ---
+/// Avro 变长整数（zigzag 编码）
+fn write_avro_long(out: &mut Vec<u8>, value: i64) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// 按 ANS-104 规定把标签编码为 Avro 数组，没有标签时为空
+fn encode_tags(tags: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if tags.is_empty() {
+        return out;
+    }
+    write_avro_long(&mut out, tags.len() as i64);
+    for (name, value) in tags {
+        for field in [name, value] {
+            write_avro_long(&mut out, field.len() as i64);
+            out.extend_from_slice(field.as_bytes());
+        }
+    }
+    out.push(0);
+    out
+}
+
+/// Arweave deep hash：签名覆盖的是各字段的嵌套 SHA-384，而不是原始字节
+fn deep_hash(chunks: &[&[u8]]) -> Vec<u8> {
+    let blob_hash = |data: &[u8]| -> Vec<u8> {
+        let tag = Sha384::digest(format!("blob{}", data.len()));
+        let mut hasher = Sha384::new();
+        hasher.update(tag);
+        hasher.update(Sha384::digest(data));
+        hasher.finalize().to_vec()
+    };
+
+    let mut acc = Sha384::digest(format!("list{}", chunks.len())).to_vec();
+    for chunk in chunks {
+        let mut hasher = Sha384::new();
+        hasher.update(&acc);
+        hasher.update(blob_hash(chunk));
+        acc = hasher.finalize().to_vec();
+    }
+    acc
+}
+
+/// 签名后的数据项，`id` 为签名的 SHA-256（base64url）
+struct DataItem {
+    id: String,
+    bytes: Vec<u8>,
+}
+
+fn create_data_item(wallet: &ArweaveWallet, data: &[u8], tags: &[(&str, &str)]) -> DataItem {
+    let owner = wallet.owner();
+    let encoded_tags = encode_tags(tags);
+    let signature_type = SIGNATURE_TYPE_ARWEAVE.to_string();
+    let signature = wallet.sign(&deep_hash(&[
+        b"dataitem",
+        b"1",
+        signature_type.as_bytes(),
+        &owner,
+        b"", // target
+        b"", // anchor
+        &encoded_tags,
+        data,
+    ]));
+
+    let mut bytes = Vec::with_capacity(2 + 2 * OWNER_LENGTH + 18 + encoded_tags.len() + data.len());
+    bytes.extend_from_slice(&SIGNATURE_TYPE_ARWEAVE.to_le_bytes());
+    bytes.extend_from_slice(&signature);
+    bytes.extend_from_slice(&owner);
+    bytes.push(0); // 没有 target
+    bytes.push(0); // 没有 anchor
+    bytes.extend_from_slice(&(tags.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(encoded_tags.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&encoded_tags);
+    bytes.extend_from_slice(data);
+
+    DataItem {
+        id: URL_SAFE_NO_PAD.encode(Sha256::digest(&signature)),
+        bytes,
+    }
+}
+
+// --- 路径清单 ---
+const MANIFEST_CONTENT_TYPE: &str = "application/x.arweave-manifest+json";
+
+/// `arweave/paths` 清单，网关据此把 `<清单 ID>/<文件名>` 解析到各个文件
+#[derive(Serialize, Deserialize)]
+struct PathManifest {
+    manifest: String,
+    version: String,
+    paths: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    id: String,
+}
+
+// --- Arweave 客户端 ---
+/// 创建 `ArweaveClient` 时的可配置项
+#[derive(Debug, Clone)]
+pub struct ArweaveOptions {
+    pub retry: RetryPolicy,
+    pub http: HttpOptions,
+    /// 接收数据项的 Irys 节点，例如 `https://node1.irys.xyz`
+    pub node_url: String,
+    /// 生成链接和 `--verify` 回读使用的 Arweave 网关，例如 `https://arweave.net`
+    pub gateway: String,
+}
+
+pub struct ArweaveClient {
+    http: reqwest::Client,
+    /// dry-run 模式下没有钱包
+    wallet: Option<ArweaveWallet>,
+    retry: RetryPolicy,
+    http_options: HttpOptions,
+    node_url: String,
+    gateway: String,
+}
+
+impl ArweaveClient {
+    pub fn new(wallet: ArweaveWallet, options: ArweaveOptions) -> Self {
+        info!("🔑 Using Arweave wallet {}", wallet.address());
+        Self::with_wallet(Some(wallet), options)
+    }
+
+    /// 不加载钱包、不访问网络的客户端，所有上传都返回占位标识
+    pub fn dry_run(options: ArweaveOptions) -> Self {
+        Self::with_wallet(None, options)
+    }
+
+    fn with_wallet(wallet: Option<ArweaveWallet>, options: ArweaveOptions) -> Self {
+        ArweaveClient {
+            http: options.http.client(),
+            wallet,
+            retry: options.retry,
+            http_options: options.http,
+            node_url: options.node_url.trim_end_matches('/').to_string(),
+            gateway: options.gateway.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// 签名并上传一个数据项，返回 Irys 节点确认的交易 ID
+    async fn post_data_item(&self, data: &[u8], tags: &[(&str, &str)]) -> Result<String> {
+        let wallet = self
+            .wallet
+            .as_ref()
+            .ok_or_else(|| anyhow!("No Arweave wallet loaded"))?;
+        let item = create_data_item(wallet, data, tags);
+
+        let url = format!("{}/tx/arweave", self.node_url);
+        let response = self
+            .http
+            .post(&url)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .body(item.bytes)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let body = response.text().await.unwrap_or_default();
+            return Err(HttpError::new(
+                status,
+                &headers,
+                format!("Irys node returned HTTP {}: {}", status, body),
+            )
+            .into());
+        }
+
+        #[derive(Deserialize)]
+        struct UploadResponse {
+            id: String,
+        }
+        let uploaded: UploadResponse = response.json().await?;
+        if uploaded.id != item.id {
+            return Err(anyhow!(
+                "Irys node returned transaction ID {}, expected {}",
+                uploaded.id,
+                item.id
+            ));
+        }
+        Ok(uploaded.id)
+    }
+
+    async fn upload_path(&self, path: &Path) -> Result<String> {
+        let data = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        self.post_data_item(&data, &[("Content-Type", content_type(path))])
+            .await
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ArweaveClient {
+    fn name(&self) -> &'static str {
+        "Arweave"
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.wallet.is_none()
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry.clone()
+    }
+
+    fn http_options(&self) -> &HttpOptions {
+        &self.http_options
+    }
+
+    async fn upload_file(&self, path: &Path) -> Result<String> {
+        self.upload_path(path).await
+    }
+
+    /// 逐个上传文件，再上传一份路径清单；返回清单的交易 ID
+    async fn upload_directory(&self, dir: &Path, files: &[PathBuf]) -> Result<String> {
+        let mut paths = BTreeMap::new();
+        for file in files {
+            let relative = file
+                .strip_prefix(dir)
+                .with_context(|| format!("{:?} is not inside {:?}", file, dir))?;
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let id = self.upload_path(file).await?;
+            info!("📄 Uploaded {} as Arweave transaction {}", name, id);
+            paths.insert(name, ManifestEntry { id });
+        }
+
+        let manifest = PathManifest {
+            manifest: "arweave/paths".to_string(),
+            version: "0.2.0".to_string(),
+            paths,
+        };
+        self.post_data_item(
+            &serde_json::to_vec(&manifest)?,
+            &[
+                ("Type", "manifest"),
+                ("Content-Type", MANIFEST_CONTENT_TYPE),
+            ],
+        )
+        .await
+    }
+
+    fn uri_scheme(&self) -> &'static str {
+        "ar"
+    }
+
+    /// 网关上的可点击链接，例如 `https://arweave.net/<manifest_id>/1.png`
+    fn gateway_url(&self, id_path: &str) -> String {
+        format!("{}/{}", self.gateway, id_path)
+    }
+
+    /// Arweave 交易 ID 是 32 字节哈希的 base64url 编码（43 个字符）
+    fn validate_id(&self, id: &str) -> Result<()> {
+        match URL_SAFE_NO_PAD.decode(id) {
+            Ok(bytes) if bytes.len() == 32 => Ok(()),
+            _ => Err(anyhow!(
+                "Invalid Arweave transaction ID: {:?} (expected 43 base64url characters)",
+                id
+            )),
+        }
+    }
+
+    /// 通过网关的 `/raw/` 接口读取路径清单本身，而不是清单解析后的内容
+    async fn list_directory(&self, id: &str) -> Result<Vec<String>> {
+        let url = format!("{}/raw/{}", self.gateway, id);
+        info!("🌐 Fetching path manifest from gateway: {}", url);
+
+        let manifest: PathManifest = retry_with_timeout(self.retry.clone(), || async {
+            let response = self.http.get(&url).send().await?;
+            if !response.status().is_success() {
+                return Err(HttpError::new(
+                    response.status(),
+                    response.headers(),
+                    format!("Gateway returned HTTP {}", response.status()),
+                )
+                .into());
+            }
+            Ok(response.json().await?)
+        })
+        .await?;
+
+        let mut names: Vec<String> = manifest
+            .paths
+            .into_keys()
+            .filter_map(|path| path.split('/').next().map(str::to_string))
+            .collect();
+        names.dedup();
+        Ok(names)
+    }
+}
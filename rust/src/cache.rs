@@ -0,0 +1,87 @@
+//! 图片文件夹 CID 缓存：内容未变化时复用上一次上传得到的 CID
+
+use crate::metadata::write_file_atomically;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// 缓存文件名，位于输出根目录下
+pub(crate) const CACHE_FILE: &str = ".pinata-cache.json";
+
+/// 文件夹内容的 SHA-256：按相对路径排序，依次哈希路径、长度和文件内容。
+/// 文件重命名、存储后端和 CID 版本都会改变上传结果，因此同样参与计算
+pub(crate) fn hash_directory(
+    dir: &Path,
+    files: &[PathBuf],
+    backend: &str,
+    cid_version: Option<u8>,
+) -> Result<String> {
+    let mut entries: Vec<(String, &PathBuf)> = files
+        .iter()
+        .map(|file| {
+            let relative = file.strip_prefix(dir).unwrap_or(file);
+            (relative.to_string_lossy().into_owned(), file)
+        })
+        .collect();
+    entries.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(backend.as_bytes());
+    hasher.update([0, cid_version.unwrap_or(u8::MAX)]);
+    for (relative, file) in entries {
+        let contents = fs::read(file)?;
+        hasher.update(relative.as_bytes());
+        hasher.update([0]);
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 单个文件内容的 SHA-256，`results.jsonl` 据此判断文件自上次上传后是否变化
+pub(crate) fn hash_file(path: &Path) -> Result<String> {
+    Ok(hash_bytes(&fs::read(path)?))
+}
+
+/// 十六进制的 SHA-256 摘要
+pub(crate) fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// `{内容哈希: CID}` 映射，保存在 `.pinata-cache.json` 中
+pub(crate) struct CidCache {
+    path: PathBuf,
+    entries: BTreeMap<String, String>,
+}
+
+impl CidCache {
+    /// 缓存文件不存在或已损坏时从空缓存开始
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("⚠️  Ignoring unreadable CID cache {:?}: {}", path, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        CidCache { path, entries }
+    }
+
+    pub(crate) fn get(&self, hash: &str) -> Option<&str> {
+        self.entries.get(hash).map(String::as_str)
+    }
+
+    pub(crate) fn insert(&mut self, hash: String, cid: String) -> Result<()> {
+        self.entries.insert(hash, cid);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_file_atomically(
+            &self.path,
+            serde_json::to_string_pretty(&self.entries)?.as_bytes(),
+        )
+    }
+}
@@ -0,0 +1,314 @@
+//! CAR（Content Addressable aRchive）文件：`upload-car` 上传前校验根 CID 与块的完整性，
+//! 打包本地文件夹时写出 CARv1（块由 `unixfs` 按 Kubo 的默认参数生成）
+
+use anyhow::{Context, Result, anyhow};
+use cid::Cid;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// multihash：sha2-256，校验块内容时只识别这一种
+const SHA2_256: u64 = 0x12;
+/// DAG-CBOR 中 CID 链接的标签
+const CBOR_TAG_CID: u64 = 42;
+/// 头部长度的合理上限，防止把非 CAR 文件的前几个字节当成巨大的长度
+const MAX_HEADER_SIZE: u64 = 1024 * 1024;
+/// CARv2 文件开头固定的 11 字节（等价于 `{"version": 2}` 的 CARv1 头部）
+const CARV2_PRAGMA: [u8; 11] = [
+    0x0a, 0xa1, 0x67, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x02,
+];
+
+/// 校验通过的 CAR 文件概要
+#[derive(Debug, Clone)]
+pub struct CarSummary {
+    pub root: String,
+    pub blocks: usize,
+    /// CAR 文件本身的字节数
+    pub size: u64,
+}
+
+/// 读取并校验 CAR 文件：头部必须只有一个根 CID，每个 sha2-256 块的内容与其 CID 一致，
+/// 并且根块确实包含在文件中；支持 CARv1 和 CARv2（读取其中的 CARv1 数据）
+pub fn inspect_car(path: &Path) -> Result<CarSummary> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let size = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    let mut pragma = [0u8; CARV2_PRAGMA.len()];
+    let is_v2 = reader.read_exact(&mut pragma).is_ok() && pragma == CARV2_PRAGMA;
+    let mut remaining = if is_v2 {
+        // CARv2 头部：16 字节特性位，随后是 CARv1 数据的偏移和长度（小端 u64）
+        let mut header = [0u8; 40];
+        reader.read_exact(&mut header)?;
+        let offset = u64::from_le_bytes(header[16..24].try_into()?);
+        let data_size = u64::from_le_bytes(header[24..32].try_into()?);
+        reader.seek(SeekFrom::Start(offset))?;
+        data_size
+    } else {
+        reader.seek(SeekFrom::Start(0))?;
+        size
+    };
+    let mut reader = reader.take(remaining);
+
+    let header_len = read_varint(&mut reader)?
+        .ok_or_else(|| anyhow!("{} is empty, not a CAR file", path.display()))?;
+    if header_len == 0 || header_len > MAX_HEADER_SIZE {
+        return Err(anyhow!("{} is not a CAR file", path.display()));
+    }
+    let mut header = vec![0u8; header_len as usize];
+    reader
+        .read_exact(&mut header)
+        .with_context(|| format!("{} is not a CAR file", path.display()))?;
+    let roots = parse_header(&header).with_context(|| {
+        format!(
+            "{} does not start with a valid CARv1 header",
+            path.display()
+        )
+    })?;
+    let root = match roots.as_slice() {
+        [root] => *root,
+        [] => return Err(anyhow!("{} has no root CID", path.display())),
+        _ => {
+            return Err(anyhow!(
+                "{} has {} root CIDs, only single-root CARs can be pinned",
+                path.display(),
+                roots.len()
+            ));
+        }
+    };
+    remaining = reader.limit();
+
+    let mut blocks = 0;
+    let mut seen_root = false;
+    let mut section = Vec::new();
+    while let Some(section_len) = read_varint(&mut reader)? {
+        if section_len > remaining {
+            return Err(anyhow!("{} is truncated", path.display()));
+        }
+        section.resize(section_len as usize, 0);
+        reader
+            .read_exact(&mut section)
+            .with_context(|| format!("{} is truncated", path.display()))?;
+        remaining = reader.limit();
+
+        let mut cursor = io::Cursor::new(section.as_slice());
+        let cid = Cid::read_bytes(&mut cursor)
+            .map_err(|e| anyhow!("Invalid block CID in {}: {}", path.display(), e))?;
+        let data = &section[cursor.position() as usize..];
+        if cid.hash().code() == SHA2_256 && Sha256::digest(data).as_slice() != cid.hash().digest() {
+            return Err(anyhow!(
+                "Block {} in {} does not match its CID (the file is corrupt)",
+                cid,
+                path.display()
+            ));
+        }
+        seen_root |= cid == root;
+        blocks += 1;
+    }
+
+    if !seen_root {
+        return Err(anyhow!(
+            "The root block {} is missing from {} (the CAR is incomplete)",
+            root,
+            path.display()
+        ));
+    }
+    Ok(CarSummary {
+        root: root.to_string(),
+        blocks,
+        size,
+    })
+}
+
+/// 写出以 `root` 为根的 CARv1；相同 CID 的块只写一次
+pub(crate) fn write_car(path: &Path, root: &[u8], blocks: &[(Vec<u8>, Vec<u8>)]) -> Result<()> {
+    // DAG-CBOR 的键按长度排序：{"roots": [CID], "version": 1}
+    let mut header = vec![0xa2];
+    cbor_text(&mut header, "roots");
+    header.push(0x81);
+    cbor_head(&mut header, 6, CBOR_TAG_CID);
+    // CID 链接的字节串以 0x00（identity multibase）开头
+    cbor_head(&mut header, 2, root.len() as u64 + 1);
+    header.push(0);
+    header.extend_from_slice(root);
+    cbor_text(&mut header, "version");
+    header.push(0x01);
+
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write_varint(&mut writer, header.len() as u64)?;
+    writer.write_all(&header)?;
+
+    let mut written = HashSet::new();
+    for (cid, data) in blocks {
+        if !written.insert(cid.as_slice()) {
+            continue;
+        }
+        write_varint(&mut writer, (cid.len() + data.len()) as u64)?;
+        writer.write_all(cid)?;
+        writer.write_all(data)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 从头部的 DAG-CBOR map 中取出 `roots`，并确认 `version` 为 1
+fn parse_header(header: &[u8]) -> Result<Vec<Cid>> {
+    let mut cbor = CborReader { bytes: header };
+    let (major, entries) = cbor.head()?;
+    if major != 5 {
+        return Err(anyhow!("the header is not a map"));
+    }
+    let (mut roots, mut version) = (None, None);
+    for _ in 0..entries {
+        let key = cbor.text()?;
+        match key {
+            "roots" => {
+                let (major, count) = cbor.head()?;
+                if major != 4 {
+                    return Err(anyhow!("roots is not an array"));
+                }
+                let mut cids = Vec::new();
+                for _ in 0..count {
+                    cids.push(cbor.cid()?);
+                }
+                roots = Some(cids);
+            }
+            "version" => version = Some(cbor.uint()?),
+            _ => cbor.skip()?,
+        }
+    }
+    match version {
+        Some(1) => roots.ok_or_else(|| anyhow!("the header has no roots")),
+        Some(other) => Err(anyhow!("unsupported CAR version {}", other)),
+        None => Err(anyhow!("the header has no version")),
+    }
+}
+
+/// 只覆盖 CAR 头部用到的 DAG-CBOR 子集（定长编码，不支持不定长）
+struct CborReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    fn take(&mut self, len: u64) -> Result<&'a [u8]> {
+        let len = usize::try_from(len)?;
+        if len > self.bytes.len() {
+            return Err(anyhow!("unexpected end of header"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// 返回主类型和参数（整数值、长度或条目数）
+    fn head(&mut self) -> Result<(u8, u64)> {
+        let initial = self.take(1)?[0];
+        let value = match initial & 0x1f {
+            n @ 0..=23 => u64::from(n),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into()?)),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into()?)),
+            27 => u64::from_be_bytes(self.take(8)?.try_into()?),
+            _ => return Err(anyhow!("indefinite-length CBOR is not valid DAG-CBOR")),
+        };
+        Ok((initial >> 5, value))
+    }
+
+    fn uint(&mut self) -> Result<u64> {
+        match self.head()? {
+            (0, value) => Ok(value),
+            _ => Err(anyhow!("expected an unsigned integer")),
+        }
+    }
+
+    fn text(&mut self) -> Result<&'a str> {
+        match self.head()? {
+            (3, len) => Ok(std::str::from_utf8(self.take(len)?)?),
+            _ => Err(anyhow!("expected a text key")),
+        }
+    }
+
+    fn cid(&mut self) -> Result<Cid> {
+        if self.head()? != (6, CBOR_TAG_CID) {
+            return Err(anyhow!("expected a CID link"));
+        }
+        let bytes = match self.head()? {
+            (2, len) => self.take(len)?,
+            _ => return Err(anyhow!("expected CID bytes")),
+        };
+        match bytes.split_first() {
+            Some((0, cid)) => Ok(Cid::try_from(cid)?),
+            _ => Err(anyhow!("CID link without the identity multibase prefix")),
+        }
+    }
+
+    fn skip(&mut self) -> Result<()> {
+        let (major, value) = self.head()?;
+        match major {
+            2 | 3 => {
+                self.take(value)?;
+            }
+            4 => (0..value).try_for_each(|_| self.skip())?,
+            5 => (0..value * 2).try_for_each(|_| self.skip())?,
+            6 => self.skip()?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn cbor_head(buf: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => buf.push(major | value as u8),
+        24..=0xff => buf.extend([major | 24, value as u8]),
+        0x100..=0xffff => {
+            buf.push(major | 25);
+            buf.extend((value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            buf.push(major | 26);
+            buf.extend((value as u32).to_be_bytes());
+        }
+        _ => {
+            buf.push(major | 27);
+            buf.extend(value.to_be_bytes());
+        }
+    }
+}
+
+fn cbor_text(buf: &mut Vec<u8>, text: &str) {
+    cbor_head(buf, 3, text.len() as u64);
+    buf.extend_from_slice(text.as_bytes());
+}
+
+/// 读取 unsigned varint；在第一个字节之前到达文件末尾时返回 `None`
+fn read_varint(reader: &mut impl Read) -> Result<Option<u64>> {
+    let mut value = 0u64;
+    for i in 0..10 {
+        let mut byte = [0u8];
+        if reader.read(&mut byte)? == 0 {
+            return match i {
+                0 => Ok(None),
+                _ => Err(anyhow!("unexpected end of file inside a varint")),
+            };
+        }
+        value |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(anyhow!("varint is too long"))
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    while value >= 0x80 {
+        writer.write_all(&[(value as u8) | 0x80])?;
+        value >>= 7;
+    }
+    writer.write_all(&[value as u8])
+}
@@ -0,0 +1,182 @@
+//! `--display-cid-version`：固定服务按自己的 CID 版本固定内容，写入元数据、结果文件和日志的 CID
+//! 统一换算成指定版本（CIDv1 使用 base32 `bafy...`）。两种版本指向同一个 multihash，网关都能解析
+
+use crate::config::{HttpOptions, RetryPolicy};
+use crate::storage::StorageBackend;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use cid::Cid;
+use pinata_sdk::RegionPolicy;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// multicodec：DAG-PB，CIDv0 只能表示这种编码的内容
+const DAG_PB: u64 = 0x70;
+/// multihash：sha2-256，CIDv0 只支持这一种哈希
+const SHA2_256: u64 = 0x12;
+
+/// 把 CID 换算成指定版本；CIDv1 的 raw 叶子等无法用 CIDv0 表示的内容返回错误
+pub fn convert_cid(cid: &str, version: u8) -> Result<String> {
+    let parsed = Cid::try_from(cid).map_err(|e| anyhow!("Cannot parse CID {}: {}", cid, e))?;
+    let converted = match version {
+        0 if parsed.codec() != DAG_PB || parsed.hash().code() != SHA2_256 => {
+            return Err(anyhow!(
+                "{} is not a DAG-PB / sha2-256 CID and has no CIDv0 form",
+                cid
+            ));
+        }
+        0 => Cid::new_v0(*parsed.hash())?,
+        1 => parsed.into_v1()?,
+        _ => return Err(anyhow!("Unsupported CID version {}", version)),
+    };
+    Ok(converted.to_string())
+}
+
+/// 两个 CID 是否指向同一内容（编码与 multihash 相同），不区分版本和 multibase
+pub(crate) fn same_content(a: &str, b: &str) -> bool {
+    match (Cid::try_from(a), Cid::try_from(b)) {
+        (Ok(a), Ok(b)) => a.codec() == b.codec() && a.hash() == b.hash(),
+        _ => a == b,
+    }
+}
+
+/// 按指定版本返回和展示 CID 的存储后端包装，上传本身不受影响
+pub struct DisplayCidVersion {
+    inner: Box<dyn StorageBackend>,
+    version: u8,
+}
+
+impl DisplayCidVersion {
+    pub fn new(inner: Box<dyn StorageBackend>, version: u8) -> Self {
+        DisplayCidVersion { inner, version }
+    }
+
+    /// 无法解析的标识（如 dry-run 的占位 CID）原样返回，无法换算的 CID 给出提示后原样返回
+    fn display(&self, cid: String) -> String {
+        if self.inner.is_dry_run() || Cid::try_from(cid.as_str()).is_err() {
+            return cid;
+        }
+        match convert_cid(&cid, self.version) {
+            Ok(converted) => converted,
+            Err(e) => {
+                warn!(
+                    "⚠️  Keeping CID {} as returned by {}: {:#}",
+                    cid,
+                    self.inner.name(),
+                    e
+                );
+                cid
+            }
+        }
+    }
+
+    /// 只换算 `<cid>/<path>` 开头的 CID，路径部分保持不变
+    fn display_id_path(&self, id_path: &str) -> String {
+        match id_path.split_once('/') {
+            Some((cid, path)) => format!("{}/{}", self.display(cid.to_string()), path),
+            None => self.display(id_path.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for DisplayCidVersion {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.inner.is_dry_run()
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.inner.retry_policy()
+    }
+
+    fn http_options(&self) -> &HttpOptions {
+        self.inner.http_options()
+    }
+
+    /// 固定时使用的版本，缓存和 `--local-cid` 按它计算
+    fn cid_version(&self) -> Option<u8> {
+        self.inner.cid_version()
+    }
+
+    fn group_id(&self) -> Option<&str> {
+        self.inner.group_id()
+    }
+
+    fn replication_regions(&self) -> &[RegionPolicy] {
+        self.inner.replication_regions()
+    }
+
+    async fn upload_file(&self, path: &Path) -> Result<String> {
+        let cid = self.inner.upload_file(path).await?;
+        Ok(self.display(cid))
+    }
+
+    async fn upload_directory(&self, dir: &Path, files: &[PathBuf]) -> Result<String> {
+        let cid = self.inner.upload_directory(dir, files).await?;
+        Ok(self.display(cid))
+    }
+
+    fn uri_scheme(&self) -> &'static str {
+        self.inner.uri_scheme()
+    }
+
+    /// `--images-cid`、`--resume` 复用的 CID 也按指定版本写入元数据
+    fn uri(&self, id_path: &str) -> String {
+        self.inner.uri(&self.display_id_path(id_path))
+    }
+
+    fn gateway_url(&self, id_path: &str) -> String {
+        self.inner.gateway_url(&self.display_id_path(id_path))
+    }
+
+    fn fetch_urls(&self, id_path: &str) -> Vec<String> {
+        self.inner.fetch_urls(&self.display_id_path(id_path))
+    }
+
+    fn validate_id(&self, id: &str) -> Result<()> {
+        self.inner.validate_id(id)
+    }
+
+    async fn list_directory(&self, id: &str) -> Result<Vec<String>> {
+        self.inner.list_directory(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 同一个空 UnixFS 文件夹的两种表示
+    const DIRECTORY_V0: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+    const DIRECTORY_V1: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+    /// CIDv1 raw 叶子（`hello world`），没有 CIDv0 形式
+    const RAW_LEAF: &str = "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e";
+
+    #[test]
+    fn converts_between_versions() {
+        assert_eq!(convert_cid(DIRECTORY_V0, 1).unwrap(), DIRECTORY_V1);
+        assert_eq!(convert_cid(DIRECTORY_V1, 0).unwrap(), DIRECTORY_V0);
+        assert_eq!(convert_cid(DIRECTORY_V0, 0).unwrap(), DIRECTORY_V0);
+        assert_eq!(convert_cid(RAW_LEAF, 1).unwrap(), RAW_LEAF);
+    }
+
+    #[test]
+    fn rejects_unconvertible_cids() {
+        assert!(convert_cid(RAW_LEAF, 0).is_err());
+        assert!(convert_cid(DIRECTORY_V0, 2).is_err());
+        assert!(convert_cid("not-a-cid", 1).is_err());
+    }
+
+    #[test]
+    fn same_content_ignores_the_version() {
+        assert!(same_content(DIRECTORY_V0, DIRECTORY_V1));
+        assert!(!same_content(DIRECTORY_V1, RAW_LEAF));
+        // 无法解析的标识（如 dry-run 的占位 CID）逐字比较
+        assert!(same_content("dry-run", "dry-run"));
+        assert!(!same_content("dry-run", DIRECTORY_V0));
+    }
+}
@@ -0,0 +1,127 @@
+//! `diff`：比较同一集合两次上传的元数据文件夹，按子 CID 找出新增、删除和内容变化的 token 文件
+
+use crate::cid_display::same_content;
+use crate::storage::StorageBackend;
+use crate::upload::{DirectoryEntry, fetch_directory_entries};
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// 摘要中每一类最多列出的文件名，完整列表用 `--json` 获取
+const MAX_SUMMARY_ENTRIES: usize = 20;
+
+/// 两个目录中同名但内容不同的条目
+#[derive(Serialize, Debug, Clone)]
+pub struct ChangedEntry {
+    pub name: String,
+    pub cid_a: String,
+    pub cid_b: String,
+}
+
+/// `diff` 的结果（`--json` 时原样输出），条目均按名称排序
+#[derive(Serialize, Debug, Clone)]
+pub struct DirectoryDiff {
+    pub cid_a: String,
+    pub cid_b: String,
+    /// 只在 `cid_b` 中出现的条目
+    pub added: Vec<DirectoryEntry>,
+    /// 只在 `cid_a` 中出现的条目
+    pub removed: Vec<DirectoryEntry>,
+    pub changed: Vec<ChangedEntry>,
+    pub unchanged: usize,
+}
+
+impl DirectoryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// 输出简洁的摘要，每类最多列出 `MAX_SUMMARY_ENTRIES` 个文件名
+    pub fn log_summary(&self) {
+        info!("🔀 {} -> {}", self.cid_a, self.cid_b);
+        if self.is_empty() {
+            info!(
+                "✅ No differences: all {} entries have the same content",
+                self.unchanged
+            );
+            return;
+        }
+        log_names("➕ Added", self.added.iter().map(|e| e.name.as_str()));
+        log_names("➖ Removed", self.removed.iter().map(|e| e.name.as_str()));
+        log_names("✏️  Changed", self.changed.iter().map(|e| e.name.as_str()));
+        info!(
+            "📊 {} added, {} removed, {} changed, {} unchanged",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len(),
+            self.unchanged
+        );
+    }
+}
+
+fn log_names<'a>(label: &str, names: impl ExactSizeIterator<Item = &'a str>) {
+    let total = names.len();
+    if total == 0 {
+        return;
+    }
+    let shown = names
+        .take(MAX_SUMMARY_ENTRIES)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let more = match total.saturating_sub(MAX_SUMMARY_ENTRIES) {
+        0 => String::new(),
+        n => format!(" and {} more", n),
+    };
+    info!("{} ({}): {}{}", label, total, shown, more);
+}
+
+/// 通过网关读取两个目录 CID 的条目并比较；CIDv0 与 CIDv1 指向同一内容时视为未变化
+pub async fn diff_directories(
+    api: &dyn StorageBackend,
+    cid_a: &str,
+    cid_b: &str,
+) -> Result<DirectoryDiff> {
+    if api.cid_version().is_none() {
+        return Err(anyhow!(
+            "❌ diff compares IPFS folder CIDs, {} does not use CIDs",
+            api.name()
+        ));
+    }
+    api.validate_id(cid_a)?;
+    api.validate_id(cid_b)?;
+
+    let (entries_a, entries_b) = futures::try_join!(
+        fetch_directory_entries(api, cid_a),
+        fetch_directory_entries(api, cid_b)
+    )?;
+    let mut remaining: BTreeMap<String, String> = entries_a
+        .into_iter()
+        .map(|entry| (entry.name, entry.cid))
+        .collect();
+
+    let mut diff = DirectoryDiff {
+        cid_a: cid_a.to_string(),
+        cid_b: cid_b.to_string(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+        unchanged: 0,
+    };
+    for entry in entries_b {
+        match remaining.remove(&entry.name) {
+            None => diff.added.push(entry),
+            Some(old) if same_content(&old, &entry.cid) => diff.unchanged += 1,
+            Some(old) => diff.changed.push(ChangedEntry {
+                name: entry.name,
+                cid_a: old,
+                cid_b: entry.cid,
+            }),
+        }
+    }
+    diff.removed = remaining
+        .into_iter()
+        .map(|(name, cid)| DirectoryEntry { name, cid })
+        .collect();
+    Ok(diff)
+}
@@ -0,0 +1,157 @@
+//! 上传与 API 请求失败时的类型化错误。
+//!
+//! `retry_with_timeout` 在放弃重试时把错误归类为 [`UploadError`]，原始错误作为 `source` 保留，
+//! 库的调用方可以通过 `downcast_ref::<UploadError>()` 区分认证、超时、限流、网络与输入错误；
+//! 失败预算用尽的 [`BudgetExhausted`] 不属于 `UploadError`，需要单独判断
+
+use crate::pinata::HttpError;
+use reqwest::StatusCode;
+
+/// 暂时性失败的累计次数超过 `--max-total-failures`，整次运行中止（服务很可能整体不可用）
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "{failures} of {attempts} request attempts failed with transient errors, exceeding --max-total-failures {limit}; aborting the run, the storage service looks unavailable"
+)]
+pub struct BudgetExhausted {
+    pub attempts: usize,
+    pub failures: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    /// 401 / 403：JWT、API Key 或钱包无效，或者没有权限
+    #[error("authentication failed")]
+    AuthFailed(#[source] anyhow::Error),
+    /// 单次请求超过 `upload_timeout_seconds`，或服务端返回 408
+    #[error("request timed out")]
+    Timeout(#[source] anyhow::Error),
+    /// 429：重试次数用尽后仍被限流
+    #[error("rate limited")]
+    RateLimited(#[source] anyhow::Error),
+    /// 连接失败、请求中断或服务端错误（5xx）
+    #[error("network error")]
+    Network(#[source] anyhow::Error),
+    /// 请求被拒绝（其他 4xx）、本地文件或参数有误、响应无法解析，重试也不会成功
+    #[error("invalid input")]
+    InvalidInput(#[source] anyhow::Error),
+}
+
+impl UploadError {
+    /// 超时、限流和网络错误值得重试
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            UploadError::Timeout(_) | UploadError::RateLimited(_) | UploadError::Network(_)
+        )
+    }
+
+    /// 按错误链中最先出现的 `HttpError`、`reqwest::Error` 或超时归类，都没有时视为输入错误
+    pub fn classify(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<UploadError>() {
+            Ok(classified) => return classified,
+            Err(error) => error,
+        };
+        for cause in error.chain() {
+            if let Some(http) = cause.downcast_ref::<HttpError>() {
+                return match http.status {
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                        UploadError::AuthFailed(error)
+                    }
+                    StatusCode::TOO_MANY_REQUESTS => UploadError::RateLimited(error),
+                    StatusCode::REQUEST_TIMEOUT => UploadError::Timeout(error),
+                    status if status.is_server_error() => UploadError::Network(error),
+                    _ => UploadError::InvalidInput(error),
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                if e.is_timeout() {
+                    return UploadError::Timeout(error);
+                }
+                if e.is_connect() || e.is_request() || e.is_body() {
+                    return UploadError::Network(error);
+                }
+            }
+            if cause.is::<tokio::time::error::Elapsed>() {
+                return UploadError::Timeout(error);
+            }
+        }
+        UploadError::InvalidInput(error)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use reqwest::header::HeaderMap;
+
+    pub(crate) fn http_error(status: u16) -> anyhow::Error {
+        let status = StatusCode::from_u16(status).unwrap();
+        anyhow::Error::from(HttpError::new(
+            status,
+            &HeaderMap::new(),
+            format!("HTTP {}", status),
+        ))
+        .context("Upload of 1.png failed")
+    }
+
+    #[test]
+    fn unauthorized_is_permanent() {
+        let error = UploadError::classify(http_error(401));
+        assert!(matches!(error, UploadError::AuthFailed(_)), "{:?}", error);
+        assert!(!error.is_transient());
+    }
+
+    #[test]
+    fn service_unavailable_is_transient() {
+        let error = UploadError::classify(http_error(503));
+        assert!(matches!(error, UploadError::Network(_)), "{:?}", error);
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn classifies_other_statuses() {
+        let cases = [
+            (403, "AuthFailed"),
+            (429, "RateLimited"),
+            (408, "Timeout"),
+            (500, "Network"),
+            (400, "InvalidInput"),
+            (404, "InvalidInput"),
+        ];
+        for (status, expected) in cases {
+            let error = UploadError::classify(http_error(status));
+            assert!(
+                format!("{:?}", error).starts_with(expected),
+                "{}: {:?}",
+                status,
+                error
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn elapsed_timeouts_are_transient() {
+        let elapsed = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        let error = UploadError::classify(anyhow::Error::from(elapsed).context("Upload timed out"));
+        assert!(matches!(error, UploadError::Timeout(_)), "{:?}", error);
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn unrecognised_errors_are_invalid_input() {
+        let error = UploadError::classify(anyhow::anyhow!("metadata/1.json is not valid JSON"));
+        assert!(matches!(error, UploadError::InvalidInput(_)), "{:?}", error);
+        assert!(!error.is_transient());
+    }
+
+    #[test]
+    fn classified_errors_are_kept() {
+        let error = UploadError::classify(anyhow::Error::from(UploadError::RateLimited(
+            http_error(429),
+        )));
+        assert!(matches!(error, UploadError::RateLimited(_)), "{:?}", error);
+    }
+}
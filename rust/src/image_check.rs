@@ -0,0 +1,147 @@
+//! 上传前的图片检查：逐个解码图片，拒绝损坏的文件，并按需限制尺寸与宽高比。
+//! 依赖 `image` crate，由默认开启的 `image-checks` feature 控制，`--no-default-features` 构建时不可用
+
+use anyhow::{Result, anyhow};
+#[cfg(feature = "image-checks")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "image-checks")]
+use tracing::{error, info, warn};
+
+/// `--check-images` / `--min-dim` / `--max-dim` / `--require-square` 指定的检查规则
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageRules {
+    /// 宽和高都不能小于该值（像素）
+    pub min_dim: Option<u32>,
+    /// 宽和高都不能大于该值（像素）
+    pub max_dim: Option<u32>,
+    /// 宽高必须相等
+    pub require_square: bool,
+}
+
+#[cfg(feature = "image-checks")]
+impl ImageRules {
+    fn violation(&self, width: u32, height: u32) -> Option<String> {
+        if let Some(min_dim) = self.min_dim
+            && width.min(height) < min_dim
+        {
+            return Some(format!(
+                "{}x{} is smaller than --min-dim {}",
+                width, height, min_dim
+            ));
+        }
+        if let Some(max_dim) = self.max_dim
+            && width.max(height) > max_dim
+        {
+            return Some(format!(
+                "{}x{} is larger than --max-dim {}",
+                width, height, max_dim
+            ));
+        }
+        if self.require_square && width != height {
+            return Some(format!("{}x{} is not square", width, height));
+        }
+        None
+    }
+}
+
+/// AVIF 解码需要原生的 dav1d 库，这里只处理纯 Rust 实现支持的格式
+#[cfg(feature = "image-checks")]
+fn is_decodable(path: &Path) -> bool {
+    use image::ImageFormat;
+
+    matches!(
+        ImageFormat::from_path(path),
+        Ok(ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP)
+    )
+}
+
+/// 只读取文件头中的宽高，不解码像素，生成元数据时使用；无法处理的格式（SVG、AVIF 等）返回 `None`
+#[cfg(feature = "image-checks")]
+pub(crate) fn read_dimensions(path: &Path) -> Result<Option<(u32, u32)>> {
+    use image::ImageReader;
+
+    if !is_decodable(path) {
+        return Ok(None);
+    }
+    let dimensions = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_dimensions()
+        .map_err(|e| anyhow!("cannot read the dimensions of {}: {}", path.display(), e))?;
+    Ok(Some(dimensions))
+}
+
+#[cfg(not(feature = "image-checks"))]
+pub(crate) fn read_dimensions(_path: &std::path::Path) -> Result<Option<(u32, u32)>> {
+    Err(not_compiled())
+}
+
+/// 完整解码图片并返回宽高，损坏的文件返回错误；无法解码的格式返回 `None`
+#[cfg(feature = "image-checks")]
+fn image_dimensions(path: &Path) -> Result<Option<(u32, u32)>> {
+    use image::{GenericImageView, ImageReader};
+
+    if !is_decodable(path) {
+        return Ok(None);
+    }
+    let image = ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| anyhow!("cannot be decoded: {}", e))?;
+    Ok(Some(image.dimensions()))
+}
+
+#[cfg(not(feature = "image-checks"))]
+fn not_compiled() -> anyhow::Error {
+    anyhow!(
+        "Image checks and --embed-image-info are not available: this binary was built without the image-checks feature"
+    )
+}
+
+/// 在上传前检查所有图片，列出每个不合格的文件后返回错误，不会固定任何内容
+#[cfg(feature = "image-checks")]
+pub(crate) fn check_images(image_files: &[PathBuf], rules: &ImageRules) -> Result<()> {
+    let mut invalid_files = 0;
+    let mut skipped = Vec::new();
+    for path in image_files {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let problem = match image_dimensions(path) {
+            Ok(Some((width, height))) => rules.violation(width, height),
+            Ok(None) => {
+                skipped.push(file_name.into_owned());
+                continue;
+            }
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(problem) = problem {
+            invalid_files += 1;
+            error!("❌ {}: {}", file_name, problem);
+        }
+    }
+
+    if !skipped.is_empty() {
+        skipped.sort();
+        warn!(
+            "⚠️  Cannot decode {} images (only PNG, JPEG, GIF and WebP are checked), uploading them unchecked: {}",
+            skipped.len(),
+            skipped.join(", ")
+        );
+    }
+    if invalid_files > 0 {
+        return Err(anyhow!(
+            "❌ {} of {} images failed the image checks",
+            invalid_files,
+            image_files.len()
+        ));
+    }
+    info!(
+        "✅ {} images decoded and passed the image checks",
+        image_files.len() - skipped.len()
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "image-checks"))]
+pub(crate) fn check_images(_image_files: &[PathBuf], _rules: &ImageRules) -> Result<()> {
+    Err(not_compiled())
+}
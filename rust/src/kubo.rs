@@ -0,0 +1,233 @@
+//! 本地 IPFS 节点存储后端：通过 Kubo（`ipfs daemon`）的 HTTP API 添加文件，不消耗 Pinata 额度
+
+use crate::config::{HttpOptions, KUBO_GATEWAY_URL, RetryPolicy};
+use crate::pinata::{HttpError, validate_cid};
+use crate::storage::{StorageBackend, gateway_urls};
+use crate::upload::fetch_directory_listing;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// 创建 `KuboClient` 时的可配置项
+#[derive(Debug, Clone)]
+pub struct KuboOptions {
+    pub retry: RetryPolicy,
+    pub http: HttpOptions,
+    /// Kubo RPC API 地址，例如 `http://127.0.0.1:5001`
+    pub api_url: String,
+    /// 生成链接和 `--verify` 回读使用的 IPFS 网关（包含 `/ipfs` 路径），默认为本地节点的网关
+    pub gateway: Option<String>,
+    /// 读取内容时在 `gateway` 之后依次尝试的网关
+    pub fallback_gateways: Vec<String>,
+    pub cid_version: u8,
+}
+
+/// `/api/v0/add` 每添加一个文件或文件夹输出一行 JSON
+#[derive(Deserialize)]
+struct AddedEntry {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+pub struct KuboClient {
+    client: reqwest::Client,
+    /// dry-run 模式下所有上传都会被跳过并返回占位 CID
+    dry_run: bool,
+    retry: RetryPolicy,
+    http_options: HttpOptions,
+    /// API 与网关地址，均不带末尾的 `/`
+    api_url: String,
+    gateway: String,
+    fallback_gateways: Vec<String>,
+    cid_version: u8,
+}
+
+impl KuboClient {
+    /// 创建客户端并确认节点可以访问
+    pub async fn connect(options: KuboOptions) -> Result<Self> {
+        let client = Self::with_options(false, options);
+        let version = client.version().await?;
+        info!(
+            "✅ Connected to IPFS node at {} (Kubo {})",
+            client.api_url, version
+        );
+        Ok(client)
+    }
+
+    /// 不访问节点的客户端，只用于 dry-run
+    pub fn dry_run(options: KuboOptions) -> Self {
+        Self::with_options(true, options)
+    }
+
+    fn with_options(dry_run: bool, options: KuboOptions) -> Self {
+        KuboClient {
+            client: options.http.client(),
+            dry_run,
+            retry: options.retry,
+            http_options: options.http,
+            api_url: options.api_url.trim_end_matches('/').to_string(),
+            gateway: options
+                .gateway
+                .as_deref()
+                .unwrap_or(KUBO_GATEWAY_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            fallback_gateways: options
+                .fallback_gateways
+                .iter()
+                .map(|gateway| gateway.trim_end_matches('/').to_string())
+                .collect(),
+            cid_version: options.cid_version,
+        }
+    }
+
+    /// Kubo RPC API 只接受 POST；连接失败时提示检查 API 地址和 `ipfs daemon`
+    async fn post(&self, path: &str, query: &[(&str, &str)], form: Option<Form>) -> Result<String> {
+        let url = format!("{}/api/v0/{}", self.api_url, path);
+        let mut request = self.client.post(&url).query(query);
+        if let Some(form) = form {
+            request = request.multipart(form);
+        }
+        let response = request.send().await.map_err(|e| {
+            if e.is_connect() {
+                anyhow!(e).context(format!(
+                    "Cannot reach the IPFS node API at {} (is `ipfs daemon` running? set --ipfs-api to change the address)",
+                    self.api_url
+                ))
+            } else {
+                e.into()
+            }
+        })?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        if !status.is_success() {
+            // 错误响应：`{"Message": "...", "Code": 0, "Type": "error"}`
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|json| json["Message"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            return Err(HttpError::new(
+                status,
+                &headers,
+                format!("IPFS node returned HTTP {}: {}", status, message),
+            )
+            .into());
+        }
+        Ok(body)
+    }
+
+    async fn version(&self) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Version {
+            #[serde(rename = "Version")]
+            version: String,
+        }
+        let body = self.post("version", &[], None).await?;
+        Ok(serde_json::from_str::<Version>(&body)?.version)
+    }
+
+    /// 添加 multipart 中的文件并固定；`wrap` 为 true 时所有文件被包装进一个文件夹，返回文件夹 CID
+    async fn add(&self, form: Form, wrap: bool) -> Result<String> {
+        let cid_version = self.cid_version.to_string();
+        let body = self
+            .post(
+                "add",
+                &[
+                    ("pin", "true"),
+                    ("cid-version", &cid_version),
+                    ("wrap-with-directory", if wrap { "true" } else { "false" }),
+                ],
+                Some(form),
+            )
+            .await?;
+
+        let entries = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<AddedEntry>)
+            .collect::<Result<Vec<_>, _>>()?;
+        // 包装文件夹的条目名为空，且总是最后输出
+        let root = if wrap {
+            entries.iter().rev().find(|entry| entry.name.is_empty())
+        } else {
+            entries.last()
+        };
+        root.map(|entry| entry.hash.clone())
+            .ok_or_else(|| anyhow!("IPFS node did not return a CID for the added content"))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for KuboClient {
+    fn name(&self) -> &'static str {
+        "Kubo"
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry.clone()
+    }
+
+    fn http_options(&self) -> &HttpOptions {
+        &self.http_options
+    }
+
+    fn cid_version(&self) -> Option<u8> {
+        Some(self.cid_version)
+    }
+
+    async fn upload_file(&self, path: &Path) -> Result<String> {
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid file path"))?
+            .to_string();
+        let form = Form::new().part("file", Part::bytes(fs::read(path)?).file_name(file_name));
+        self.add(form, false).await
+    }
+
+    /// 文件以相对路径命名并包装进一个文件夹，文件夹 CID 的第一层就是 `dir` 的内容
+    async fn upload_directory(&self, dir: &Path, files: &[PathBuf]) -> Result<String> {
+        let mut form = Form::new();
+        for path in files {
+            let relative_path = path.strip_prefix(dir)?;
+            form = form.part(
+                "file",
+                Part::bytes(fs::read(path)?)
+                    .file_name(relative_path.to_string_lossy().into_owned()),
+            );
+        }
+        self.add(form, true).await
+    }
+
+    fn uri_scheme(&self) -> &'static str {
+        "ipfs"
+    }
+
+    fn gateway_url(&self, cid_path: &str) -> String {
+        format!("{}/{}", self.gateway, cid_path)
+    }
+
+    fn fetch_urls(&self, cid_path: &str) -> Vec<String> {
+        gateway_urls(&self.gateway, &self.fallback_gateways, cid_path)
+    }
+
+    fn validate_id(&self, cid: &str) -> Result<()> {
+        validate_cid(cid)
+    }
+
+    async fn list_directory(&self, cid: &str) -> Result<Vec<String>> {
+        fetch_directory_listing(self, cid).await
+    }
+}
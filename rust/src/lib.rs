@@ -0,0 +1,62 @@
+//! NFT 元数据生成与上传（Pinata / Arweave / NFT.Storage / 本地 IPFS 节点）的核心逻辑。
+//!
+//! 二进制入口（`main.rs`）只负责解析参数、初始化日志并输出结果摘要，
+//! `process_batch_collection` / `process_single_file` 等工作流也可以作为库直接调用。
+
+pub mod arweave;
+pub mod cache;
+pub mod car;
+pub mod cid_display;
+pub mod cli;
+pub mod config;
+pub mod diff;
+pub mod error;
+pub mod image_check;
+pub mod kubo;
+pub mod metadata;
+pub mod nft_storage;
+pub mod optimize;
+pub mod pinata;
+pub mod results;
+pub mod schema;
+pub mod storage;
+pub mod telemetry;
+pub mod thumbnail;
+pub mod unixfs;
+pub mod upload;
+pub mod workflow;
+
+pub use arweave::{ArweaveClient, ArweaveOptions, ArweaveWallet};
+pub use car::{CarSummary, inspect_car};
+pub use cid_display::{DisplayCidVersion, convert_cid};
+pub use cli::{Cli, Commands, ListSort, LogFormat, QueueStatus, TokenIdSource, parse_key_value};
+pub use config::{
+    Config, HttpOptions, RequestRateLimiter, RetryBudget, RetryPolicy, fallback_gateways,
+    format_size,
+};
+pub use diff::{ChangedEntry, DirectoryDiff, diff_directories};
+pub use error::{BudgetExhausted, UploadError};
+pub use image_check::ImageRules;
+pub use kubo::{KuboClient, KuboOptions};
+pub use metadata::{
+    Attribute, AttributeFilter, ContractMetadata, DisplayType, MetadataOptions, NftMetadata,
+    TokenIds, TokenOverride, load_metadata_csv,
+};
+pub use nft_storage::{NftStorageClient, NftStorageOptions};
+pub use pinata::{
+    ClientOptions, HttpError, PinLabels, PinataAuth, PinataClient, check_replication_regions,
+};
+pub use results::{
+    BatchRecord, BatchResult, FailedUpload, PerFileRecord, RunRecord, SingleRecord, SingleResult,
+    Throughput, TokenUploadResult, UploadKind, UploadLogEntry, base_uri,
+};
+pub use schema::MetadataSchema;
+pub use storage::{Backend, StorageBackend};
+pub use telemetry::{Telemetry, otel_layer};
+pub use upload::{Cancelled, DirectoryEntry, ProgressLogWriter, cancellable, progress_container};
+pub use workflow::{
+    BatchOptions, FileList, SingleOptions, UnpinTargets, UploadConfirmation, WorkingDirs,
+    Workspace, check_pin_queue, list_pins, pin_by_hash, process_batch_collection,
+    process_single_file, run_smoke_test, show_metadata, show_usage, unpin_batch, unpin_by_hash,
+    upload_car, upload_contract_metadata, upload_dir, verify_file,
+};
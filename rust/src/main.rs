@@ -1,684 +1,768 @@
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Result, anyhow};
 use chrono::Utc;
-use clap::{Parser, Subcommand};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use dotenvy::dotenv;
-use pinata_sdk::{PinByFile, PinataApi};
-use serde::{Deserialize, Serialize};
-use std::env;
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::{Path, PathBuf};
-use std::time::Duration;
-use tokio::time::timeout;
-use tokio_retry::Retry;
-use tokio_retry::strategy::{ExponentialBackoff, jitter};
-use tracing::{Level, error, info, warn};
-use tracing_subscriber;
-
-// --- 配置 ---
-const MAX_RETRIES: usize = 3;
-const RETRY_DELAY_MS: u64 = 5000;
-const UPLOAD_TIMEOUT_SECONDS: u64 = 300; // 5分钟超时
-
-// --- 文件格式配置 ---
-const METADATA_FILE_SUFFIX: &str = ""; // 默认不带后缀，符合标准NFT格式
-const SUPPORTED_METADATA_FORMATS: [&str; 4] = ["", ".json", ".yaml", ".yml"]; // 支持的格式列表，包括空字符串
-
-// --- 获取配置的函数 ---
-fn get_metadata_file_suffix() -> String {
-    // 优先从环境变量读取
-    if let Ok(suffix) = env::var("METADATA_FILE_SUFFIX") {
-        // 验证格式是否支持
-        if SUPPORTED_METADATA_FORMATS.contains(&suffix.as_str()) {
-            return suffix;
-        } else {
-            warn!(
-                "⚠️  Unsupported metadata format: {}, using default: {}",
-                suffix, METADATA_FILE_SUFFIX
+use rust::{
+    ArweaveClient, ArweaveOptions, ArweaveWallet, AttributeFilter, Backend, BatchOptions,
+    BatchResult, BudgetExhausted, Cancelled, Cli, ClientOptions, Commands, Config,
+    ContractMetadata, DisplayCidVersion, FileList, HttpOptions, ImageRules, KuboClient,
+    KuboOptions, LogFormat, MetadataOptions, MetadataSchema, NftStorageClient, NftStorageOptions,
+    PinLabels, PinataAuth, PinataClient, ProgressLogWriter, RequestRateLimiter, RetryBudget,
+    RunRecord, SingleOptions, SingleResult, StorageBackend, TokenIdSource, TokenIds, UnpinTargets,
+    UploadConfirmation, UploadError, WorkingDirs, Workspace, base_uri, cancellable,
+    check_pin_queue, check_replication_regions, diff_directories, fallback_gateways, format_size,
+    list_pins, load_metadata_csv, otel_layer, pin_by_hash, process_batch_collection,
+    process_single_file, progress_container, run_smoke_test, show_metadata, show_usage,
+    unpin_batch, unpin_by_hash, upload_car, upload_contract_metadata, upload_dir, verify_file,
+};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+
+fn report_batch_result(storage: &dyn StorageBackend, result: &BatchResult, trailing_slash: bool) {
+    if !result.failed.is_empty() {
+        error!(
+            "❌ {} files failed to upload (--continue-on-error):",
+            result.failed.len()
+        );
+        for failed in &result.failed {
+            error!(
+                "   {} (token {}): {}",
+                failed.file, failed.token_id, failed.error
             );
         }
+        error!(
+            "❌ Rerun with --resume {:?} to retry only the failed files",
+            result.output_dir
+        );
     }
-
-    // 如果没有环境变量或格式不支持，使用默认值
-    METADATA_FILE_SUFFIX.to_string()
-}
-
-// --- 数据结构 ---
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct Attribute {
-    trait_type: String,
-    value: serde_json::Value,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct NftMetadata {
-    name: String,
-    description: String,
-    image: String,
-    attributes: Vec<Attribute>,
-}
-
-// --- 命令行接口定义 ---
-#[derive(Parser, Debug)]
-#[command(author, version, about = "A production-grade NFT metadata upload tool (Rust version)", long_about = None)]
-struct Cli {
-    #[command(subcommand)]
-    command: Commands,
-}
-
-#[derive(Subcommand, Debug)]
-enum Commands {
-    /// Batch processing mode
-    #[command(name = "batch")]
-    Batch {
-        /// Generate both versions (with and without suffix)
-        #[arg(long)]
-        both_versions: bool,
-    },
-    /// Single file processing mode
-    #[command(name = "single")]
-    Single {
-        /// Token ID for the NFT
-        #[arg(long)]
-        token_id: Option<u64>,
-    },
-    /// Test mode
-    #[command(name = "test")]
-    Test,
-    /// Pin file by CID
-    #[command(name = "pin")]
-    Pin {
-        #[arg(required = true)]
-        cid: String,
-    },
-    /// Check pin queue status
-    #[command(name = "queue")]
-    Queue,
-}
-
-// --- 核心上传函数 (带重试和超时) ---
-async fn upload_directory_with_retry(api: &PinataApi, dir_path: &Path) -> Result<String> {
-    let retry_strategy = ExponentialBackoff::from_millis(RETRY_DELAY_MS)
-        .map(jitter)
-        .take(MAX_RETRIES);
-    info!(
-        "🔄 Starting upload with retry mechanism (max {} attempts)",
-        MAX_RETRIES
-    );
-    let result = Retry::spawn(retry_strategy, || async {
-        let upload_future = upload_directory_to_pinata(api, dir_path);
-        timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECONDS), upload_future).await?
-    })
-    .await;
-    match result {
-        Ok(cid) => {
-            info!("✅ Upload completed successfully after retries");
-            Ok(cid)
+    if let Some(bytes_saved) = result.bytes_saved {
+        info!(
+            "🗜️  --optimize-images saved {} bytes of pinned storage and gateway bandwidth",
+            bytes_saved
+        );
+    }
+    if let Some(throughput) = &result.throughput {
+        info!(
+            "📊 Uploaded {} ({} files) in {:.1}s, effective {:.2} MB/s",
+            format_size(throughput.bytes_uploaded),
+            throughput.files_uploaded,
+            throughput.elapsed_seconds,
+            throughput.mb_per_second
+        );
+    }
+    if result.cancelled {
+        info!("\n--- 🛑 Batch cancelled ---");
+        if let Some(cid) = &result.images_cid {
+            info!("Images folder was uploaded: {}", storage.uri(cid));
         }
-        Err(e) => {
-            error!("❌ Upload failed after {} attempts: {}", MAX_RETRIES, e);
-            Err(e)
+        if !result.tokens.is_empty() {
+            info!(
+                "{} images were uploaded before cancelling",
+                result.tokens.len()
+            );
         }
+        info!("Partial results saved to {:?}", result.results_file());
+        return;
     }
-}
-
-async fn upload_directory_to_pinata(api: &PinataApi, dir_path: &Path) -> Result<String> {
-    let path_str = dir_path
-        .to_str()
-        .ok_or_else(|| anyhow!("Invalid folder path"))?;
-
-    let upload_start = std::time::Instant::now();
-    info!("--- Uploading folder to Pinata: {} ---", path_str);
-    info!(
-        "⏱️  Upload started at: {}",
-        chrono::Utc::now().format("%H:%M:%S")
-    );
-
-    let pin_obj = PinByFile::new(path_str);
-    let res = api
-        .pin_file(pin_obj)
-        .await
-        .map_err(|e| anyhow!("Upload failed: {}", e))?;
-
-    let upload_duration = upload_start.elapsed();
-    let cid = res.ipfs_hash;
-
-    info!("✅ Folder uploaded successfully! CID: {}", cid);
-    info!(
-        "⏱️  Upload completed in: {:.2} seconds",
-        upload_duration.as_secs_f64()
-    );
-
-    Ok(cid)
-}
-
-async fn upload_single_file_to_pinata(api: &PinataApi, file_path: &Path) -> Result<String> {
-    let path_str = file_path
-        .to_str()
-        .ok_or_else(|| anyhow!("Invalid file path"))?;
-
-    let upload_start = std::time::Instant::now();
-    let file_size = fs::metadata(file_path)?.len();
-    let file_size_mb = file_size as f64 / 1024.0 / 1024.0;
-
-    info!("--- Uploading single file to Pinata: {} ---", path_str);
-    info!(
-        "⏱️  Upload started at: {}",
-        chrono::Utc::now().format("%H:%M:%S")
-    );
-    info!("📁 File size: {:.2} MB", file_size_mb);
-
-    let pin_obj = PinByFile::new(path_str);
-    let res = api
-        .pin_file(pin_obj)
-        .await
-        .map_err(|e| anyhow!("Upload failed: {}", e))?;
-
-    let upload_duration = upload_start.elapsed();
-    let upload_speed = file_size_mb / upload_duration.as_secs_f64();
-    let cid = res.ipfs_hash;
-
-    info!("✅ File uploaded successfully! CID: {}", cid);
-    info!(
-        "⏱️  Upload completed in: {:.2} seconds",
-        upload_duration.as_secs_f64()
-    );
-    info!("📊 Upload speed: {:.2} MB/s", upload_speed);
-
-    Ok(cid)
-}
-
-// --- 工作流 ---
-async fn process_batch_collection(api: &PinataApi, generate_both_versions: bool) -> Result<()> {
-    info!("==============================================");
-    info!("🚀 Starting batch NFT collection processing (Pinata)...");
-    info!("==============================================");
-
-    let assets_dir = PathBuf::from("assets");
-    let images_input_dir = assets_dir.join("batch_images");
-    if !images_input_dir.exists() {
-        return Err(anyhow!(
-            "❌ Input directory does not exist: {:?}",
-            images_input_dir
-        ));
+    if result.failed.is_empty() {
+        info!("\n--- ✨ Batch process completed ✨ ---");
+    } else {
+        warn!("\n--- ⚠️  Batch process completed with errors ---");
     }
-
-    let images_folder_cid = upload_directory_with_retry(api, &images_input_dir).await?;
-    info!("\n🖼️  Images folder CID obtained: {}", images_folder_cid);
-
-    let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S-%3fZ").to_string();
-    let output_dir = PathBuf::from("output").join(format!("batch-upload-{}", timestamp));
-    let results_dir = output_dir.join("results");
-    fs::create_dir_all(&results_dir)?;
-
-    let image_files: Vec<PathBuf> = fs::read_dir(&images_input_dir)?
-        .filter_map(Result::ok)
-        .map(|e| e.path())
-        .filter(|p| p.is_file())
-        .collect();
-
-    let (metadata_with_suffix_cid, metadata_without_suffix_cid, metadata_dir) =
-        if generate_both_versions {
-            let (cid_with, cid_without, dir) =
-                generate_and_upload_both_versions(api, &image_files, &images_folder_cid).await?;
-            (Some(cid_with), Some(cid_without), Some(dir))
-        } else {
-            // 单版本生成时，根据环境变量决定是否带后缀
-            let should_use_suffix = !get_metadata_file_suffix().is_empty();
-            let (cid, dir) = generate_and_upload_single_version(
-                api,
-                &image_files,
-                &images_folder_cid,
-                should_use_suffix,
-            )
-            .await?;
-            (None, Some(cid), Some(dir))
-        };
-
-    save_batch_results(
-        &output_dir,
-        &images_folder_cid,
-        metadata_with_suffix_cid.as_deref(),
-        metadata_without_suffix_cid.as_deref(),
-        image_files.len(),
-        metadata_dir.as_deref(),
-    )
-    .await?;
-
-    info!("\n--- ✨ Batch process completed ✨ ---");
-    if let Some(cid) = metadata_without_suffix_cid {
+    if !result.tokens.is_empty() {
         info!(
-            "Next step (no suffix), you can set Base URI in contract to: ipfs://{}/",
-            cid
+            "Each token has its own metadata CID, see {:?} for the full token URI list",
+            result.results_file()
         );
     }
-    if let Some(cid) = metadata_with_suffix_cid {
+    if let Some(cid) = &result.thumbnails_cid {
+        info!("Thumbnails folder: {}", storage.uri(cid));
+    }
+    if let Some(cid) = &result.metadata_without_suffix_cid {
         info!(
-            "Next step (with suffix), you can set Base URI in contract to: ipfs://{}/",
-            cid
+            "Next step (no suffix), you can set Base URI in contract to: {}",
+            base_uri(storage, cid, trailing_slash)
+        );
+    }
+    if let Some(cid) = &result.metadata_with_suffix_cid {
+        info!(
+            "Next step (with suffix), you can set Base URI in contract to: {}",
+            base_uri(storage, cid, trailing_slash)
         );
     }
-
-    Ok(())
-}
-
-async fn generate_and_upload_both_versions(
-    api: &PinataApi,
-    image_files: &[PathBuf],
-    images_folder_cid: &str,
-) -> Result<(String, String, PathBuf)> {
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S").to_string();
-
-    // Create separate directories for each version
-    let metadata_dir_with_suffix =
-        PathBuf::from("output").join(format!("batch_images-metadata-with-suffix-{}", timestamp));
-    let metadata_dir_without_suffix = PathBuf::from("output").join(format!(
-        "batch_images-metadata-without-suffix-{}",
-        timestamp
-    ));
-
-    // Create version with suffix
-    create_metadata_files(
-        image_files,
-        &metadata_dir_with_suffix,
-        images_folder_cid,
-        true, // with suffix
-        true, // is_dual_version
-    )
-    .await?;
-
-    info!("📁 Uploading metadata folder with suffix...");
-    let cid_with = upload_directory_with_retry(api, &metadata_dir_with_suffix).await?;
-
-    // Create version without suffix
-    create_metadata_files(
-        image_files,
-        &metadata_dir_without_suffix,
-        images_folder_cid,
-        false, // without suffix
-        true,  // is_dual_version
-    )
-    .await?;
-
-    info!("📁 Uploading metadata folder without suffix...");
-    let cid_without = upload_directory_with_retry(api, &metadata_dir_without_suffix).await?;
-
-    // Clean up the with-suffix directory, keep the without-suffix for local save
-    fs::remove_dir_all(&metadata_dir_with_suffix)?;
-
-    Ok((cid_with, cid_without, metadata_dir_without_suffix))
-}
-
-async fn generate_and_upload_single_version(
-    api: &PinataApi,
-    image_files: &[PathBuf],
-    images_folder_cid: &str,
-    with_suffix: bool,
-) -> Result<(String, PathBuf)> {
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S").to_string();
-    let metadata_dir = PathBuf::from("output").join(format!("batch_images-metadata-{}", timestamp));
-
-    create_metadata_files(
-        image_files,
-        &metadata_dir,
-        images_folder_cid,
-        with_suffix,
-        false,
-    )
-    .await?;
-
-    info!("📁 Uploading metadata folder...");
-    let cid = upload_directory_with_retry(api, &metadata_dir).await?;
-
-    // Don't remove the directory, we'll save it
-    Ok((cid, metadata_dir))
 }
 
-async fn create_metadata_files(
-    image_files: &[PathBuf],
-    dir: &Path,
-    images_folder_cid: &str,
-    with_suffix: bool,
-    is_dual_version: bool,
-) -> Result<()> {
-    if dir.exists() {
-        fs::remove_dir_all(dir)?;
-    }
-    fs::create_dir_all(dir)?;
-
-    for image_file in image_files {
-        let token_id_str = image_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow!("Invalid filename"))?;
-        let token_id: u64 = token_id_str.parse()?;
-        let image_filename = image_file
-            .file_name()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow!("Invalid filename"))?;
-
-        let metadata = NftMetadata {
-            name: format!("MetaCore #{}", token_id),
-            description: "A unique member of the MetaCore collection.".to_string(),
-            image: format!("ipfs://{}/{}", images_folder_cid, image_filename),
-            attributes: vec![Attribute {
-                trait_type: "ID".to_string(),
-                value: token_id.into(),
-            }],
-        };
-
-        let file_name = if with_suffix {
-            if is_dual_version {
-                // 双版本生成时，带后缀版本固定使用 .json
-                format!("{}.json", token_id_str)
-            } else {
-                // 单版本生成时，使用环境变量设置的后缀
-                format!("{}{}", token_id_str, get_metadata_file_suffix())
-            }
-        } else {
-            // 不带后缀版本，始终不带后缀
-            token_id_str.to_string()
-        };
-
-        let file_path = dir.join(&file_name);
-        let mut file = File::create(&file_path)?;
-        file.write_all(serde_json::to_string_pretty(&metadata)?.as_bytes())?;
-        file.flush()?;
-        drop(file);
-
-        info!("📄 Created metadata file: {}", file_path.to_string_lossy());
+/// `--quiet` 的 stdout 输出：文件夹模式依次为图片文件夹 CID、元数据文件夹 CID（双版本时再加带后缀的版本）；
+/// 逐文件模式每个 token 一行 `<token_id>\t<image_cid>\t<metadata_cid>`
+fn print_batch_cids(result: &BatchResult) {
+    if let Some(cid) = &result.images_cid {
+        println!("{}", cid);
     }
-
-    // Verify files were created and are readable
-    let files_in_dir: Vec<_> = fs::read_dir(dir)?.filter_map(Result::ok).collect();
-    info!(
-        "📁 Created {} metadata files in: {}",
-        files_in_dir.len(),
-        dir.to_string_lossy()
-    );
-
-    // Verify each file is readable and has content
-    for file_entry in &files_in_dir {
-        let file_path = &file_entry.path();
-        let file_size = fs::metadata(file_path)?.len();
-        let content = fs::read_to_string(file_path)?;
-        info!(
-            "✅ File {} is readable, size: {} bytes, content length: {} bytes",
-            file_path.to_string_lossy(),
-            file_size,
-            content.len()
+    for cid in [
+        &result.metadata_without_suffix_cid,
+        &result.metadata_with_suffix_cid,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        println!("{}", cid);
+    }
+    for token in &result.tokens {
+        println!(
+            "{}\t{}\t{}",
+            token.token_id,
+            token.image_cid,
+            token.metadata_cid.as_deref().unwrap_or("")
         );
     }
+}
 
-    // Additional verification: check folder size before upload
-    let folder_size = calculate_folder_size(dir)?;
-    let folder_size_mb = folder_size as f64 / 1024.0 / 1024.0;
+fn report_single_result(storage: &dyn StorageBackend, result: &SingleResult) {
+    info!("\n--- ✨ Single file process completed ✨ ---");
     info!(
-        "📁 Metadata folder size before upload: {:.2} MB ({} bytes)",
-        folder_size_mb, folder_size
+        "Next step, you can set Token URI in contract to: {}",
+        result.token_uri(storage)
     );
-
-    // Force filesystem sync before upload
-    if let Ok(_) = std::process::Command::new("sync").output() {
-        info!("📁 Filesystem sync completed");
-    }
-
-    Ok(())
 }
 
-fn calculate_folder_size(dir_path: &Path) -> Result<u64> {
-    let mut total_size = 0u64;
-
-    for entry in fs::read_dir(dir_path)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file() {
-            let file_size = fs::metadata(&path)?.len();
-            total_size += file_size;
-        } else if path.is_dir() {
-            total_size += calculate_folder_size(&path)?;
+async fn connect_pinata(
+    options: ClientOptions,
+    dry_run: bool,
+    group: Option<&str>,
+    create_group: bool,
+) -> Result<PinataClient> {
+    if dry_run {
+        let mut api = PinataClient::dry_run(options);
+        if let Some(group) = group {
+            api.join_group(group, create_group).await?;
         }
+        return Ok(api);
     }
-
-    Ok(total_size)
+    let auth = PinataAuth::from_env()?;
+    let mut api = PinataClient::new(&auth, options)
+        .map_err(|e| anyhow!("Pinata API initialization failed: {}", e))?;
+    api.test_authentication()
+        .await
+        .map_err(|e| anyhow!("Pinata authentication failed: {}", e))?;
+    info!("✅ Pinata authentication successful!");
+    if let Some(group) = group {
+        api.join_group(group, create_group)
+            .await
+            .map_err(|e| anyhow!("Failed to resolve Pinata group {:?}: {}", group, e))?;
+    }
+    Ok(api)
 }
 
-async fn save_batch_results(
-    output_dir: &Path,
-    images_cid: &str,
-    metadata_with_suffix_cid: Option<&str>,
-    metadata_without_suffix_cid: Option<&str>,
-    total_files: usize,
-    metadata_dir: Option<&Path>,
-) -> Result<()> {
-    let results = serde_json::json!({
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "images_cid": images_cid,
-        "metadata_with_suffix_cid": metadata_with_suffix_cid,
-        "metadata_without_suffix_cid": metadata_without_suffix_cid,
-        "total_files": total_files,
-        "status": "completed"
-    });
-
-    let results_file = output_dir.join("results").join("upload-result.json");
-    let mut file = File::create(&results_file)?;
-    file.write_all(serde_json::to_string_pretty(&results)?.as_bytes())?;
-
-    // Copy metadata folder if provided
-    if let Some(metadata_src) = metadata_dir {
-        let metadata_dest = output_dir.join("metadata");
-        if metadata_src.exists() {
-            if metadata_dest.exists() {
-                fs::remove_dir_all(&metadata_dest)?;
-            }
-            fs::create_dir_all(&metadata_dest)?;
-
-            // Copy all files from metadata directory
-            for entry in fs::read_dir(metadata_src)? {
-                let entry = entry?;
-                let src_path = entry.path();
-                let dest_path = metadata_dest.join(src_path.file_name().unwrap());
-
-                if src_path.is_file() {
-                    fs::copy(&src_path, &dest_path)?;
-                    info!("📄 Copied metadata file: {}", dest_path.to_string_lossy());
-                }
-            }
-            info!("📁 Metadata folder saved to: {:?}", metadata_dest);
-        }
+fn connect_arweave(
+    options: ArweaveOptions,
+    wallet: Option<&Path>,
+    dry_run: bool,
+) -> Result<ArweaveClient> {
+    if dry_run {
+        return Ok(ArweaveClient::dry_run(options));
     }
-
-    let readme_content = format!(
-        "# Batch Upload Results
-
-## Upload Information
-- **Timestamp**: {}
-- **Images CID**: `{}`
-- **Metadata with suffix CID**: `{}`
-- **Metadata without suffix CID**: `{}`
-- **Total files**: {}
-
-## Usage
-- For contracts expecting .json suffix: Use `ipfs://{}/`
-- For contracts without suffix: Use `ipfs://{}/`
-
-## Files
-- Images are available at: `ipfs://{}/`
-- Metadata files are available at the respective CIDs above.
-- Local metadata files are saved in the `metadata/` folder for reference.
-",
-        chrono::Utc::now().to_rfc3339(),
-        images_cid,
-        metadata_with_suffix_cid.unwrap_or("N/A"),
-        metadata_without_suffix_cid.unwrap_or("N/A"),
-        total_files,
-        metadata_with_suffix_cid.unwrap_or(""),
-        metadata_without_suffix_cid.unwrap_or(""),
-        images_cid
-    );
-
-    let readme_file = output_dir.join("README.md");
-    let mut readme = File::create(&readme_file)?;
-    readme.write_all(readme_content.as_bytes())?;
-
-    info!("✅ Results saved to: {:?}", output_dir);
-    Ok(())
+    let wallet = wallet.ok_or_else(|| {
+        anyhow!(
+            "Please set ARWEAVE_WALLET in .env file (or pass --arweave-wallet) to upload to Arweave"
+        )
+    })?;
+    Ok(ArweaveClient::new(ArweaveWallet::load(wallet)?, options))
 }
 
-async fn process_single_file(api: &PinataApi, token_id: Option<u64>) -> Result<()> {
-    info!("==============================================");
-    info!("🚀 Starting single file processing (Pinata)...");
-    info!("==============================================");
-
-    let assets_dir = PathBuf::from("assets");
-    let image_dir = assets_dir.join("image");
-    if !image_dir.exists() {
-        return Err(anyhow!(
-            "❌ Image directory does not exist: {:?}",
-            image_dir
-        ));
+fn connect_nft_storage(options: NftStorageOptions, dry_run: bool) -> Result<NftStorageClient> {
+    if dry_run {
+        return Ok(NftStorageClient::dry_run(options));
     }
+    NftStorageClient::from_env(options)
+}
 
-    let image_files: Vec<PathBuf> = fs::read_dir(&image_dir)?
-        .filter_map(Result::ok)
-        .map(|e| e.path())
-        .filter(|p| p.is_file())
-        .collect();
-
-    if image_files.is_empty() {
-        return Err(anyhow!("❌ No image files found in {:?}", image_dir));
+async fn connect_kubo(options: KuboOptions, dry_run: bool) -> Result<KuboClient> {
+    if dry_run {
+        return Ok(KuboClient::dry_run(options));
     }
+    KuboClient::connect(options).await
+}
 
-    let image_file = &image_files[0];
-    info!("📁 Uploading image file: {}", image_file.display());
-    let image_cid = upload_single_file_to_pinata(api, image_file).await?;
-    info!("✅ Image uploaded successfully! CID: {}", image_cid);
-
-    let token_id = token_id.unwrap_or(1);
-    let metadata = NftMetadata {
-        name: format!("MetaCore #{}", token_id),
-        description: "A unique member of the MetaCore collection.".to_string(),
-        image: format!("ipfs://{}", image_cid),
-        attributes: vec![Attribute {
-            trait_type: "ID".to_string(),
-            value: token_id.into(),
-        }],
-    };
-
-    let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S-%3fZ").to_string();
-    let output_dir = PathBuf::from("output").join(format!("single-upload-{}", timestamp));
-    let results_dir = output_dir.join("results");
-    fs::create_dir_all(&results_dir)?;
-
-    // 简化：只创建和上传一个元数据文件
-    let base_filename = image_file
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| anyhow!("Invalid filename"))?;
-
-    // 为了便于管理，我们给本地备份文件一个 .json 后缀，但上传时可以指定不带后缀的名字
-    let local_metadata_path = output_dir.join(format!("{}.json", base_filename));
-    let mut file = File::create(&local_metadata_path)?;
-    file.write_all(serde_json::to_string_pretty(&metadata)?.as_bytes())?;
-
-    info!(
-        "📄 Created local metadata file: {}",
-        local_metadata_path.display()
-    );
-    info!("📁 Uploading metadata file...");
-
-    // 上传这个文件，并获得其最终的、唯一的CID
-    let metadata_cid = upload_single_file_to_pinata(api, &local_metadata_path).await?;
-    info!("✅ Metadata uploaded successfully! CID: {}", metadata_cid);
-
-    // 简化结果保存
-    let results_dir = output_dir.join("results");
-    fs::create_dir_all(&results_dir)?;
+/// 被 Ctrl-C 取消时的退出码（128 + SIGINT），与 shell 的约定一致
+const EXIT_CANCELLED: i32 = 130;
 
-    let results = serde_json::json!({
-        "image_cid": image_cid,
-       "metadata_cid": metadata_cid, // 只记录一个CID
-        "status": "completed",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "token_id": token_id
+/// 第一次 Ctrl-C 取消进行中的上传并保存已完成部分，第二次立即退出
+fn listen_for_ctrl_c(cancel: CancellationToken) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        warn!(
+            "🛑 Ctrl-C received, cancelling in-flight uploads and saving what has completed (press Ctrl-C again to exit immediately)"
+        );
+        cancel.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(EXIT_CANCELLED);
+        }
     });
+}
 
-    let results_file = results_dir.join("upload-result.json");
-    let mut file = File::create(&results_file)?;
-    file.write_all(serde_json::to_string_pretty(&results)?.as_bytes())?;
+const PINATA_ONLY: &str = "--verify-auth-only, upload-car, pin, unpin, unpin-batch, queue, list, usage and test are only supported with --backend pinata";
 
-    // 简化README内容
-    let readme_content = format!(
-        "# Single File Upload Results
+fn pinata_only(api: Option<&PinataClient>) -> Result<&PinataClient> {
+    api.ok_or_else(|| anyhow!(PINATA_ONLY))
+}
 
-## Upload Information
-- **Timestamp**: {}
-- **Image CID**: `{}`
-- **Metadata CID**: `{}`
-- **Token ID**: {}
+#[tokio::main]
+async fn main() -> Result<()> {
+    let start_time = std::time::Instant::now();
 
-## Usage
-- The Token URI for this NFT is: `ipfs://{}`
+    dotenv().ok();
+    let cli = Cli::parse();
+    match (&cli.command, cli.verify_auth_only) {
+        (None, false) => Cli::command()
+            .error(
+                ErrorKind::MissingSubcommand,
+                "a subcommand is required unless --verify-auth-only is given",
+            )
+            .exit(),
+        (Some(_), true) => Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--verify-auth-only cannot be combined with a subcommand",
+            )
+            .exit(),
+        _ => {}
+    }
 
-## Files
-- Image is available at: `https://gateway.pinata.cloud/ipfs/{}`
-- Metadata is available at: `https://gateway.pinata.cloud/ipfs/{}`
-",
-        chrono::Utc::now().to_rfc3339(),
-        image_cid,
-        metadata_cid,
-        token_id,
-        metadata_cid, // Token URI
-        image_cid,    // Gateway link for image
-        metadata_cid  // Gateway link for metadata
+    let progress = progress_container(cli.no_progress || cli.quiet);
+    // RUST_LOG 未设置（或无法解析）时默认输出 info 级别；--quiet 时 stdout 只留给 CID，日志只保留警告和错误并写到 stderr
+    let filter = if cli.quiet {
+        EnvFilter::new("warn")
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+    };
+    // diff --json 的 stdout 同样只留给结果
+    let json_stdout = matches!(
+        cli.command,
+        Some(Commands::Diff { json: true, .. } | Commands::Show { .. })
     );
-
-    let readme_file = output_dir.join("README.md");
-    let mut readme = File::create(&readme_file)?;
-    readme.write_all(readme_content.as_bytes())?;
-
-    info!("✅ Results saved to: {:?}", output_dir);
-    info!("\n--- ✨ Single file process completed ✨ ---");
-    info!(
-        "Next step, you can set Token URI in contract to: ipfs://{}",
-        metadata_cid
+    let writer = if cli.quiet || json_stdout {
+        BoxMakeWriter::new(io::stderr)
+    } else {
+        BoxMakeWriter::new(ProgressLogWriter(progress.clone()))
+    };
+    let fmt_layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    let fmt_layer = match cli.log_format {
+        LogFormat::Text => fmt_layer.boxed(),
+        LogFormat::Json => fmt_layer.json().boxed(),
+    };
+    // 保存到 main 结束，drop 时导出剩余的 span
+    let (otel_layer, telemetry) = match &cli.otel_endpoint {
+        Some(endpoint) => {
+            let (layer, telemetry) = otel_layer(endpoint)?;
+            (Some(layer), Some(telemetry))
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otel_layer)
+        .with(filter)
+        .init();
+
+    let mut config = Config::load(&cli.config)?;
+    // 命令行参数优先于配置文件和环境变量
+    if let Some(max_retries) = cli.max_retries {
+        config.max_retries = max_retries;
+    }
+    if let Some(retry_delay_ms) = cli.retry_delay_ms {
+        config.retry_delay_ms = retry_delay_ms;
+    }
+    if let Some(max_backoff_ms) = cli.max_backoff_ms {
+        config.max_backoff_ms = max_backoff_ms;
+    }
+    if let Some(upload_timeout_secs) = cli.upload_timeout_secs {
+        config.upload_timeout_seconds = upload_timeout_secs;
+    }
+    if let Some(per_mb_secs) = cli.upload_timeout_per_mb_secs {
+        config.upload_timeout_per_mb_seconds = per_mb_secs;
+    }
+    if let Some(gateway) = cli.gateway.first() {
+        config.gateway = gateway.clone();
+    }
+    let fallback_gateways = fallback_gateways(&cli.gateway);
+
+    let uploads_only = matches!(
+        cli.command,
+        Some(
+            Commands::Batch { .. }
+                | Commands::Single { .. }
+                | Commands::UploadDir { .. }
+                | Commands::ContractMeta { .. }
+        )
+    );
+    // report 只读本地文件，verify、show 和 diff 只访问网关
+    let any_backend = matches!(
+        cli.command,
+        Some(
+            Commands::Report { .. }
+                | Commands::Verify { .. }
+                | Commands::Show { .. }
+                | Commands::Diff { .. }
+        )
     );
+    if cli.backend != Backend::Pinata && !uploads_only && !any_backend {
+        return Err(anyhow!(PINATA_ONLY));
+    }
+    let has_pin_labels = cli.pin_name.is_some() || !cli.pin_keyvalues.is_empty();
+    let has_replication_regions = !cli.replication_region.is_empty();
+    // NFT.Storage 总是返回 CIDv1
+    if cli.backend == Backend::NftStorage && cli.cid_version == Some(0) {
+        warn!("⚠️  NFT.Storage always returns CIDv1 (bafy...), ignoring --cid-version 0");
+    }
 
-    Ok(())
-}
+    // 所有后端共享同一个失败预算，同时统计整次运行的请求次数
+    let budget = Arc::new(RetryBudget::new(cli.max_total_failures));
+    let mut retry = config.retry_policy().with_budget(budget.clone());
+    if let Some(per_second) = cli.max_requests_per_second {
+        retry = retry.with_rate_limit(Arc::new(RequestRateLimiter::new(per_second)));
+    }
+    let http = HttpOptions::new(cli.proxy, cli.ca_bundle.as_deref())?;
+    http.log_effective();
+    let arweave_options = ArweaveOptions {
+        retry: retry.clone(),
+        http: http.clone(),
+        node_url: cli.irys_node,
+        gateway: cli.arweave_gateway,
+    };
+    let nft_storage_options = NftStorageOptions {
+        retry: retry.clone(),
+        http: http.clone(),
+        gateway: cli.gateway.first().cloned(),
+        fallback_gateways: fallback_gateways.clone(),
+    };
+    let kubo_options = KuboOptions {
+        retry: retry.clone(),
+        http: http.clone(),
+        api_url: cli.ipfs_api,
+        gateway: cli.gateway.first().cloned(),
+        fallback_gateways: fallback_gateways.clone(),
+        cid_version: cli.cid_version.unwrap_or(0),
+    };
+    let client_options = ClientOptions {
+        retry: retry.clone(),
+        http,
+        gateway: config.gateway,
+        fallback_gateways,
+        pin_labels: PinLabels {
+            run_name: cli.pin_name.unwrap_or_else(|| {
+                let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S");
+                match &cli.collection_name {
+                    Some(collection) => format!("{} {}", collection, timestamp),
+                    None => format!("nft-upload-{}", timestamp),
+                }
+            }),
+            keyvalues: cli.pin_keyvalues.into_iter().collect(),
+        },
+        cid_version: cli.cid_version.unwrap_or(0),
+        replication_regions: cli.replication_region,
+    };
+    check_replication_regions(&client_options.replication_regions)?;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
-    let start_time = std::time::Instant::now();
+    if cli.dry_run {
+        if !uploads_only {
+            return Err(anyhow!(
+                "--dry-run is only supported by the batch, single, upload-dir and contract-meta commands"
+            ));
+        }
+        warn!("🧪 Dry run: nothing will be uploaded, placeholder CIDs will be used");
+    }
 
-    dotenv().ok();
-    let api_key = env::var("PINATA_API_KEY").context("Please set PINATA_API_KEY in .env file")?;
-    let secret_key =
-        env::var("PINATA_SECRET_KEY").context("Please set PINATA_SECRET_KEY in .env file")?;
+    // report 只读取已有的结果文件，按其中记录的后端重建链接，不需要凭证也不访问网络
+    if let Some(Commands::Report { run_dir }) = &cli.command {
+        let outcome = RunRecord::load(run_dir).and_then(|record| {
+            let storage: Box<dyn StorageBackend> = match record.backend()? {
+                Backend::Pinata => Box::new(PinataClient::dry_run(client_options)),
+                Backend::Arweave => Box::new(ArweaveClient::dry_run(arweave_options)),
+                Backend::NftStorage => Box::new(NftStorageClient::dry_run(nft_storage_options)),
+                Backend::Kubo => Box::new(KuboClient::dry_run(kubo_options)),
+            };
+            record.write_report(storage.as_ref(), run_dir)
+        });
+        if let Err(e) = outcome {
+            error!("❌ Script execution failed: {:?}", e);
+            drop(telemetry);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let api = PinataApi::new(&api_key, &secret_key)
-        .map_err(|e| anyhow!("Pinata API initialization failed: {}", e))?;
-    api.test_authentication()
-        .await
-        .map_err(|e| anyhow!("Pinata authentication failed: {}", e))?;
-    info!("✅ Pinata authentication successful!");
+    // --verify-auth-only：认证结果就是退出码，不读取素材也不创建输出目录
+    let Some(command) = cli.command else {
+        connect_pinata(client_options, false, None, false).await?;
+        return Ok(());
+    };
 
-    let cli = Cli::parse();
-    if let Err(e) = match cli.command {
-        Commands::Batch { both_versions, .. } => {
-            process_batch_collection(&api, both_versions).await
+    // pin / unpin / queue / list / usage / test 直接使用 Pinata API，batch / single 只依赖存储后端接口
+    let mut api = None;
+    let storage: Box<dyn StorageBackend> = match cli.backend {
+        Backend::Pinata => {
+            let client = connect_pinata(
+                client_options,
+                cli.dry_run,
+                cli.group.as_deref(),
+                cli.create_group,
+            )
+            .await?;
+            api = Some(client.clone());
+            Box::new(client)
         }
-        Commands::Single { token_id, .. } => process_single_file(&api, token_id).await,
-        _ => {
-            warn!("This command is not implemented yet");
-            Ok(())
+        Backend::Arweave => Box::new(connect_arweave(
+            arweave_options,
+            cli.arweave_wallet.as_deref(),
+            cli.dry_run,
+        )?),
+        Backend::NftStorage => Box::new(connect_nft_storage(nft_storage_options, cli.dry_run)?),
+        Backend::Kubo => Box::new(connect_kubo(kubo_options, cli.dry_run).await?),
+    };
+    let storage: Box<dyn StorageBackend> = match cli.display_cid_version {
+        Some(_) if storage.cid_version().is_none() => {
+            return Err(anyhow!(
+                "❌ --display-cid-version only applies to IPFS backends, {} does not use CIDs",
+                storage.name()
+            ));
         }
-    } {
-        error!("❌ Script execution failed: {:?}", e);
+        Some(version) => Box::new(DisplayCidVersion::new(storage, version)),
+        None => storage,
+    };
+    let storage = storage.as_ref();
+    // pin 名称/标签只有 Pinata 支持，其他后端忽略这些参数并给出提示
+    if has_pin_labels && api.is_none() {
+        warn!(
+            "⚠️  --pin-name and --metadata are Pinata pin labels, {} ignores them",
+            storage.name()
+        );
+    }
+    if cli.group.is_some() && api.is_none() {
+        warn!(
+            "⚠️  --group is a Pinata feature, {} ignores it",
+            storage.name()
+        );
+    }
+    if has_replication_regions && api.is_none() {
+        warn!(
+            "⚠️  --replication-region is a Pinata pin policy, {} ignores it",
+            storage.name()
+        );
     }
 
+    let workspace = Workspace {
+        assets_dir: cli.assets_dir,
+        output_dir: cli.output_dir,
+        overwrite: cli.overwrite,
+        run_name: cli.deterministic,
+        confirmation: UploadConfirmation {
+            max_bytes: config.confirm_upload_mb.saturating_mul(1024 * 1024),
+            max_files: config.confirm_upload_files,
+            assume_yes: cli.yes,
+        },
+    };
+    let options = MetadataOptions {
+        collection_name: cli.collection_name,
+        name_template: cli.name_template,
+        description_template: cli.description_template,
+        animation_url_template: cli.animation_url_template,
+        external_url_template: cli.external_url_template,
+        background_color: cli.background_color,
+        attributes_suffix: cli.attributes_suffix,
+        attribute_filter: AttributeFilter::new(cli.attribute_allowlist, cli.attribute_denylist),
+        id_trait: (!cli.no_id_trait).then_some(cli.id_trait_name),
+        embed_image_info: cli.embed_image_info,
+        overrides: match &cli.metadata_csv {
+            Some(path) => load_metadata_csv(path)?,
+            None => HashMap::new(),
+        },
+        image_extensions: cli
+            .include_ext
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect(),
+        metadata_file_suffix: config.metadata_file_suffix,
+        string_ids: false,
+        token_ids: TokenIds::FileStem,
+        schema: match (&cli.schema, cli.validate) {
+            (Some(path), _) => Some(Arc::new(MetadataSchema::load(path)?)),
+            (None, true) => Some(Arc::new(MetadataSchema::erc721())),
+            (None, false) => None,
+        },
+    };
+    let cancel = CancellationToken::new();
+    listen_for_ctrl_c(cancel.clone());
+    // batch 自己处理取消并保存部分结果，其他命令在 Ctrl-C 时直接中止
+    let is_batch = matches!(command, Commands::Batch { .. });
+    let mut failed_uploads = false;
+    let work = async {
+        match command {
+            Commands::Batch {
+                both_versions,
+                per_file,
+                concurrency,
+                images_cid,
+                allow_gaps,
+                string_ids,
+                verify,
+                no_cache,
+                local_cid,
+                resume,
+                check_images,
+                min_dim,
+                max_dim,
+                require_square,
+                recursive,
+                file_list,
+                token_ids,
+                start_token_id,
+                prune_local,
+                keep_working,
+                continue_on_error,
+                optimize_images,
+                jpeg_quality,
+                verify_manifest,
+                generate_thumbnails,
+            } => {
+                let by_path = token_ids == Some(TokenIdSource::Path);
+                let options = MetadataOptions {
+                    // 相对路径中的目录名不是数字
+                    string_ids: string_ids || by_path,
+                    token_ids: if by_path {
+                        TokenIds::RelativePath(workspace.batch_images_dir())
+                    } else {
+                        TokenIds::FileStem
+                    },
+                    ..options
+                };
+                let batch = BatchOptions {
+                    both_versions,
+                    per_file,
+                    concurrency,
+                    images_cid,
+                    allow_gaps,
+                    verify,
+                    no_cache,
+                    local_cid,
+                    cancel: cancel.clone(),
+                    resume,
+                    image_rules: (check_images
+                        || min_dim.is_some()
+                        || max_dim.is_some()
+                        || require_square)
+                        .then_some(ImageRules {
+                            min_dim,
+                            max_dim,
+                            require_square,
+                        }),
+                    recursive,
+                    start_token_id,
+                    working_dirs: match (prune_local, keep_working) {
+                        (true, _) => WorkingDirs::Prune,
+                        (_, true) => WorkingDirs::KeepAll,
+                        _ => WorkingDirs::KeepMetadata,
+                    },
+                    continue_on_error,
+                    base_uri_trailing_slash: cli.base_uri_trailing_slash,
+                    optimize_images: optimize_images.then_some(jpeg_quality),
+                    verify_manifest,
+                    file_list: file_list.as_deref().map(FileList::read).transpose()?,
+                    generate_thumbnails,
+                    max_file_size: cli.max_file_size,
+                };
+                process_batch_collection(storage, &workspace, &batch, &options, &progress)
+                    .await
+                    .map(|result| {
+                        report_batch_result(storage, &result, cli.base_uri_trailing_slash);
+                        if cli.quiet {
+                            print_batch_cids(&result);
+                        }
+                        failed_uploads = !result.failed.is_empty();
+                    })
+            }
+            Commands::Single {
+                token_id,
+                file,
+                as_folder,
+            } => {
+                let single = SingleOptions {
+                    token_id,
+                    file,
+                    as_folder,
+                    max_file_size: cli.max_file_size,
+                };
+                process_single_file(storage, &workspace, &single, &options)
+                    .await
+                    .map(|result| {
+                        report_single_result(storage, &result);
+                        if cli.quiet {
+                            println!("{}", result.image_cid);
+                            match &result.metadata_path {
+                                Some(path) => println!("{}/{}", result.metadata_cid, path),
+                                None => println!("{}", result.metadata_cid),
+                            }
+                        }
+                    })
+            }
+            Commands::UploadDir { path, verify } => upload_dir(
+                storage,
+                &workspace,
+                &path,
+                verify,
+                cli.base_uri_trailing_slash,
+            )
+            .await
+            .map(|cid| {
+                if cli.quiet {
+                    println!("{}", cid);
+                }
+            }),
+            Commands::UploadCar { path } => upload_car(
+                pinata_only(api.as_ref())?,
+                &workspace,
+                &path,
+                cli.base_uri_trailing_slash,
+            )
+            .await
+            .map(|cid| {
+                if cli.quiet {
+                    println!("{}", cid);
+                }
+            }),
+            Commands::ContractMeta {
+                from,
+                name,
+                description,
+                image,
+                external_link,
+                seller_fee_basis_points,
+                fee_recipient,
+            } => {
+                let mut metadata = match &from {
+                    Some(path) => ContractMetadata::load(path)?,
+                    None => ContractMetadata::default(),
+                };
+                // 命令行参数优先于 --from 文件，名称最后回退到 --collection-name
+                if let Some(name) = name {
+                    metadata.name = name;
+                }
+                if metadata.name.is_empty()
+                    && let Some(collection) = options.collection_name
+                {
+                    metadata.name = collection;
+                }
+                metadata.description = description.or(metadata.description);
+                metadata.image = image.or(metadata.image);
+                metadata.external_link = external_link.or(metadata.external_link);
+                metadata.seller_fee_basis_points =
+                    seller_fee_basis_points.or(metadata.seller_fee_basis_points);
+                metadata.fee_recipient = fee_recipient.or(metadata.fee_recipient);
+                upload_contract_metadata(storage, &workspace, metadata)
+                    .await
+                    .map(|contract_uri| {
+                        if cli.quiet {
+                            println!("{}", contract_uri);
+                        }
+                    })
+            }
+            Commands::Pin { cid } => {
+                pin_by_hash(pinata_only(api.as_ref())?, &workspace, &cid).await
+            }
+            Commands::Unpin { cid } => unpin_by_hash(pinata_only(api.as_ref())?, &cid).await,
+            Commands::UnpinBatch {
+                name_prefix,
+                cid_file,
+            } => {
+                let targets = match (&name_prefix, &cid_file) {
+                    (Some(prefix), _) => UnpinTargets::NamePrefix(prefix),
+                    (None, Some(path)) => UnpinTargets::CidFile(path),
+                    (None, None) => unreachable!("clap requires --name-prefix or --cid-file"),
+                };
+                unpin_batch(pinata_only(api.as_ref())?, targets, workspace.assume_yes()).await
+            }
+            Commands::Queue {
+                status,
+                since,
+                limit,
+            } => check_pin_queue(pinata_only(api.as_ref())?, status, since, limit).await,
+            Commands::List {
+                name_contains,
+                sort,
+            } => list_pins(pinata_only(api.as_ref())?, name_contains.as_deref(), sort).await,
+            Commands::Usage => show_usage(pinata_only(api.as_ref())?).await,
+            Commands::Verify { cid, file } => verify_file(storage, &cid, &file).await,
+            Commands::Show { cid, token_id, raw } => {
+                show_metadata(storage, &cid, token_id.as_deref(), raw)
+                    .await
+                    .map(|content| println!("{}", content))
+            }
+            Commands::Diff { cid_a, cid_b, json } => diff_directories(storage, &cid_a, &cid_b)
+                .await
+                .and_then(|diff| {
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&diff)?);
+                    } else {
+                        diff.log_summary();
+                    }
+                    Ok(())
+                }),
+            Commands::Test { skip_fetch } => {
+                run_smoke_test(pinata_only(api.as_ref())?, skip_fetch).await
+            }
+            Commands::Report { .. } => unreachable!("report is handled before connecting"),
+        }
+    };
+    let outcome = if is_batch {
+        work.await
+    } else {
+        cancellable(&cancel, work).await
+    };
+    // 失败为 1（包括 --continue-on-error 时有文件上传失败），Ctrl-C 取消为 130
+    let exit_code = match outcome {
+        Err(e) if e.is::<Cancelled>() => {
+            warn!("🛑 Cancelled");
+            EXIT_CANCELLED
+        }
+        Err(e) => {
+            error!("❌ Script execution failed: {:?}", e);
+            if e.is::<BudgetExhausted>() {
+                error!(
+                    "🛑 Check the storage service status before retrying (see --max-total-failures)"
+                );
+            }
+            if let Some(UploadError::AuthFailed(_)) = e.downcast_ref::<UploadError>() {
+                error!(
+                    "🔑 The storage backend rejected the credentials, check PINATA_JWT / API keys"
+                );
+            }
+            1
+        }
+        Ok(()) => i32::from(failed_uploads),
+    };
+
+    if uploads_only && budget.attempts() > 0 {
+        info!(
+            "📊 {} upload and API request attempts, {} failed",
+            budget.attempts(),
+            budget.failures()
+        );
+    }
     info!("Total script execution time: {:?}", start_time.elapsed());
+    if exit_code != 0 {
+        // process::exit 不会运行析构函数
+        drop(telemetry);
+        std::process::exit(exit_code);
+    }
     Ok(())
 }
@@ -0,0 +1,1196 @@
+//! NFT 元数据的生成与写入
+
+use crate::image_check::read_dimensions;
+use crate::schema::MetadataSchema;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// --- 元数据生成选项 ---
+pub(crate) const DEFAULT_COLLECTION_NAME: &str = "MetaCore";
+pub(crate) const DEFAULT_NAME_TEMPLATE: &str = "{collection} #{id}";
+pub(crate) const DEFAULT_DESCRIPTION_TEMPLATE: &str =
+    "A unique member of the {collection} collection.";
+pub(crate) const DEFAULT_ATTRIBUTES_SUFFIX: &str = ".attributes.json";
+pub(crate) const DEFAULT_IMAGE_EXTENSIONS: &str = "png,jpg,jpeg,gif,webp,avif,svg";
+
+/// 元数据生成选项：name/description 模板（支持 `{id}`、`{filename}` 和 `{collection}` 占位符）、属性侧车文件后缀与可接受的图片扩展名
+#[derive(Debug, Clone)]
+pub struct MetadataOptions {
+    /// `--collection-name`：模板中的 `{collection}`，未设置时为 `MetaCore`；设置后也出现在结果 README 标题中
+    pub collection_name: Option<String>,
+    pub name_template: String,
+    pub description_template: String,
+    /// 可选的 `animation_url` / `external_url` 模板，未设置时元数据中不输出对应字段
+    pub animation_url_template: Option<String>,
+    pub external_url_template: Option<String>,
+    /// `--background-color`：所有 token 共用的 `background_color`，侧车文件中的值优先
+    pub background_color: Option<String>,
+    pub attributes_suffix: String,
+    /// 侧车文件中哪些属性写入元数据
+    pub attribute_filter: AttributeFilter,
+    /// 自动生成的 token ID 属性的 `trait_type`（默认 `ID`）；`None` 表示不生成该属性
+    pub id_trait: Option<String>,
+    /// 追加图片的 `width`、`height`、`size_bytes` 属性
+    pub embed_image_info: bool,
+    /// `--metadata-csv` 中按 token ID 指定的 name/description，优先于模板
+    pub overrides: HashMap<String, TokenOverride>,
+    /// 小写、不带 `.` 的图片扩展名
+    pub image_extensions: Vec<String>,
+    /// 单版本元数据文件后缀（空字符串表示不带后缀）
+    pub metadata_file_suffix: String,
+    /// 使用文件名作为字符串 token ID，而不是解析为整数
+    pub string_ids: bool,
+    /// token ID 从图片路径的哪一部分得出
+    pub token_ids: TokenIds,
+    /// 设置后，每个元数据文件在写入和上传前都要通过该 schema 校验
+    pub schema: Option<Arc<MetadataSchema>>,
+}
+
+/// `--attribute-allowlist` / `--attribute-denylist`：按 `trait_type` 筛选侧车文件中的属性，名称不区分大小写。
+/// 只作用于侧车文件，token ID 属性和 `--embed-image-info` 的属性不受影响
+#[derive(Debug, Clone, Default)]
+pub enum AttributeFilter {
+    #[default]
+    All,
+    /// 只保留这些 `trait_type`（已转为小写）
+    Allow(HashSet<String>),
+    /// 去掉这些 `trait_type`（已转为小写）
+    Deny(HashSet<String>),
+}
+
+impl AttributeFilter {
+    /// 两个列表都为空时不筛选；命令行保证最多只有一个不为空
+    pub fn new(allowlist: Vec<String>, denylist: Vec<String>) -> Self {
+        let normalize = |names: Vec<String>| -> HashSet<String> {
+            names
+                .iter()
+                .map(|name| name.trim().to_lowercase())
+                .filter(|name| !name.is_empty())
+                .collect()
+        };
+        let (allow, deny) = (normalize(allowlist), normalize(denylist));
+        if !allow.is_empty() {
+            AttributeFilter::Allow(allow)
+        } else if !deny.is_empty() {
+            AttributeFilter::Deny(deny)
+        } else {
+            AttributeFilter::All
+        }
+    }
+
+    fn keeps(&self, trait_type: &str) -> bool {
+        match self {
+            AttributeFilter::All => true,
+            AttributeFilter::Allow(names) => names.contains(&trait_type.to_lowercase()),
+            AttributeFilter::Deny(names) => !names.contains(&trait_type.to_lowercase()),
+        }
+    }
+}
+
+/// 从图片路径得出 token ID 的方式
+#[derive(Debug, Clone, Default)]
+pub enum TokenIds {
+    /// 文件名（不含扩展名），如 `42.png` → `42`
+    #[default]
+    FileStem,
+    /// 相对该目录的路径（不含扩展名，目录之间用 `-` 连接），如 `rares/42.png` → `rares-42`
+    RelativePath(PathBuf),
+    /// `--start-token-id` 按排序后的顺序分配的编号，文件名只决定顺序
+    Sequential(HashMap<PathBuf, u64>),
+}
+
+impl MetadataOptions {
+    /// 图片对应的 token ID，也是元数据文件名（不含后缀）
+    pub(crate) fn token_id(&self, image_file: &Path) -> Result<String> {
+        let invalid = || anyhow!("Invalid filename: {}", image_file.display());
+        match &self.token_ids {
+            TokenIds::FileStem => Ok(image_file
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .ok_or_else(invalid)?
+                .to_string()),
+            TokenIds::Sequential(ids) => ids
+                .get(image_file)
+                .map(u64::to_string)
+                .ok_or_else(|| anyhow!("No token ID assigned to {}", image_file.display())),
+            TokenIds::RelativePath(root) => {
+                let relative = image_file
+                    .strip_prefix(root)
+                    .with_context(|| {
+                        format!("{} is outside {}", image_file.display(), root.display())
+                    })?
+                    .with_extension("");
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_str().ok_or_else(invalid))
+                    .collect::<Result<Vec<_>>>()
+                    .map(|parts| parts.join("-"))
+            }
+        }
+    }
+
+    fn render(&self, template: &str, token_id: &str, image_filename: &str) -> String {
+        template
+            .replace(
+                "{collection}",
+                self.collection_name
+                    .as_deref()
+                    .unwrap_or(DEFAULT_COLLECTION_NAME),
+            )
+            .replace("{id}", token_id)
+            .replace("{filename}", image_filename)
+    }
+
+    pub(crate) fn is_image(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.image_extensions.contains(&ext.to_lowercase()))
+    }
+
+    pub(crate) fn is_sidecar(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(|s| s.to_str())
+            .is_some_and(|name| name.ends_with(&self.attributes_suffix))
+    }
+
+    /// 读取图片旁的 `<文件名><suffix>` 侧车文件，文件不存在时返回空的 [`Sidecar`]
+    fn load_sidecar(&self, image_file: &Path, token_id_str: &str) -> Result<Sidecar> {
+        let stem = image_file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid filename"))?;
+        let sidecar = image_file.with_file_name(format!("{}{}", stem, self.attributes_suffix));
+        if !sidecar.exists() {
+            return Ok(Sidecar::default());
+        }
+
+        let content = fs::read_to_string(&sidecar)
+            .with_context(|| format!("Failed to read attributes file {:?}", sidecar))?;
+        // 以 `{` 开头的是对象形式，否则按原来的属性数组解析
+        let Sidecar {
+            mut attributes,
+            background_color,
+        } = if content.trim_start().starts_with('{') {
+            serde_json::from_str(&content).with_context(|| {
+                format!(
+                    "Invalid attributes file {:?} for token {}: expected {{\"attributes\": [...], \"background_color\": \"RRGGBB\"}} (both optional)",
+                    sidecar, token_id_str
+                )
+            })?
+        } else {
+            Sidecar {
+                attributes: serde_json::from_str(&content).with_context(|| {
+                    format!(
+                        "Invalid attributes file {:?} for token {}: expected an array of {{\"trait_type\", \"value\"}} objects, optionally with \"display_type\": number, boost_number, boost_percentage or date",
+                        sidecar, token_id_str
+                    )
+                })?,
+                background_color: None,
+            }
+        };
+        if let Some(color) = &background_color {
+            parse_background_color(color).map_err(|e| {
+                anyhow!(
+                    "Invalid attributes file {:?} for token {}: background_color {}",
+                    sidecar,
+                    token_id_str,
+                    e
+                )
+            })?;
+        }
+        if let Some(attribute) = attributes
+            .iter()
+            .find(|a| a.display_type.is_some() && !a.value.is_number())
+        {
+            return Err(anyhow!(
+                "Invalid attributes file {:?} for token {}: \"{}\" has a display_type but its value {} is not a number",
+                sidecar,
+                token_id_str,
+                attribute.trait_type,
+                attribute.value
+            ));
+        }
+        info!(
+            "🏷️  Loaded {} attributes for token {} from {}",
+            attributes.len(),
+            token_id_str,
+            sidecar.display()
+        );
+        let mut dropped = Vec::new();
+        attributes.retain(|attribute| {
+            let keep = self.attribute_filter.keeps(&attribute.trait_type);
+            if !keep {
+                dropped.push(attribute.trait_type.clone());
+            }
+            keep
+        });
+        if !dropped.is_empty() {
+            info!(
+                "🙈 Dropped {} attributes for token {}: {}",
+                dropped.len(),
+                token_id_str,
+                dropped.join(", ")
+            );
+        }
+        Ok(Sidecar {
+            attributes,
+            background_color,
+        })
+    }
+
+    /// `--embed-image-info` 时从图片读取的数值属性；侧车文件中已有的同名属性优先，不会重复添加
+    pub(crate) fn image_info_attributes(
+        &self,
+        image_file: &Path,
+        existing: &[Attribute],
+    ) -> Result<Vec<Attribute>> {
+        if !self.embed_image_info {
+            return Ok(Vec::new());
+        }
+        let mut info = Vec::with_capacity(3);
+        match read_dimensions(image_file)? {
+            Some((width, height)) => {
+                info.push(("width", u64::from(width)));
+                info.push(("height", u64::from(height)));
+            }
+            None => warn!(
+                "⚠️  Cannot read the dimensions of {}, embedding size_bytes only",
+                image_file.display()
+            ),
+        }
+        info.push(("size_bytes", fs::metadata(image_file)?.len()));
+
+        Ok(info
+            .into_iter()
+            .filter(|(name, _)| !existing.iter().any(|a| a.trait_type == *name))
+            .map(|(name, value)| Attribute {
+                trait_type: name.to_string(),
+                value: value.into(),
+                display_type: None,
+            })
+            .collect())
+    }
+
+    /// 按 `image_files` 的顺序从 `start` 开始分配 token ID，返回使用这些编号的选项
+    pub(crate) fn numbered_from(&self, start: u64, image_files: &[PathBuf]) -> Result<Self> {
+        let mut ids = HashMap::with_capacity(image_files.len());
+        for (offset, image_file) in image_files.iter().enumerate() {
+            let token_id = start.checked_add(offset as u64).ok_or_else(|| {
+                anyhow!(
+                    "--start-token-id {} overflows for {} images",
+                    start,
+                    image_files.len()
+                )
+            })?;
+            ids.insert(image_file.clone(), token_id);
+        }
+        Ok(Self {
+            token_ids: TokenIds::Sequential(ids),
+            ..self.clone()
+        })
+    }
+
+    /// 双版本生成时带后缀版本的后缀：使用配置的后缀，未配置时为 `.json`
+    pub(crate) fn dual_version_suffix(&self) -> &str {
+        if self.metadata_file_suffix.is_empty() {
+            ".json"
+        } else {
+            &self.metadata_file_suffix
+        }
+    }
+
+    pub(crate) fn metadata_file_name(
+        &self,
+        token_id_str: &str,
+        with_suffix: bool,
+        is_dual_version: bool,
+    ) -> String {
+        if with_suffix {
+            let suffix = if is_dual_version {
+                self.dual_version_suffix()
+            } else {
+                // 单版本生成时，使用配置的后缀
+                &self.metadata_file_suffix
+            };
+            format!("{}{}", token_id_str, suffix)
+        } else {
+            // 不带后缀版本，始终不带后缀
+            token_id_str.to_string()
+        }
+    }
+
+    /// ID 属性的值：默认为文件名解析出的整数，`--string-ids` 时直接使用文件名（不含扩展名）
+    fn token_id_value(&self, token_id_str: &str) -> Result<serde_json::Value> {
+        if self.string_ids {
+            return Ok(token_id_str.into());
+        }
+        let token_id: u64 = token_id_str.parse().with_context(|| {
+            format!(
+                "Token ID {:?} is not numeric (use --string-ids for non-numeric filenames)",
+                token_id_str
+            )
+        })?;
+        Ok(token_id.into())
+    }
+
+    /// 一张图片的完整元数据：模板、侧车文件（属性与 `background_color`）和 `--embed-image-info` 的属性；
+    /// batch 和 single 都经过这里，两种模式对侧车文件的处理保持一致
+    pub(crate) fn build_for_image(
+        &self,
+        image_file: &Path,
+        token_id_str: &str,
+        image: String,
+    ) -> Result<NftMetadata> {
+        let image_filename = image_file
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid filename"))?;
+        let Sidecar {
+            mut attributes,
+            background_color,
+        } = self.load_sidecar(image_file, token_id_str)?;
+        let image_info = self.image_info_attributes(image_file, &attributes)?;
+        attributes.extend(image_info);
+        let mut metadata = self.build(token_id_str, image_filename, image, attributes)?;
+        if background_color.is_some() {
+            metadata.background_color = background_color;
+        }
+        Ok(metadata)
+    }
+
+    pub(crate) fn build(
+        &self,
+        token_id_str: &str,
+        image_filename: &str,
+        image: String,
+        extra_attributes: Vec<Attribute>,
+    ) -> Result<NftMetadata> {
+        let mut attributes = Vec::with_capacity(extra_attributes.len() + 1);
+        // 不生成 ID 属性时也照常检查 token ID 是否为数字
+        let id_value = self.token_id_value(token_id_str)?;
+        // 侧车文件自带 ID 属性时不再重复添加默认值
+        if let Some(id_trait) = &self.id_trait
+            && !extra_attributes.iter().any(|a| a.trait_type == *id_trait)
+        {
+            attributes.push(Attribute {
+                trait_type: id_trait.clone(),
+                value: id_value,
+                display_type: None,
+            });
+        }
+        attributes.extend(extra_attributes);
+
+        let overrides = self.overrides.get(token_id_str);
+        Ok(NftMetadata {
+            name: overrides
+                .and_then(|o| o.name.clone())
+                .unwrap_or_else(|| self.render(&self.name_template, token_id_str, image_filename)),
+            description: overrides
+                .and_then(|o| o.description.clone())
+                .unwrap_or_else(|| {
+                    self.render(&self.description_template, token_id_str, image_filename)
+                }),
+            image,
+            animation_url: self
+                .animation_url_template
+                .as_deref()
+                .map(|template| self.render(template, token_id_str, image_filename)),
+            external_url: self
+                .external_url_template
+                .as_deref()
+                .map(|template| self.render(template, token_id_str, image_filename)),
+            thumbnail: None,
+            background_color: self.background_color.clone(),
+            attributes,
+        })
+    }
+}
+
+/// `--metadata-csv` 中一个 token 的覆盖值，空单元格表示沿用模板
+#[derive(Debug, Clone, Default)]
+pub struct TokenOverride {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+const CSV_TOKEN_ID_COLUMN: &str = "token_id";
+const CSV_OVERRIDE_COLUMNS: [&str; 2] = ["name", "description"];
+
+/// 读取 `token_id,name,description` 格式的 CSV；`name` 和 `description` 列可以只出现一个
+pub fn load_metadata_csv(path: &Path) -> Result<HashMap<String, TokenOverride>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to open metadata CSV {:?}", path))?;
+    let headers = reader
+        .headers()
+        .with_context(|| format!("Failed to read the header row of {:?}", path))?
+        .clone();
+
+    let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+    let token_id_column = column(CSV_TOKEN_ID_COLUMN).ok_or_else(|| {
+        anyhow!(
+            "Metadata CSV {:?} has no {:?} column (found: {})",
+            path,
+            CSV_TOKEN_ID_COLUMN,
+            headers.iter().collect::<Vec<_>>().join(",")
+        )
+    })?;
+    if let Some(unknown) = headers
+        .iter()
+        .map(str::trim)
+        .find(|h| *h != CSV_TOKEN_ID_COLUMN && !CSV_OVERRIDE_COLUMNS.contains(h))
+    {
+        return Err(anyhow!(
+            "Metadata CSV {:?} has an unknown column {:?}, expected token_id,name,description",
+            path,
+            unknown
+        ));
+    }
+    let name_column = column("name");
+    let description_column = column("description");
+    if name_column.is_none() && description_column.is_none() {
+        return Err(anyhow!(
+            "Metadata CSV {:?} needs a name or description column next to token_id",
+            path
+        ));
+    }
+
+    let mut overrides = HashMap::new();
+    for record in reader.records() {
+        let record = record.with_context(|| format!("Failed to parse metadata CSV {:?}", path))?;
+        let line = record.position().map_or(0, |p| p.line());
+        let cell = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let token_id = cell(Some(token_id_column))
+            .ok_or_else(|| anyhow!("{}:{}: token_id is empty", path.display(), line))?;
+        let token_override = TokenOverride {
+            name: cell(name_column),
+            description: cell(description_column),
+        };
+        if overrides.insert(token_id.clone(), token_override).is_some() {
+            return Err(anyhow!(
+                "{}:{}: token {} appears more than once",
+                path.display(),
+                line,
+                token_id
+            ));
+        }
+    }
+
+    info!(
+        "📝 Loaded name/description overrides for {} tokens from {}",
+        overrides.len(),
+        path.display()
+    );
+    Ok(overrides)
+}
+
+// --- 数据结构 ---
+/// 属性侧车文件的内容：属性数组，或者同时带有 token 级字段的对象
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Sidecar {
+    #[serde(default)]
+    attributes: Vec<Attribute>,
+    /// 覆盖该 token 的 `--background-color`
+    #[serde(default)]
+    background_color: Option<String>,
+}
+
+/// 校验 `background_color`：OpenSea 要求 6 位十六进制颜色（如 `1a2b3c`），不带 `#`
+pub fn parse_background_color(s: &str) -> Result<String, String> {
+    if s.len() == 6 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(s.to_string());
+    }
+    match s.strip_prefix('#') {
+        Some(rest) if parse_background_color(rest).is_ok() => Err(format!(
+            "must not start with #, write {:?} instead of {:?}",
+            rest, s
+        )),
+        _ => Err(format!(
+            "expected 6 hex digits without # (e.g. 1a2b3c), got {:?}",
+            s
+        )),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attribute {
+    pub trait_type: String,
+    pub value: serde_json::Value,
+    /// OpenSea 按数值或日期展示属性，只能通过侧车文件设置；未设置时不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_type: Option<DisplayType>,
+}
+
+/// OpenSea 支持的 `display_type`，值都必须是数字（`date` 为 Unix 时间戳，单位秒）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayType {
+    Number,
+    BoostNumber,
+    BoostPercentage,
+    Date,
+}
+
+/// 单个 token 的元数据。序列化的键顺序即字段的声明顺序，部分链上或索引器的解析器依赖这一顺序：
+/// `name`、`description`、`image`、可选字段，最后是 `attributes`。新增的可选字段放在 `attributes` 之前，
+/// 不要调整已有字段的顺序，并同步更新 README「元数据文件格式」中的示例
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NftMetadata {
+    pub name: String,
+    pub description: String,
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    /// `--generate-thumbnails` 上传的缩略图
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// OpenSea 的展示背景色，6 位十六进制、不带 `#`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+/// OpenSea 风格的集合级元数据，即合约 `contractURI` 指向的 JSON
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ContractMetadata {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_link: Option<String>,
+    /// 二级市场版税，单位为万分之一（100 = 1%）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seller_fee_basis_points: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<String>,
+    /// `--from` 文件中的其他字段（如 `banner_image`、`collaborators`）原样保留
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ContractMetadata {
+    /// 读取 `--from` 指定的 JSON 文件作为基础
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read contract metadata {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid contract metadata {:?}", path))
+    }
+
+    /// 按 OpenSea 的要求检查：名称不能为空，版税不超过 100%，设置版税时必须有收款地址
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!(
+                "❌ Contract metadata needs a name (--name or --collection-name)"
+            ));
+        }
+        if let Some(fee) = self.seller_fee_basis_points {
+            if fee > MAX_BASIS_POINTS {
+                return Err(anyhow!(
+                    "❌ seller_fee_basis_points is {}, the maximum is {} (100%)",
+                    fee,
+                    MAX_BASIS_POINTS
+                ));
+            }
+            if fee > 0 && self.fee_recipient.is_none() {
+                return Err(anyhow!(
+                    "❌ seller_fee_basis_points is set but fee_recipient is missing, marketplaces would have nowhere to send the fees"
+                ));
+            }
+        }
+        if let Some(recipient) = &self.fee_recipient
+            && !is_eth_address(recipient)
+        {
+            return Err(anyhow!(
+                "❌ fee_recipient {:?} is not an Ethereum address (0x followed by 40 hex digits)",
+                recipient
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 10000 个基点即 100%
+const MAX_BASIS_POINTS: u32 = 10_000;
+
+fn is_eth_address(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// 图片在上传的图片文件夹中的路径：相对 `root`、用 `/` 分隔，如 `sub/42.png`
+pub(crate) fn image_path_in_folder(root: &Path, image_file: &Path) -> Result<String> {
+    let relative = image_file.strip_prefix(root).with_context(|| {
+        format!(
+            "{} is outside the images folder {}",
+            image_file.display(),
+            root.display()
+        )
+    })?;
+    relative
+        .components()
+        .map(|c| {
+            c.as_os_str()
+                .to_str()
+                .ok_or_else(|| anyhow!("Invalid filename: {}", image_file.display()))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(|parts| parts.join("/"))
+}
+
+/// 元数据中 `image` 字段的来源
+pub(crate) enum ImageSource<'a> {
+    /// 图片作为整个文件夹上传：`uri` 为文件夹 URI（如 `ipfs://<folder_cid>`），`root` 为上传的本地目录，
+    /// 图片 URI 为 `<uri>/<相对 root 的路径>`，子目录中的图片为 `<uri>/sub/42.png`；
+    /// `thumbnails` 为每张图片缩略图的 URI，没有缩略图的图片不写 `thumbnail`
+    Folder {
+        uri: &'a str,
+        root: &'a Path,
+        thumbnails: Option<&'a HashMap<PathBuf, String>>,
+    },
+    /// 每张图片单独上传，值为每张图片的 URI（如 `ipfs://<image_cid>`）
+    PerFile(&'a HashMap<PathBuf, String>),
+}
+
+impl ImageSource<'_> {
+    fn uri_for(&self, image_file: &Path) -> Result<String> {
+        match self {
+            ImageSource::Folder { uri, root, .. } => Ok(format!(
+                "{}/{}",
+                uri,
+                image_path_in_folder(root, image_file)?
+            )),
+            ImageSource::PerFile(image_uris) => image_uris
+                .get(image_file)
+                .cloned()
+                .ok_or_else(|| anyhow!("No image URI recorded for {}", image_file.display())),
+        }
+    }
+
+    fn thumbnail_for(&self, image_file: &Path) -> Option<String> {
+        match self {
+            ImageSource::Folder {
+                thumbnails: Some(thumbnails),
+                ..
+            } => thumbnails.get(image_file).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// 按元数据文件的扩展名选择格式：`.yaml` / `.yml` 为 YAML，其余（包括不带后缀）为 JSON
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+}
+
+/// 写入 `path` 的元数据格式名称，用于日志
+pub(crate) fn metadata_format(path: &Path) -> &'static str {
+    if is_yaml(path) { "YAML" } else { "JSON" }
+}
+
+/// 序列化要写入 `path` 的元数据
+pub(crate) fn serialize_metadata(path: &Path, metadata: &NftMetadata) -> Result<String> {
+    if is_yaml(path) {
+        Ok(serde_yaml::to_string(metadata)?)
+    } else {
+        Ok(serde_json::to_string_pretty(metadata)?)
+    }
+}
+
+/// 读取本工具写出的元数据文件，格式与 [`serialize_metadata`] 一致
+pub(crate) fn read_metadata_file(path: &Path) -> Result<NftMetadata> {
+    let content = fs::read_to_string(path)?;
+    let metadata = if is_yaml(path) {
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Invalid metadata file {:?}", path))?
+    } else {
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid metadata file {:?}", path))?
+    };
+    Ok(metadata)
+}
+
+/// 先写入 `<name>.tmp` 并落盘，再重命名为目标文件，读取方只会看到完整的文件
+pub(crate) fn write_file_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?;
+    let mut tmp_name = file_name.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {:?} to {:?}", tmp_path, path))?;
+    Ok(())
+}
+
+/// 为每张图片生成 `(元数据文件名, 元数据)`，顺序与 `image_files` 一致
+fn build_documents(
+    image_files: &[PathBuf],
+    images: &ImageSource<'_>,
+    options: &MetadataOptions,
+    with_suffix: bool,
+    is_dual_version: bool,
+) -> Result<Vec<(String, NftMetadata)>> {
+    image_files
+        .iter()
+        .map(|image_file| {
+            let token_id = options.token_id(image_file)?;
+            let token_id_str = token_id.as_str();
+            let mut metadata =
+                options.build_for_image(image_file, token_id_str, images.uri_for(image_file)?)?;
+            metadata.thumbnail = images.thumbnail_for(image_file);
+            let file_name = options.metadata_file_name(token_id_str, with_suffix, is_dual_version);
+            Ok((file_name, metadata))
+        })
+        .collect()
+}
+
+/// 在上传图片之前，用占位的图片文件夹 URI 生成并校验所有元数据：
+/// 属性侧车文件或模板的错误不会等到图片已经被固定后才发现。未设置 schema 时什么也不做
+pub(crate) fn validate_before_upload(
+    image_files: &[PathBuf],
+    images_root: &Path,
+    placeholder_folder_uri: &str,
+    options: &MetadataOptions,
+) -> Result<()> {
+    let Some(schema) = &options.schema else {
+        return Ok(());
+    };
+    let documents = build_documents(
+        image_files,
+        &ImageSource::Folder {
+            uri: placeholder_folder_uri,
+            root: images_root,
+            thumbnails: None,
+        },
+        options,
+        false,
+        false,
+    )?;
+    schema.validate(&documents)?;
+    info!(
+        "✅ All {} metadata files match the metadata schema",
+        documents.len()
+    );
+    Ok(())
+}
+
+/// 为每张图片生成元数据文件，返回与 `image_files` 顺序一致的元数据文件路径
+pub(crate) async fn create_metadata_files(
+    image_files: &[PathBuf],
+    dir: &Path,
+    images: &ImageSource<'_>,
+    options: &MetadataOptions,
+    with_suffix: bool,
+    is_dual_version: bool,
+) -> Result<Vec<PathBuf>> {
+    let documents = build_documents(image_files, images, options, with_suffix, is_dual_version)?;
+    if let Some(schema) = &options.schema {
+        schema.validate(&documents)?;
+    }
+
+    fs::create_dir_all(dir)?;
+
+    let mut metadata_files = Vec::with_capacity(documents.len());
+
+    for (file_name, metadata) in documents {
+        let file_path = dir.join(file_name);
+        write_file_atomically(
+            &file_path,
+            serialize_metadata(&file_path, &metadata)?.as_bytes(),
+        )?;
+
+        info!("📄 Created metadata file: {}", file_path.to_string_lossy());
+        metadata_files.push(file_path);
+    }
+
+    info!(
+        "📁 Created {} metadata files in: {}",
+        metadata_files.len(),
+        dir.to_string_lossy()
+    );
+
+    // Additional verification: check folder size before upload
+    let folder_size = calculate_folder_size(dir)?;
+    let folder_size_mb = folder_size as f64 / 1024.0 / 1024.0;
+    info!(
+        "📁 Metadata folder size before upload: {:.2} MB ({} bytes)",
+        folder_size_mb, folder_size
+    );
+
+    Ok(metadata_files)
+}
+
+pub(crate) fn calculate_folder_size(dir_path: &Path) -> Result<u64> {
+    let mut total_size = 0u64;
+
+    for entry in fs::read_dir(dir_path)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.is_file() {
+            let file_size = fs::metadata(&path)?.len();
+            total_size += file_size;
+        } else if path.is_dir() {
+            total_size += calculate_folder_size(&path)?;
+        }
+    }
+
+    Ok(total_size)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 与命令行默认值一致的选项
+    pub(crate) fn options() -> MetadataOptions {
+        MetadataOptions {
+            collection_name: None,
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            description_template: DEFAULT_DESCRIPTION_TEMPLATE.to_string(),
+            animation_url_template: None,
+            external_url_template: None,
+            background_color: None,
+            attributes_suffix: DEFAULT_ATTRIBUTES_SUFFIX.to_string(),
+            attribute_filter: AttributeFilter::All,
+            id_trait: Some("ID".to_string()),
+            embed_image_info: false,
+            overrides: HashMap::new(),
+            image_extensions: vec!["png".to_string()],
+            metadata_file_suffix: String::new(),
+            string_ids: false,
+            token_ids: TokenIds::FileStem,
+            schema: None,
+        }
+    }
+
+    /// 在 `dir` 中写入 `<stem>.png`，`sidecar` 不为 `None` 时同时写入它的侧车文件
+    fn image_with_sidecar(dir: &Path, stem: &str, sidecar: Option<&str>) -> PathBuf {
+        let image = dir.join(format!("{}.png", stem));
+        fs::write(&image, b"png").unwrap();
+        if let Some(sidecar) = sidecar {
+            fs::write(dir.join(format!("{}.attributes.json", stem)), sidecar).unwrap();
+        }
+        image
+    }
+
+    fn trait_types(metadata: &NftMetadata) -> Vec<&str> {
+        metadata
+            .attributes
+            .iter()
+            .map(|a| a.trait_type.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn attribute_filter_applies_to_sidecar_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = image_with_sidecar(
+            dir.path(),
+            "1",
+            Some(
+                r#"[{"trait_type": "Color", "value": "Red"}, {"trait_type": "Cost", "value": 3}]"#,
+            ),
+        );
+
+        let deny = MetadataOptions {
+            attribute_filter: AttributeFilter::new(vec![], vec!["COST".to_string()]),
+            ..options()
+        };
+        let metadata = deny
+            .build_for_image(&image, "1", "ipfs://x".into())
+            .unwrap();
+        assert_eq!(trait_types(&metadata), ["ID", "Color"]);
+
+        // token ID 属性不受 allowlist 影响
+        let allow = MetadataOptions {
+            attribute_filter: AttributeFilter::new(vec!["cost".to_string()], vec![]),
+            ..options()
+        };
+        let metadata = allow
+            .build_for_image(&image, "1", "ipfs://x".into())
+            .unwrap();
+        assert_eq!(trait_types(&metadata), ["ID", "Cost"]);
+    }
+
+    #[test]
+    fn sidecar_background_color_overrides_the_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let with_color = image_with_sidecar(
+            dir.path(),
+            "1",
+            Some(r#"{"attributes": [], "background_color": "ffffff"}"#),
+        );
+        let without = image_with_sidecar(dir.path(), "2", None);
+        let options = MetadataOptions {
+            background_color: Some("000000".to_string()),
+            ..options()
+        };
+
+        // batch 模式
+        let documents = build_documents(
+            &[with_color.clone(), without.clone()],
+            &ImageSource::Folder {
+                uri: "ipfs://folder",
+                root: dir.path(),
+                thumbnails: None,
+            },
+            &options,
+            false,
+            false,
+        )
+        .unwrap();
+        let colors: Vec<_> = documents
+            .iter()
+            .map(|(_, metadata)| metadata.background_color.as_deref())
+            .collect();
+        assert_eq!(colors, [Some("ffffff"), Some("000000")]);
+
+        // single 模式直接调用 build_for_image
+        let single = |image: &Path| {
+            options
+                .build_for_image(image, "1", "ipfs://image".into())
+                .unwrap()
+                .background_color
+        };
+        assert_eq!(single(&with_color).as_deref(), Some("ffffff"));
+        assert_eq!(single(&without).as_deref(), Some("000000"));
+    }
+
+    #[test]
+    fn invalid_sidecar_background_color_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = image_with_sidecar(
+            dir.path(),
+            "1",
+            Some(r##"{"background_color": "#ffffff"}"##),
+        );
+        let error = options()
+            .build_for_image(&image, "1", "ipfs://x".into())
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("must not start with #"),
+            "{:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn parses_background_colors() {
+        assert_eq!(parse_background_color("1a2B3c").as_deref(), Ok("1a2B3c"));
+        for invalid in ["", "fff", "1a2b3c4", "12345g", "#1a2b3c"] {
+            assert!(parse_background_color(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    /// 所有可选字段都设置了的元数据，用于锁定键顺序和格式
+    fn full_metadata() -> NftMetadata {
+        NftMetadata {
+            name: "MetaCore #1".to_string(),
+            description: "A unique member of the MetaCore collection.".to_string(),
+            image: "ipfs://bafyimages/1.png".to_string(),
+            animation_url: Some("https://example.com/1.mp4".to_string()),
+            external_url: Some("https://example.com/token/1".to_string()),
+            thumbnail: Some("ipfs://bafythumbnails/1.png".to_string()),
+            background_color: Some("1a2b3c".to_string()),
+            attributes: vec![
+                Attribute {
+                    trait_type: "ID".to_string(),
+                    value: 1.into(),
+                    display_type: None,
+                },
+                Attribute {
+                    trait_type: "Stamina".to_string(),
+                    value: 87.into(),
+                    display_type: Some(DisplayType::BoostPercentage),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn json_key_order_and_format_are_stable() {
+        let golden = r#"{
+  "name": "MetaCore #1",
+  "description": "A unique member of the MetaCore collection.",
+  "image": "ipfs://bafyimages/1.png",
+  "animation_url": "https://example.com/1.mp4",
+  "external_url": "https://example.com/token/1",
+  "thumbnail": "ipfs://bafythumbnails/1.png",
+  "background_color": "1a2b3c",
+  "attributes": [
+    {
+      "trait_type": "ID",
+      "value": 1
+    },
+    {
+      "trait_type": "Stamina",
+      "value": 87,
+      "display_type": "boost_percentage"
+    }
+  ]
+}"#;
+        let json = serialize_metadata(Path::new("1.json"), &full_metadata()).unwrap();
+        assert_eq!(json, golden);
+    }
+
+    #[test]
+    fn unset_optional_fields_are_omitted() {
+        let metadata = NftMetadata {
+            animation_url: None,
+            external_url: None,
+            thumbnail: None,
+            background_color: None,
+            attributes: Vec::new(),
+            ..full_metadata()
+        };
+        let json = serialize_metadata(Path::new("1"), &metadata).unwrap();
+        assert_eq!(
+            json,
+            r#"{
+  "name": "MetaCore #1",
+  "description": "A unique member of the MetaCore collection.",
+  "image": "ipfs://bafyimages/1.png",
+  "attributes": []
+}"#
+        );
+    }
+
+    #[test]
+    fn yaml_round_trips_to_the_same_metadata_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = full_metadata();
+        let as_json =
+            |metadata: &NftMetadata| serialize_metadata(Path::new("1.json"), metadata).unwrap();
+
+        for name in ["1.json", "1.yaml", "1.yml"] {
+            let path = dir.path().join(name);
+            write_file_atomically(
+                &path,
+                serialize_metadata(&path, &metadata).unwrap().as_bytes(),
+            )
+            .unwrap();
+            let read_back = read_metadata_file(&path).unwrap();
+            assert_eq!(as_json(&read_back), as_json(&metadata), "{}", name);
+        }
+        let yaml = fs::read_to_string(dir.path().join("1.yaml")).unwrap();
+        assert!(yaml.starts_with("name: 'MetaCore #1'\n"), "{}", yaml);
+    }
+
+    #[test]
+    fn folder_image_uris_keep_subdirectories() {
+        let root = Path::new("assets").join("batch_images");
+        // 用 `join` 拼接路径，Windows 上分隔符为 `\`，URI 中仍然是 `/`
+        let nested = root.join("sub").join("42.png");
+        let images = ImageSource::Folder {
+            uri: "ipfs://bafyfolder",
+            root: &root,
+            thumbnails: None,
+        };
+        assert_eq!(
+            images.uri_for(&nested).unwrap(),
+            "ipfs://bafyfolder/sub/42.png"
+        );
+        assert_eq!(
+            images.uri_for(&root.join("1.png")).unwrap(),
+            "ipfs://bafyfolder/1.png"
+        );
+        assert_eq!(
+            image_path_in_folder(&root, &root.join("a").join("b").join("7.png")).unwrap(),
+            "a/b/7.png"
+        );
+        assert!(image_path_in_folder(&root, Path::new("elsewhere/1.png")).is_err());
+    }
+
+    /// 目录中的文件名，按名称排序
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_target_without_leaving_a_tmp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.json");
+        fs::write(&path, "old").unwrap();
+
+        write_file_atomically(&path, b"new contents").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new contents");
+        assert_eq!(file_names(dir.path()), ["1.json"]);
+    }
+
+    #[tokio::test]
+    async fn created_metadata_files_are_complete_and_readable() {
+        let images = tempfile::tempdir().unwrap();
+        let image_files: Vec<PathBuf> = ["1", "2", "3"]
+            .iter()
+            .map(|stem| image_with_sidecar(images.path(), stem, None))
+            .collect();
+        let output = tempfile::tempdir().unwrap();
+        let dir = output.path().join("metadata");
+        let options = MetadataOptions {
+            metadata_file_suffix: ".yaml".to_string(),
+            ..options()
+        };
+
+        let files = create_metadata_files(
+            &image_files,
+            &dir,
+            &ImageSource::Folder {
+                uri: "ipfs://bafyfolder",
+                root: images.path(),
+                thumbnails: None,
+            },
+            &options,
+            true,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(files.len(), 3);
+        for (index, file) in files.iter().enumerate() {
+            let metadata = read_metadata_file(file).unwrap();
+            let token_id = index + 1;
+            assert_eq!(metadata.name, format!("MetaCore #{}", token_id));
+            assert_eq!(
+                metadata.image,
+                format!("ipfs://bafyfolder/{}.png", token_id)
+            );
+        }
+        assert_eq!(file_names(&dir), ["1.yaml", "2.yaml", "3.yaml"]);
+    }
+}
@@ -0,0 +1,195 @@
+//! NFT.Storage 存储后端：免费的 IPFS 固定服务，返回的 CID 与 Pinata 的一样可以直接写入元数据
+
+use crate::config::{HttpOptions, NFT_STORAGE_API_URL, NFT_STORAGE_GATEWAY_URL, RetryPolicy};
+use crate::pinata::{HttpError, validate_cid};
+use crate::storage::{StorageBackend, gateway_urls};
+use crate::upload::fetch_directory_listing;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 创建 `NftStorageClient` 时的可配置项
+#[derive(Debug, Clone)]
+pub struct NftStorageOptions {
+    pub retry: RetryPolicy,
+    pub http: HttpOptions,
+    /// 生成链接和 `--verify` 回读使用的 IPFS 网关（包含 `/ipfs` 路径），默认为 nftstorage.link
+    pub gateway: Option<String>,
+    /// 读取内容时在 `gateway` 之后依次尝试的网关
+    pub fallback_gateways: Vec<String>,
+}
+
+/// `POST /upload` 的响应：`{"ok": true, "value": {"cid": "bafy..."}}`
+#[derive(Deserialize)]
+struct UploadResponse {
+    value: UploadValue,
+}
+
+#[derive(Deserialize)]
+struct UploadValue {
+    cid: String,
+}
+
+pub struct NftStorageClient {
+    client: reqwest::Client,
+    /// dry-run 模式下所有上传都会被跳过并返回占位 CID
+    dry_run: bool,
+    retry: RetryPolicy,
+    http_options: HttpOptions,
+    /// 网关地址，不带末尾的 `/`
+    gateway: String,
+    fallback_gateways: Vec<String>,
+}
+
+impl NftStorageClient {
+    pub fn new(token: &str, options: NftStorageOptions) -> Result<Self> {
+        let mut header = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| anyhow!("NFT_STORAGE_TOKEN contains invalid characters"))?;
+        header.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, header);
+
+        let client = options
+            .http
+            .client_builder()
+            .default_headers(headers)
+            .build()?;
+        Ok(Self::with_options(client, false, options))
+    }
+
+    /// 从 `NFT_STORAGE_TOKEN` 环境变量读取 API token
+    pub fn from_env(options: NftStorageOptions) -> Result<Self> {
+        let token = env::var("NFT_STORAGE_TOKEN")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| anyhow!("Please set NFT_STORAGE_TOKEN in .env file"))?;
+        Self::new(&token, options)
+    }
+
+    /// 不带凭证的客户端，只用于 dry-run
+    pub fn dry_run(options: NftStorageOptions) -> Self {
+        Self::with_options(options.http.client(), true, options)
+    }
+
+    fn with_options(client: reqwest::Client, dry_run: bool, options: NftStorageOptions) -> Self {
+        NftStorageClient {
+            client,
+            dry_run,
+            retry: options.retry,
+            http_options: options.http,
+            gateway: options
+                .gateway
+                .as_deref()
+                .unwrap_or(NFT_STORAGE_GATEWAY_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            fallback_gateways: options
+                .fallback_gateways
+                .iter()
+                .map(|gateway| gateway.trim_end_matches('/').to_string())
+                .collect(),
+        }
+    }
+
+    /// 单个文件直接作为请求体上传；多个文件以 multipart 上传，NFT.Storage 会把它们包装成一个文件夹
+    async fn upload(&self, body: UploadBody) -> Result<String> {
+        let request = self.client.post(format!("{}/upload", NFT_STORAGE_API_URL));
+        let request = match body {
+            UploadBody::File(bytes) => request.body(bytes),
+            UploadBody::Directory(form) => request.multipart(form),
+        };
+        let response = request.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let body = response.text().await.unwrap_or_default();
+            // 错误响应：`{"ok": false, "error": {"name": "...", "message": "..."}}`
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|json| json["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            return Err(HttpError::new(
+                status,
+                &headers,
+                format!("NFT.Storage API returned HTTP {}: {}", status, message),
+            )
+            .into());
+        }
+
+        let uploaded: UploadResponse = response.json().await?;
+        Ok(uploaded.value.cid)
+    }
+}
+
+enum UploadBody {
+    File(Vec<u8>),
+    Directory(Form),
+}
+
+#[async_trait]
+impl StorageBackend for NftStorageClient {
+    fn name(&self) -> &'static str {
+        "NFT.Storage"
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry.clone()
+    }
+
+    fn http_options(&self) -> &HttpOptions {
+        &self.http_options
+    }
+
+    /// NFT.Storage 总是返回 CIDv1，不支持 `--cid-version`
+    fn cid_version(&self) -> Option<u8> {
+        Some(1)
+    }
+
+    async fn upload_file(&self, path: &Path) -> Result<String> {
+        self.upload(UploadBody::File(fs::read(path)?)).await
+    }
+
+    /// 文件以相对路径命名，文件夹 CID 的第一层就是 `dir` 的内容
+    async fn upload_directory(&self, dir: &Path, files: &[PathBuf]) -> Result<String> {
+        let mut form = Form::new();
+        for path in files {
+            let relative_path = path.strip_prefix(dir)?;
+            form = form.part(
+                "file",
+                Part::bytes(fs::read(path)?)
+                    .file_name(relative_path.to_string_lossy().into_owned()),
+            );
+        }
+        self.upload(UploadBody::Directory(form)).await
+    }
+
+    fn uri_scheme(&self) -> &'static str {
+        "ipfs"
+    }
+
+    fn gateway_url(&self, cid_path: &str) -> String {
+        format!("{}/{}", self.gateway, cid_path)
+    }
+
+    fn fetch_urls(&self, cid_path: &str) -> Vec<String> {
+        gateway_urls(&self.gateway, &self.fallback_gateways, cid_path)
+    }
+
+    fn validate_id(&self, cid: &str) -> Result<()> {
+        validate_cid(cid)
+    }
+
+    async fn list_directory(&self, cid: &str) -> Result<Vec<String>> {
+        fetch_directory_listing(self, cid).await
+    }
+}
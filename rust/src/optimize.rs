@@ -0,0 +1,157 @@
+//! `--optimize-images`：上传前把 PNG 无损压缩（oxipng）、把 JPEG 按指定质量重新编码，
+//! 优化后的副本写入临时目录并代替原图上传，磁盘上的原始文件不会被修改。
+//! 依赖 `oxipng` 和 `image`，由默认开启的 `image-optimize` feature 控制
+
+#[cfg(feature = "image-optimize")]
+use crate::config::format_size;
+use anyhow::Result;
+#[cfg(feature = "image-optimize")]
+use anyhow::{Context, anyhow};
+#[cfg(feature = "image-optimize")]
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "image-optimize")]
+use tracing::{debug, info, warn};
+
+/// 未指定 `--jpeg-quality` 时重新编码 JPEG 使用的质量
+pub const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// 优化后的图片副本，与原图一一对应（顺序相同、相对路径相同）；临时目录随该值一起删除
+pub(crate) struct OptimizedImages {
+    dir: tempfile::TempDir,
+    pub(crate) files: Vec<PathBuf>,
+    /// 所有副本比原图少的字节数
+    pub(crate) bytes_saved: u64,
+}
+
+impl OptimizedImages {
+    /// 副本所在的目录，结构与原来的图片目录一致，可以直接作为文件夹上传
+    pub(crate) fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// 优化一张图片，返回要上传的内容；无法优化的格式返回 `None`
+#[cfg(feature = "image-optimize")]
+fn optimize_bytes(path: &Path, original: &[u8], jpeg_quality: u8) -> Result<Option<Vec<u8>>> {
+    use image::ImageFormat;
+
+    match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Png) => {
+            let mut options = oxipng::Options::from_preset(2);
+            // 只去掉不影响显示的辅助块（文本、时间等）
+            options.strip = oxipng::StripChunks::Safe;
+            let optimized = oxipng::optimize_from_memory(original, &options)
+                .map_err(|e| anyhow!("oxipng failed: {}", e))?;
+            Ok(Some(optimized))
+        }
+        Ok(ImageFormat::Jpeg) => {
+            use image::codecs::jpeg::JpegEncoder;
+
+            let image = image::load_from_memory_with_format(original, ImageFormat::Jpeg)
+                .map_err(|e| anyhow!("cannot be decoded: {}", e))?;
+            let mut encoded = Vec::new();
+            JpegEncoder::new_with_quality(&mut encoded, jpeg_quality)
+                .encode_image(&image)
+                .map_err(|e| anyhow!("JPEG re-encoding failed: {}", e))?;
+            Ok(Some(encoded))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// 把 `image_files`（位于 `root` 下）的优化副本写入临时目录；结果更大或无法优化的图片原样复制
+#[cfg(feature = "image-optimize")]
+pub(crate) fn optimize_images(
+    image_files: &[PathBuf],
+    root: &Path,
+    jpeg_quality: u8,
+) -> Result<OptimizedImages> {
+    info!(
+        "🗜️  Optimizing {} images (lossless PNG, JPEG quality {})...",
+        image_files.len(),
+        jpeg_quality
+    );
+    let dir = tempfile::Builder::new()
+        .prefix("optimized-images-")
+        .tempdir()?;
+    let mut files = Vec::with_capacity(image_files.len());
+    let mut original_total = 0u64;
+    let mut optimized_total = 0u64;
+    let mut skipped = 0usize;
+
+    for path in image_files {
+        let relative = path
+            .strip_prefix(root)
+            .with_context(|| format!("{:?} is not inside {:?}", path, root))?;
+        let target = dir.path().join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let original = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let content = match optimize_bytes(path, &original, jpeg_quality) {
+            Ok(Some(optimized)) if optimized.len() < original.len() => {
+                debug!(
+                    "🗜️  {}: {} → {} bytes",
+                    relative.display(),
+                    original.len(),
+                    optimized.len()
+                );
+                optimized
+            }
+            Ok(Some(_)) => original.clone(),
+            Ok(None) => {
+                skipped += 1;
+                original.clone()
+            }
+            Err(e) => {
+                warn!(
+                    "⚠️  Cannot optimize {}, uploading it unchanged: {:#}",
+                    relative.display(),
+                    e
+                );
+                original.clone()
+            }
+        };
+        fs::write(&target, &content)?;
+        original_total += original.len() as u64;
+        optimized_total += content.len() as u64;
+        files.push(target);
+    }
+
+    let bytes_saved = original_total - optimized_total;
+    if skipped > 0 {
+        info!(
+            "⏭️  {} images are neither PNG nor JPEG, uploading them unchanged",
+            skipped
+        );
+    }
+    info!(
+        "🗜️  Optimized images: {} → {}, saved {} ({:.1}%)",
+        format_size(original_total),
+        format_size(optimized_total),
+        format_size(bytes_saved),
+        if original_total == 0 {
+            0.0
+        } else {
+            bytes_saved as f64 * 100.0 / original_total as f64
+        }
+    );
+    Ok(OptimizedImages {
+        dir,
+        files,
+        bytes_saved,
+    })
+}
+
+#[cfg(not(feature = "image-optimize"))]
+pub(crate) fn optimize_images(
+    _image_files: &[PathBuf],
+    _root: &Path,
+    _jpeg_quality: u8,
+) -> Result<OptimizedImages> {
+    Err(anyhow::anyhow!(
+        "--optimize-images is not available: this binary was built without the image-optimize feature"
+    ))
+}
@@ -0,0 +1,942 @@
+//! Pinata REST API 客户端与认证
+
+use crate::config::{HttpOptions, PINATA_API_URL, PINATA_UPLOADS_URL, RetryPolicy};
+use crate::storage::{StorageBackend, content_type, gateway_urls};
+use crate::upload::fetch_directory_listing;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use pinata_sdk::{
+    MetadataValue, PinByHashResult, PinJobs, PinJobsFilter, PinList, PinListFilter, PinMetadata,
+    PinPolicy, PinnedObject, RegionPolicy, TotalPinnedData,
+};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, RETRY_AFTER};
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+// --- Pinata 客户端 ---
+/// Pinata 认证方式，JWT 优先于 API Key + Secret
+pub enum PinataAuth {
+    Jwt(String),
+    ApiKey { api_key: String, secret_key: String },
+}
+
+impl PinataAuth {
+    pub fn from_env() -> Result<Self> {
+        let read = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let jwt = read("PINATA_JWT");
+        let api_key = read("PINATA_API_KEY");
+        let secret_key = read("PINATA_SECRET_KEY");
+
+        match (jwt, api_key, secret_key) {
+            (Some(jwt), api_key, secret_key) => {
+                if api_key.is_some() || secret_key.is_some() {
+                    info!("🔑 Both PINATA_JWT and API key/secret are set, using JWT");
+                } else {
+                    info!("🔑 Using JWT authentication (PINATA_JWT)");
+                }
+                Ok(PinataAuth::Jwt(jwt))
+            }
+            (None, Some(api_key), Some(secret_key)) => {
+                info!("🔑 Using API key authentication (PINATA_API_KEY + PINATA_SECRET_KEY)");
+                Ok(PinataAuth::ApiKey {
+                    api_key,
+                    secret_key,
+                })
+            }
+            (None, Some(_), None) => Err(anyhow!(
+                "Please set PINATA_SECRET_KEY in .env file (or use PINATA_JWT instead)"
+            )),
+            (None, None, Some(_)) => Err(anyhow!(
+                "Please set PINATA_API_KEY in .env file (or use PINATA_JWT instead)"
+            )),
+            (None, None, None) => Err(anyhow!(
+                "Please set PINATA_JWT, or PINATA_API_KEY and PINATA_SECRET_KEY, in .env file"
+            )),
+        }
+    }
+
+    fn headers(&self) -> Result<HeaderMap> {
+        let sensitive = |value: &str| -> Result<HeaderValue> {
+            let mut header = HeaderValue::from_str(value)
+                .map_err(|_| anyhow!("Pinata credentials contain invalid characters"))?;
+            header.set_sensitive(true);
+            Ok(header)
+        };
+
+        let mut headers = HeaderMap::new();
+        match self {
+            PinataAuth::Jwt(jwt) => {
+                headers.insert(AUTHORIZATION, sensitive(&format!("Bearer {}", jwt))?);
+            }
+            PinataAuth::ApiKey {
+                api_key,
+                secret_key,
+            } => {
+                headers.insert("pinata_api_key", sensitive(api_key)?);
+                headers.insert("pinata_secret_api_key", sensitive(secret_key)?);
+            }
+        }
+        Ok(headers)
+    }
+}
+
+/// 附加到每个 pin 上的 `pinataMetadata`，便于在 Pinata 控制台中查找
+#[derive(Debug, Clone)]
+pub struct PinLabels {
+    /// pin 名称前缀（集合/本次运行的名称），实际名称为 `<run_name>/<文件或文件夹名>`
+    pub run_name: String,
+    pub keyvalues: BTreeMap<String, String>,
+}
+
+/// 上传时附带的 `pinataOptions`
+#[derive(Serialize, Debug)]
+struct PinataOptions {
+    #[serde(rename = "cidVersion")]
+    cid_version: u8,
+    #[serde(rename = "groupId", skip_serializing_if = "Option::is_none")]
+    group_id: Option<String>,
+    #[serde(rename = "customPinPolicy", skip_serializing_if = "Option::is_none")]
+    custom_pin_policy: Option<PinPolicy>,
+}
+
+/// `pinByHash` 的请求体；SDK 的 `PinByHash` 把选项序列化为 `pinataOption`，Pinata 不认这个字段
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PinByHashRequest<'a> {
+    hash_to_pin: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pinata_options: Option<HashPinOptions>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HashPinOptions {
+    custom_pin_policy: PinPolicy,
+}
+
+/// v3 API 中的 pin 分组；旧的 `pinFileToIPFS` 上传属于公共网络（public）的分组
+#[derive(Deserialize, Debug, Clone)]
+struct PinGroup {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GroupResponse {
+    data: PinGroup,
+}
+
+/// v3 上传接口的响应，只关心其中的 CID
+#[derive(Deserialize)]
+struct UploadResponse {
+    data: UploadedFile,
+}
+
+#[derive(Deserialize)]
+struct UploadedFile {
+    cid: String,
+}
+
+#[derive(Deserialize)]
+struct GroupListResponse {
+    data: GroupList,
+}
+
+#[derive(Deserialize)]
+struct GroupList {
+    #[serde(default)]
+    groups: Vec<PinGroup>,
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+/// 创建 `PinataClient` 时的可配置项
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub retry: RetryPolicy,
+    pub http: HttpOptions,
+    pub gateway: String,
+    /// 读取内容时在 `gateway` 之后依次尝试的网关
+    pub fallback_gateways: Vec<String>,
+    pub pin_labels: PinLabels,
+    pub cid_version: u8,
+    /// `--replication-region`：自定义 pin 策略，为空时使用账户的默认策略
+    pub replication_regions: Vec<RegionPolicy>,
+}
+
+/// 同一区域只能指定一次，并且至少要保留一个副本
+pub fn check_replication_regions(regions: &[RegionPolicy]) -> Result<()> {
+    let mut seen = Vec::new();
+    for region in regions {
+        let id = format!("{:?}", region.id);
+        if seen.contains(&id) {
+            return Err(anyhow!(
+                "❌ --replication-region {} is given more than once",
+                id
+            ));
+        }
+        seen.push(id);
+    }
+    if !regions.is_empty()
+        && regions
+            .iter()
+            .all(|region| region.desired_replication_count == 0)
+    {
+        return Err(anyhow!(
+            "❌ --replication-region needs at least one region with a replication count above 0"
+        ));
+    }
+    Ok(())
+}
+
+/// Pinata API 或网关返回的非 2xx 响应，保留状态码以便区分可重试的错误
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: reqwest::StatusCode,
+    pub message: String,
+    /// 服务端通过 `Retry-After` 要求的最短等待时间（通常随 429 返回）
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpError {
+    pub(crate) fn new(status: reqwest::StatusCode, headers: &HeaderMap, message: String) -> Self {
+        HttpError {
+            status,
+            message,
+            retry_after: parse_retry_after(headers),
+        }
+    }
+
+    /// 限流（429）、请求超时（408）和服务端错误（5xx）通常是暂时的，其余 4xx 重试也不会成功
+    pub fn is_transient(&self) -> bool {
+        self.status.is_server_error()
+            || self.status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || self.status == reqwest::StatusCode::REQUEST_TIMEOUT
+    }
+}
+
+/// `Retry-After` 可以是秒数，也可以是 HTTP 日期（如 `Wed, 21 Oct 2015 07:28:00 GMT`）
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    // 日期已经过去时无需额外等待
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Pinata REST API 的轻量封装（SDK 只支持 API Key 认证）
+#[derive(Clone)]
+pub struct PinataClient {
+    client: reqwest::Client,
+    /// dry-run 模式下所有上传都会被跳过并返回占位 CID
+    pub(crate) dry_run: bool,
+    pub(crate) retry: RetryPolicy,
+    http_options: HttpOptions,
+    /// 网关地址，不带末尾的 `/`
+    gateway: String,
+    fallback_gateways: Vec<String>,
+    pin_labels: PinLabels,
+    /// 上传返回的 CID 版本：0 为 `Qm...`，1 为 base32 `bafy...`
+    pub(crate) cid_version: u8,
+    /// `--group` 解析出的分组 ID，所有上传都会加入该分组
+    group_id: Option<String>,
+    replication_regions: Vec<RegionPolicy>,
+    /// REST API 和 v3 上传接口的地址，不带末尾的 `/`
+    api_url: String,
+    uploads_url: String,
+}
+
+impl PinataClient {
+    pub fn new(auth: &PinataAuth, options: ClientOptions) -> Result<Self> {
+        let client = options
+            .http
+            .client_builder()
+            .default_headers(auth.headers()?)
+            .build()?;
+        Ok(Self::with_options(client, false, options))
+    }
+
+    /// 不带凭证的客户端，只用于 dry-run
+    pub fn dry_run(options: ClientOptions) -> Self {
+        Self::with_options(options.http.client(), true, options)
+    }
+
+    fn with_options(client: reqwest::Client, dry_run: bool, options: ClientOptions) -> Self {
+        PinataClient {
+            client,
+            dry_run,
+            retry: options.retry,
+            http_options: options.http,
+            gateway: options.gateway.trim_end_matches('/').to_string(),
+            fallback_gateways: options
+                .fallback_gateways
+                .iter()
+                .map(|gateway| gateway.trim_end_matches('/').to_string())
+                .collect(),
+            pin_labels: options.pin_labels,
+            cid_version: options.cid_version,
+            group_id: None,
+            replication_regions: options.replication_regions,
+            api_url: PINATA_API_URL.to_string(),
+            uploads_url: PINATA_UPLOADS_URL.to_string(),
+        }
+    }
+
+    /// 把 API 和上传接口都指向 `base_url`，测试中用来连接本地的模拟服务
+    #[cfg(test)]
+    pub(crate) fn with_base_url(mut self, base_url: &str) -> Self {
+        self.api_url = base_url.trim_end_matches('/').to_string();
+        self.uploads_url = self.api_url.clone();
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.api_url, path)
+    }
+
+    pub async fn test_authentication(&self) -> Result<()> {
+        let response = self
+            .client
+            .get(self.url("/data/testAuthentication"))
+            .send()
+            .await?;
+        Self::check_status(response).await.map(|_| ())
+    }
+
+    /// 让之后的上传加入 `group` 分组：UUID 直接作为分组 ID，否则按名称精确查找，
+    /// 找不到时 `create` 为 `true` 则新建同名分组
+    pub async fn join_group(&mut self, group: &str, create: bool) -> Result<()> {
+        if self.dry_run {
+            info!("🧪 Dry run: not resolving Pinata group {}", group);
+            return Ok(());
+        }
+        let group = if is_group_id(group) {
+            PinGroup {
+                id: group.to_string(),
+                name: group.to_string(),
+            }
+        } else if let Some(existing) = self.find_group(group).await? {
+            existing
+        } else if create {
+            let created = self.create_group(group).await?;
+            info!("🗂️  Created Pinata group {} ({})", created.name, created.id);
+            created
+        } else {
+            return Err(anyhow!(
+                "No Pinata group named {:?} (pass --create-group to create it)",
+                group
+            ));
+        };
+        if group.name == group.id {
+            info!("🗂️  Uploads will be added to Pinata group {}", group.id);
+        } else {
+            info!(
+                "🗂️  Uploads will be added to Pinata group {} ({})",
+                group.name, group.id
+            );
+        }
+        self.group_id = Some(group.id);
+        Ok(())
+    }
+
+    /// 名称过滤是模糊匹配，逐页查找名称完全相同的分组
+    async fn find_group(&self, name: &str) -> Result<Option<PinGroup>> {
+        let mut page_token = None;
+        loop {
+            let mut request = self
+                .client
+                .get(self.url("/v3/groups/public"))
+                .query(&[("name", name)]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+            let response = request.send().await?;
+            let page: GroupListResponse = Self::check_status(response).await?.json().await?;
+            if let Some(group) = page.data.groups.into_iter().find(|g| g.name == name) {
+                return Ok(Some(group));
+            }
+            match page.data.next_page_token.filter(|t| !t.is_empty()) {
+                Some(token) => page_token = Some(token),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    async fn create_group(&self, name: &str) -> Result<PinGroup> {
+        let response = self
+            .client
+            .post(self.url("/v3/groups/public"))
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .await?;
+        let created: GroupResponse = Self::check_status(response).await?.json().await?;
+        Ok(created.data)
+    }
+
+    /// 上传单个文件或整个文件夹，文件夹会返回其根 CID
+    async fn pin_file(&self, file_or_dir: &Path) -> Result<PinnedObject> {
+        if file_or_dir.is_dir() {
+            let mut files = Vec::new();
+            for entry in WalkDir::new(file_or_dir) {
+                let entry = entry?;
+                if entry.path().is_file() {
+                    files.push(entry.into_path());
+                }
+            }
+            return self.pin_directory_files(file_or_dir, &files).await;
+        }
+
+        let file_name = file_or_dir
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid file path"))?
+            .to_string();
+        let form = Form::new().part("file", file_part(file_or_dir, file_name.clone())?);
+        self.pin_form(form, &file_name).await
+    }
+
+    /// 以 `<文件夹名>/<相对路径>` 命名上传 `dir` 下的指定文件，只有列出的文件会进入文件夹 CID
+    async fn pin_directory_files(&self, dir: &Path, files: &[PathBuf]) -> Result<PinnedObject> {
+        let dir_name = dir
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid folder path"))?;
+
+        let mut form = Form::new();
+        for path in files {
+            let relative_path = path.strip_prefix(dir)?;
+            let part_name = format!("{}/{}", dir_name, relative_path.to_string_lossy());
+            form = form.part("file", file_part(path, part_name)?);
+        }
+        self.pin_form(form, dir_name).await
+    }
+
+    fn pin_metadata(&self, name: &str) -> PinMetadata {
+        PinMetadata {
+            name: Some(format!("{}/{}", self.pin_labels.run_name, name)),
+            keyvalues: self
+                .pin_labels
+                .keyvalues
+                .iter()
+                .map(|(key, value)| (key.clone(), MetadataValue::String(value.clone())))
+                .collect(),
+        }
+    }
+
+    async fn pin_form(&self, form: Form, name: &str) -> Result<PinnedObject> {
+        let options = PinataOptions {
+            cid_version: self.cid_version,
+            group_id: self.group_id.clone(),
+            custom_pin_policy: self.custom_pin_policy(),
+        };
+        let form = form
+            .text(
+                "pinataMetadata",
+                serde_json::to_string(&self.pin_metadata(name))?,
+            )
+            .text("pinataOptions", serde_json::to_string(&options)?);
+        let response = self
+            .client
+            .post(self.url("/pinning/pinFileToIPFS"))
+            .multipart(form)
+            .send()
+            .await?;
+        Ok(Self::check_status(response).await?.json().await?)
+    }
+
+    fn custom_pin_policy(&self) -> Option<PinPolicy> {
+        (!self.replication_regions.is_empty()).then(|| PinPolicy {
+            regions: self.replication_regions.clone(),
+        })
+    }
+
+    /// 通过 v3 上传接口上传 CAR 文件（`car=true`）：Pinata 直接导入其中的块，返回根 CID。
+    /// pin 名称、自定义标签和 `--group` 与其他上传一致
+    pub(crate) async fn upload_car(&self, path: &Path) -> Result<String> {
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid file path"))?
+            .to_string();
+        if !self.replication_regions.is_empty() {
+            warn!(
+                "⚠️  The v3 upload API has no custom pin policy, --replication-region does not apply to CAR uploads"
+            );
+        }
+        let metadata = self.pin_metadata(&file_name);
+        let part = Part::bytes(fs::read(path)?)
+            .file_name(file_name.clone())
+            .mime_str("application/vnd.ipld.car")?;
+        let mut form = Form::new()
+            .text("network", "public")
+            .text("car", "true")
+            .text("name", metadata.name.unwrap_or(file_name))
+            .text(
+                "keyvalues",
+                serde_json::to_string(&self.pin_labels.keyvalues)?,
+            );
+        if let Some(group_id) = &self.group_id {
+            form = form.text("group_id", group_id.clone());
+        }
+        let response = self
+            .client
+            .post(format!("{}/v3/files", self.uploads_url))
+            .multipart(form.part("file", part))
+            .send()
+            .await?;
+        let uploaded: UploadResponse = Self::check_status(response).await?.json().await?;
+        Ok(uploaded.data.cid)
+    }
+
+    /// 按 CID 固定已有内容，附带 `--replication-region` 的 pin 策略
+    pub(crate) async fn pin_by_hash(&self, cid: &str) -> Result<PinByHashResult> {
+        let request = PinByHashRequest {
+            hash_to_pin: cid,
+            pinata_options: self
+                .custom_pin_policy()
+                .map(|custom_pin_policy| HashPinOptions { custom_pin_policy }),
+        };
+        let response = self
+            .client
+            .post(self.url("/pinning/pinByHash"))
+            .json(&request)
+            .send()
+            .await?;
+        Ok(Self::check_status(response).await?.json().await?)
+    }
+
+    pub(crate) async fn get_pin_jobs(&self, filters: PinJobsFilter) -> Result<PinJobs> {
+        let response = self
+            .client
+            .get(self.url("/pinning/pinJobs"))
+            .query(&filters)
+            .send()
+            .await?;
+        Ok(Self::check_status(response).await?.json().await?)
+    }
+
+    pub(crate) async fn get_pin_list(&self, filters: &PinListFilter) -> Result<PinList> {
+        let response = self
+            .client
+            .get(self.url("/data/pinList"))
+            .query(filters)
+            .send()
+            .await?;
+        Ok(Self::check_status(response).await?.json().await?)
+    }
+
+    pub(crate) async fn get_total_pinned_data(&self) -> Result<TotalPinnedData> {
+        let response = self
+            .client
+            .get(self.url("/data/userPinnedDataTotal"))
+            .send()
+            .await?;
+        Ok(Self::check_status(response).await?.json().await?)
+    }
+
+    /// 取消固定；返回 `false` 表示该 CID 本来就没有被当前账户固定
+    pub(crate) async fn unpin(&self, hash: &str) -> Result<bool> {
+        let response = self
+            .client
+            .delete(self.url(&format!("/pinning/unpin/{}", hash)))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::BAD_REQUEST {
+            let headers = response.headers().clone();
+            let body = response.text().await.unwrap_or_default();
+            if body.contains("NOT_PINNED") || body.to_lowercase().contains("not pinned") {
+                return Ok(false);
+            }
+            return Err(HttpError::new(
+                status,
+                &headers,
+                format!("Pinata API returned HTTP {}: {}", status, body),
+            )
+            .into());
+        }
+        Self::check_status(response).await.map(|_| true)
+    }
+
+    /// 非 2xx 响应转换为带 Pinata 错误信息的错误
+    async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|json| match &json["error"] {
+                serde_json::Value::String(reason) => Some(reason.clone()),
+                serde_json::Value::Object(error) => Some(format!(
+                    "{}: {}",
+                    error.get("reason").and_then(|v| v.as_str()).unwrap_or(""),
+                    error.get("details").and_then(|v| v.as_str()).unwrap_or("")
+                )),
+                _ => None,
+            })
+            .unwrap_or(body);
+        Err(HttpError::new(
+            status,
+            &headers,
+            format!("Pinata API returned HTTP {}: {}", status, message),
+        )
+        .into())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for PinataClient {
+    fn name(&self) -> &'static str {
+        "Pinata"
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry.clone()
+    }
+
+    fn http_options(&self) -> &HttpOptions {
+        &self.http_options
+    }
+
+    fn cid_version(&self) -> Option<u8> {
+        Some(self.cid_version)
+    }
+
+    fn group_id(&self) -> Option<&str> {
+        self.group_id.as_deref()
+    }
+
+    fn replication_regions(&self) -> &[RegionPolicy] {
+        &self.replication_regions
+    }
+
+    async fn upload_file(&self, path: &Path) -> Result<String> {
+        Ok(self.pin_file(path).await?.ipfs_hash)
+    }
+
+    async fn upload_directory(&self, dir: &Path, files: &[PathBuf]) -> Result<String> {
+        Ok(self.pin_directory_files(dir, files).await?.ipfs_hash)
+    }
+
+    fn uri_scheme(&self) -> &'static str {
+        "ipfs"
+    }
+
+    /// 网关上的可点击链接，例如 `https://gateway.pinata.cloud/ipfs/<cid>/1.png`
+    fn gateway_url(&self, cid_path: &str) -> String {
+        format!("{}/{}", self.gateway, cid_path)
+    }
+
+    fn fetch_urls(&self, cid_path: &str) -> Vec<String> {
+        gateway_urls(&self.gateway, &self.fallback_gateways, cid_path)
+    }
+
+    fn validate_id(&self, cid: &str) -> Result<()> {
+        validate_cid(cid)
+    }
+
+    async fn list_directory(&self, cid: &str) -> Result<Vec<String>> {
+        fetch_directory_listing(self, cid).await
+    }
+}
+
+/// multipart 中的一个文件，带上按扩展名判断的 `Content-Type`，网关返回文件时使用正确的类型
+fn file_part(path: &Path, part_name: String) -> Result<Part> {
+    Ok(Part::bytes(fs::read(path)?)
+        .file_name(part_name)
+        .mime_str(content_type(path))?)
+}
+
+/// 分组 ID 是 UUID（如 `0f6e4b8c-3f0a-4f37-9a3e-1c2d3e4f5a6b`），其余值按分组名称处理
+fn is_group_id(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+// --- CID 校验 ---
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE32_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz234567";
+
+/// 在请求网络之前拒绝明显格式错误的 CID（支持 CIDv0 `Qm...` 和 CIDv1 base32 `b...` / base58btc `z...`）
+pub(crate) fn validate_cid(cid: &str) -> Result<()> {
+    let is_v0 = cid.len() == 46
+        && cid.starts_with("Qm")
+        && cid.chars().all(|c| BASE58_ALPHABET.contains(c));
+    let is_v1_base32 = cid.len() >= 50
+        && cid.starts_with('b')
+        && cid[1..].chars().all(|c| BASE32_ALPHABET.contains(c));
+    let is_v1_base58 = cid.len() >= 40
+        && cid.starts_with('z')
+        && cid[1..].chars().all(|c| BASE58_ALPHABET.contains(c));
+
+    if is_v0 || is_v1_base32 || is_v1_base58 {
+        Ok(())
+    } else {
+        Err(anyhow!("❌ Invalid CID: {}", cid))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::error::UploadError;
+    use crate::upload::retry_with_timeout;
+    use crate::upload::tests::fast_policy;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 本地的模拟 Pinata 服务：按顺序返回 `responses`，用完后重复最后一个；记录每个请求的方法和路径
+    pub(crate) struct MockServer {
+        pub(crate) url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        pub(crate) async fn start(responses: Vec<(u16, &str)>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let log = requests.clone();
+            let mut responses: Vec<(u16, String)> = responses
+                .into_iter()
+                .map(|(status, body)| (status, body.to_string()))
+                .collect();
+            responses.reverse();
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let Some(request) = read_request(&mut stream).await else {
+                        continue;
+                    };
+                    log.lock().unwrap().push(request);
+                    let (status, body) = match responses.len() {
+                        1 => responses[0].clone(),
+                        _ => responses.pop().unwrap(),
+                    };
+                    let reason = reqwest::StatusCode::from_u16(status)
+                        .unwrap()
+                        .canonical_reason()
+                        .unwrap_or("");
+                    let response = format!(
+                        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        reason,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                }
+            });
+            MockServer { url, requests }
+        }
+
+        /// 已收到的请求，如 `DELETE /pinning/unpin/Qm...`
+        pub(crate) fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        /// 连接到本服务、最多重试 `max_retries` 次的客户端
+        pub(crate) fn client(&self, max_retries: usize) -> PinataClient {
+            PinataClient::new(
+                &PinataAuth::Jwt("test-jwt".to_string()),
+                client_options(&format!("{}/ipfs", self.url), max_retries),
+            )
+            .unwrap()
+            .with_base_url(&self.url)
+        }
+    }
+
+    /// pin 名称前缀为 `test`、不带标签和 pin 策略的客户端选项
+    pub(crate) fn client_options(gateway: &str, max_retries: usize) -> ClientOptions {
+        ClientOptions {
+            retry: fast_policy(max_retries),
+            http: HttpOptions::default(),
+            gateway: gateway.to_string(),
+            fallback_gateways: Vec::new(),
+            pin_labels: PinLabels {
+                run_name: "test".to_string(),
+                keyvalues: BTreeMap::new(),
+            },
+            cid_version: 0,
+            replication_regions: Vec::new(),
+        }
+    }
+
+    /// 读完请求头和 `Content-Length` 指定的请求体，返回请求行中的方法和路径
+    async fn read_request(stream: &mut TcpStream) -> Option<String> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 8192];
+        let header_end = loop {
+            if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+            let n = stream.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                return None;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).into_owned();
+        let content_length = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        while buf.len() < header_end + content_length {
+            let n = stream.read(&mut chunk).await.ok()?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let mut request_line = head.lines().next()?.split_whitespace();
+        Some(format!("{} {}", request_line.next()?, request_line.next()?))
+    }
+
+    #[tokio::test]
+    async fn unauthorized_responses_are_not_retried() {
+        let server = MockServer::start(vec![(
+            401,
+            r#"{"error":{"reason":"INVALID_CREDENTIALS","details":"Invalid API key"}}"#,
+        )])
+        .await;
+        let api = server.client(3);
+
+        let error = retry_with_timeout(api.retry.clone(), || api.test_authentication())
+            .await
+            .unwrap_err();
+
+        assert_eq!(server.requests(), ["GET /data/testAuthentication"]);
+        assert!(
+            matches!(
+                error.downcast_ref::<UploadError>(),
+                Some(UploadError::AuthFailed(_))
+            ),
+            "{:#}",
+            error
+        );
+        assert!(format!("{:#}", error).contains("INVALID_CREDENTIALS: Invalid API key"));
+    }
+
+    #[tokio::test]
+    async fn service_unavailable_responses_are_retried() {
+        let server = MockServer::start(vec![
+            (503, r#"{"error":"Service Unavailable"}"#),
+            (503, r#"{"error":"Service Unavailable"}"#),
+            (
+                200,
+                r#"{"message":"Congratulations! You are communicating with the Pinata API!"}"#,
+            ),
+        ])
+        .await;
+        let api = server.client(3);
+
+        retry_with_timeout(api.retry.clone(), || api.test_authentication())
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn service_unavailable_gives_up_after_max_retries() {
+        let server = MockServer::start(vec![(503, r#"{"error":"Service Unavailable"}"#)]).await;
+        let api = server.client(2);
+
+        let error = retry_with_timeout(api.retry.clone(), || api.test_authentication())
+            .await
+            .unwrap_err();
+
+        assert_eq!(server.requests().len(), 3);
+        assert!(matches!(
+            error.downcast_ref::<UploadError>(),
+            Some(UploadError::Network(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn upload_car_returns_the_root_cid() {
+        let server = MockServer::start(vec![(
+            200,
+            r#"{"data":{"id":"0195","name":"images.car","cid":"bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354"}}"#,
+        )])
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let car = dir.path().join("images.car");
+        fs::write(&car, b"car bytes").unwrap();
+
+        let cid = server.client(0).upload_car(&car).await.unwrap();
+
+        assert_eq!(
+            cid,
+            "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354"
+        );
+        assert_eq!(server.requests(), ["POST /v3/files"]);
+    }
+
+    #[tokio::test]
+    async fn unpin_reports_cids_that_are_not_pinned() {
+        const CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+        let server = MockServer::start(vec![
+            (200, "OK"),
+            (
+                400,
+                r#"{"error":{"reason":"NOT_PINNED","details":"not pinned by this user"}}"#,
+            ),
+            (404, r#"{"error":"Current user has not pinned the cid"}"#),
+            (404, r#"{"error":"Not Found"}"#),
+        ])
+        .await;
+        let api = server.client(0);
+
+        assert!(api.unpin(CID).await.unwrap());
+        assert!(!api.unpin(CID).await.unwrap());
+        assert!(!api.unpin(CID).await.unwrap());
+        // 其他 4xx 仍然是错误，状态码保留下来用于归类
+        let error = api.unpin(CID).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<HttpError>().map(|e| e.status),
+            Some(reqwest::StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            server.requests(),
+            vec![format!("DELETE /pinning/unpin/{}", CID); 4]
+        );
+    }
+}
@@ -0,0 +1,1381 @@
+//! 上传结果：`results/` JSON、README、`manifest.csv` 与元数据副本
+
+use crate::cache::hash_file;
+use crate::metadata::{MetadataOptions, image_path_in_folder, read_metadata_file};
+use crate::storage::{Backend, StorageBackend};
+use anyhow::{Context, Result, anyhow};
+use pinata_sdk::RegionPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// 逐文件上传模式下单个 token 的上传结果
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenUploadResult {
+    pub token_id: String,
+    pub image_file: String,
+    pub image_cid: String,
+    /// 运行被取消时，元数据尚未上传的 token 为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_cid: Option<String>,
+}
+
+/// `--continue-on-error` 时重试用尽仍未上传成功的文件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FailedUpload {
+    /// 最后一次失败的完整错误链
+    pub error: String,
+    pub file: String,
+    pub kind: UploadKind,
+    pub token_id: String,
+}
+
+/// 一次 batch 实际传输的数据量与耗时；`--resume` 复用、缓存命中和 dry-run 不计入。
+/// 速率按墙钟时间计算（从开始上传到写入结果），并发上传时不会把各文件的耗时重复累加
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Throughput {
+    pub bytes_uploaded: u64,
+    pub elapsed_seconds: f64,
+    pub files_uploaded: usize,
+    /// 有效聚合速率，1 MB = 1024 × 1024 字节
+    pub mb_per_second: f64,
+}
+
+/// `manifest.csv` 中的一行：token 与图片、元数据 URI 的对应关系
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ManifestRow {
+    token_id: String,
+    image_filename: String,
+    metadata_cid_path: String,
+    image_ipfs_uri: String,
+    metadata_ipfs_uri: String,
+}
+
+/// 早期的结果文件没有 `backend` 字段，那时只支持 Pinata
+fn default_backend() -> String {
+    "Pinata".to_string()
+}
+
+/// 早期的结果文件总是建议带斜杠的 Base URI
+fn default_trailing_slash() -> bool {
+    true
+}
+
+/// `--deterministic` 时写入所有结果文件的时间，相同输入的两次运行生成完全相同的文件
+const DETERMINISTIC_TIMESTAMP: &str = "1970-01-01T00:00:00+00:00";
+
+/// 结果文件中的时间：当前时间，`--deterministic` 时为固定值
+pub(crate) fn record_timestamp(deterministic: bool) -> String {
+    if deterministic {
+        DETERMINISTIC_TIMESTAMP.to_string()
+    } else {
+        chrono::Utc::now().to_rfc3339()
+    }
+}
+
+/// 合约 Base URI：默认以 `/` 结尾，合约自己拼接斜杠时传入 `false`
+pub fn base_uri(api: &dyn StorageBackend, cid: &str, trailing_slash: bool) -> String {
+    let uri = api.uri(cid);
+    if trailing_slash { uri + "/" } else { uri }
+}
+
+// 以下结构即 `results/upload-result.json` 的内容，字段按字母顺序排列（与文件中的键顺序一致）
+
+/// 文件夹模式 batch 的结果文件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchRecord {
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    #[serde(default = "default_trailing_slash")]
+    pub base_uri_trailing_slash: bool,
+    pub cid_version: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    pub images_cid: String,
+    pub metadata_with_suffix_cid: Option<String>,
+    pub metadata_without_suffix_cid: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replication_regions: Vec<RegionPolicy>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<Throughput>,
+    /// `--generate-thumbnails` 上传的缩略图文件夹
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnails_cid: Option<String>,
+    pub timestamp: String,
+    pub total_files: usize,
+}
+
+impl BatchRecord {
+    /// 被取消时元数据 CID 为空，只记录已经上传的图片文件夹
+    pub(crate) fn new(
+        api: &dyn StorageBackend,
+        collection: Option<&str>,
+        images_cid: &str,
+        metadata_with_suffix_cid: Option<&str>,
+        metadata_without_suffix_cid: Option<&str>,
+        total_files: usize,
+        cancelled: bool,
+    ) -> Self {
+        let dry_run = api.is_dry_run();
+        BatchRecord {
+            backend: api.name().to_string(),
+            base_uri_trailing_slash: true,
+            cid_version: api.cid_version(),
+            collection: collection.map(str::to_string),
+            dry_run,
+            group_id: api.group_id().map(str::to_string),
+            images_cid: images_cid.to_string(),
+            metadata_with_suffix_cid: metadata_with_suffix_cid.map(str::to_string),
+            metadata_without_suffix_cid: metadata_without_suffix_cid.map(str::to_string),
+            replication_regions: api.replication_regions().to_vec(),
+            status: run_status(dry_run, cancelled).to_string(),
+            throughput: None,
+            thumbnails_cid: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            total_files,
+        }
+    }
+}
+
+/// 逐文件模式 batch 的结果文件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PerFileRecord {
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    pub cid_version: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<FailedUpload>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replication_regions: Vec<RegionPolicy>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<Throughput>,
+    pub timestamp: String,
+    pub tokens: Vec<TokenUploadResult>,
+    pub total_files: usize,
+}
+
+impl PerFileRecord {
+    /// 有上传失败（且不是被取消）时状态为 `completed_with_errors`
+    pub(crate) fn new(
+        api: &dyn StorageBackend,
+        collection: Option<&str>,
+        tokens: &[TokenUploadResult],
+        failed: &[FailedUpload],
+        cancelled: bool,
+    ) -> Self {
+        let dry_run = api.is_dry_run();
+        let status = if !failed.is_empty() && !cancelled {
+            STATUS_PARTIAL
+        } else {
+            run_status(dry_run, cancelled)
+        };
+        PerFileRecord {
+            backend: api.name().to_string(),
+            cid_version: api.cid_version(),
+            collection: collection.map(str::to_string),
+            dry_run,
+            failed: failed.to_vec(),
+            group_id: api.group_id().map(str::to_string),
+            mode: "per_file".to_string(),
+            replication_regions: api.replication_regions().to_vec(),
+            status: status.to_string(),
+            throughput: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            tokens: tokens.to_vec(),
+            total_files: tokens.len(),
+        }
+    }
+}
+
+/// single 的结果文件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SingleRecord {
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    pub cid_version: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    pub image_cid: String,
+    pub metadata_cid: String,
+    /// `--as-folder` 时元数据文件在文件夹中的文件名，Token URI 为 `<metadata_cid>/<metadata_path>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replication_regions: Vec<RegionPolicy>,
+    #[serde(default)]
+    pub status: String,
+    pub timestamp: String,
+    pub token_id: u64,
+}
+
+impl SingleRecord {
+    pub(crate) fn new(
+        api: &dyn StorageBackend,
+        collection: Option<&str>,
+        token_id: u64,
+        image_cid: &str,
+        metadata_cid: &str,
+        metadata_path: Option<&str>,
+    ) -> Self {
+        let dry_run = api.is_dry_run();
+        SingleRecord {
+            backend: api.name().to_string(),
+            cid_version: api.cid_version(),
+            collection: collection.map(str::to_string),
+            dry_run,
+            group_id: api.group_id().map(str::to_string),
+            image_cid: image_cid.to_string(),
+            metadata_cid: metadata_cid.to_string(),
+            metadata_path: metadata_path.map(str::to_string),
+            replication_regions: api.replication_regions().to_vec(),
+            status: run_status(dry_run, false).to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            token_id,
+        }
+    }
+}
+
+/// 任意一次运行的结果文件，按字段区分类型；`report` 子命令据此重新生成 README 与清单
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum RunRecord {
+    PerFile(PerFileRecord),
+    Single(SingleRecord),
+    Batch(BatchRecord),
+}
+
+impl RunRecord {
+    /// 读取 `<run_dir>/results/upload-result.json`
+    pub fn load(run_dir: &Path) -> Result<Self> {
+        let results_file = results_file(run_dir);
+        let content = fs::read_to_string(&results_file).with_context(|| {
+            format!(
+                "Failed to read {:?}, is {:?} the output directory of a batch or single run?",
+                results_file, run_dir
+            )
+        })?;
+        let json: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("{:?} is not valid JSON", results_file))?;
+        serde_json::from_value(json).map_err(|_| {
+            anyhow!(
+                "{:?} is not a batch, per-file or single results file (missing or mistyped fields)",
+                results_file
+            )
+        })
+    }
+
+    fn backend_name(&self) -> &str {
+        match self {
+            RunRecord::PerFile(record) => &record.backend,
+            RunRecord::Single(record) => &record.backend,
+            RunRecord::Batch(record) => &record.backend,
+        }
+    }
+
+    /// 生成这次运行的存储后端，用于重建 URI 和网关链接
+    pub fn backend(&self) -> Result<Backend> {
+        let name = self.backend_name();
+        Backend::from_name(name)
+            .ok_or_else(|| anyhow!("Unknown storage backend {:?} in the results file", name))
+    }
+
+    /// 重新生成 `run_dir` 中的 README 和（文件夹模式时）`manifest.csv`，不修改结果文件本身
+    pub fn write_report(&self, api: &dyn StorageBackend, run_dir: &Path) -> Result<()> {
+        match self {
+            RunRecord::PerFile(record) => write_per_file_readme(api, run_dir, record)?,
+            RunRecord::Single(record) => write_single_readme(api, run_dir, record)?,
+            RunRecord::Batch(record) => {
+                let metadata_dir = run_dir.join("metadata");
+                match &record.metadata_without_suffix_cid {
+                    Some(metadata_cid) if metadata_dir.is_dir() => {
+                        let manifest = manifest_from_metadata_dir(
+                            api,
+                            &metadata_dir,
+                            &record.images_cid,
+                            metadata_cid,
+                        )?;
+                        write_manifest_csv(run_dir, &manifest)?;
+                    }
+                    _ => warn!(
+                        "⚠️  {:?} has no saved metadata, skipping manifest.csv",
+                        metadata_dir
+                    ),
+                }
+                write_batch_readme(api, run_dir, record)?;
+            }
+        }
+        info!("✅ Report regenerated in: {:?}", run_dir);
+        Ok(())
+    }
+}
+
+fn results_file(output_dir: &Path) -> PathBuf {
+    output_dir.join("results").join("upload-result.json")
+}
+
+pub(crate) fn write_results_file(output_dir: &Path, record: &impl Serialize) -> Result<()> {
+    let mut file = File::create(results_file(output_dir))?;
+    file.write_all(serde_json::to_string_pretty(record)?.as_bytes())?;
+    Ok(())
+}
+
+fn write_readme(output_dir: &Path, content: &str) -> Result<()> {
+    let readme_file = output_dir.join("README.md");
+    let mut readme = File::create(&readme_file)?;
+    readme.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+const STATUS_CANCELLED: &str = "cancelled";
+const STATUS_PARTIAL: &str = "completed_with_errors";
+
+/// 设置了 `--collection-name` 时标题前加上集合名称
+fn readme_title(collection: Option<&str>, title: &str) -> String {
+    match collection {
+        Some(collection) => format!("{} — {}", collection, title),
+        None => title.to_string(),
+    }
+}
+
+fn run_notice(dry_run: bool, status: &str) -> &'static str {
+    if status == STATUS_CANCELLED {
+        "\n> 🛑 **CANCELLED** — the run was stopped with Ctrl-C, only the CIDs listed below were uploaded.\n"
+    } else if status == STATUS_PARTIAL {
+        "\n> ❌ **INCOMPLETE** — some files failed to upload, see Failed uploads below and rerun with `--resume`.\n"
+    } else if dry_run {
+        "\n> ⚠️ **DRY RUN** — nothing was uploaded, every CID below is a placeholder.\n"
+    } else {
+        ""
+    }
+}
+
+fn run_status(dry_run: bool, cancelled: bool) -> &'static str {
+    if cancelled {
+        STATUS_CANCELLED
+    } else if dry_run {
+        "dry_run"
+    } else {
+        "completed"
+    }
+}
+
+/// batch 工作流的结果
+#[derive(Serialize, Debug, Clone)]
+pub struct BatchResult {
+    /// 图片文件夹 CID，逐文件模式下为 `None`
+    pub images_cid: Option<String>,
+    /// `--generate-thumbnails` 上传的缩略图文件夹 CID
+    pub thumbnails_cid: Option<String>,
+    pub metadata_with_suffix_cid: Option<String>,
+    pub metadata_without_suffix_cid: Option<String>,
+    /// 逐文件模式下每个 token 的 CID，文件夹模式下为空
+    pub tokens: Vec<TokenUploadResult>,
+    pub total_files: usize,
+    /// 本次运行的结果目录（README、results、metadata）
+    pub output_dir: PathBuf,
+    pub dry_run: bool,
+    /// 运行被 Ctrl-C 中止，上面只包含已经完成的部分
+    pub cancelled: bool,
+    /// `--continue-on-error` 时上传失败的文件，文件夹模式下为空
+    pub failed: Vec<FailedUpload>,
+    /// `--optimize-images` 时上传的副本比原图少的字节数
+    pub bytes_saved: Option<u64>,
+    /// 实际传输的字节数与有效速率，没有任何实际上传（dry-run、全部复用）时为 `None`
+    pub throughput: Option<Throughput>,
+}
+
+impl BatchResult {
+    pub fn results_file(&self) -> PathBuf {
+        results_file(&self.output_dir)
+    }
+}
+
+/// `results.jsonl` 中记录的上传内容
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadKind {
+    Image,
+    Metadata,
+    ImagesFolder,
+    /// `--generate-thumbnails` 的缩略图文件夹
+    ThumbnailsFolder,
+    MetadataFolder,
+}
+
+/// `results/results.jsonl` 中的一行，每完成一次上传立即追加
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UploadLogEntry {
+    pub cid: String,
+    /// 上传的文件名或文件夹名
+    pub file: String,
+    pub kind: UploadKind,
+    /// 单个文件内容的 SHA-256，`--resume` 据此确认文件没有变化；文件夹为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub timestamp: String,
+    /// 单个文件的 token ID（文件名去掉扩展名），文件夹为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+}
+
+/// 运行过程中逐条写入的上传记录：中途失败或进程崩溃时，已完成上传的 CID 不会丢失。
+/// 文件在第一次上传完成时才创建，运行结束后与汇总的 `upload-result.json` 并存
+pub(crate) struct UploadLog {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+    started: Instant,
+    bytes_uploaded: AtomicU64,
+    files_uploaded: AtomicUsize,
+    /// `--deterministic`：记录固定的时间，不记录随每次运行变化的吞吐量
+    deterministic: bool,
+}
+
+impl UploadLog {
+    pub(crate) fn new(output_dir: &Path, deterministic: bool) -> Self {
+        UploadLog {
+            path: upload_log_file(output_dir),
+            file: Mutex::new(None),
+            deterministic,
+            started: Instant::now(),
+            bytes_uploaded: AtomicU64::new(0),
+            files_uploaded: AtomicUsize::new(0),
+        }
+    }
+
+    /// 记录一次真正发往存储服务的上传（单个文件或整个文件夹的内容）
+    pub(crate) fn record_transfer(&self, files: &[PathBuf]) -> Result<()> {
+        let mut bytes = 0;
+        for file in files {
+            bytes += fs::metadata(file)
+                .with_context(|| format!("Failed to read {:?}", file))?
+                .len();
+        }
+        self.bytes_uploaded.fetch_add(bytes, Ordering::Relaxed);
+        self.files_uploaded
+            .fetch_add(files.len(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 从创建日志到现在的传输汇总；没有实际上传任何文件时为 `None`
+    pub(crate) fn throughput(&self) -> Option<Throughput> {
+        let files_uploaded = self.files_uploaded.load(Ordering::Relaxed);
+        if files_uploaded == 0 || self.deterministic {
+            return None;
+        }
+        let bytes_uploaded = self.bytes_uploaded.load(Ordering::Relaxed);
+        let elapsed_seconds = self.started.elapsed().as_secs_f64();
+        let mb_per_second = if elapsed_seconds > 0.0 {
+            bytes_uploaded as f64 / (1024.0 * 1024.0) / elapsed_seconds
+        } else {
+            0.0
+        };
+        Some(Throughput {
+            bytes_uploaded,
+            elapsed_seconds,
+            files_uploaded,
+            mb_per_second,
+        })
+    }
+
+    pub(crate) fn append(&self, kind: UploadKind, path: &Path, cid: &str) -> Result<()> {
+        let (token_id, sha256) = match kind {
+            UploadKind::Image | UploadKind::Metadata => (
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned()),
+                Some(hash_file(path)?),
+            ),
+            UploadKind::ImagesFolder
+            | UploadKind::ThumbnailsFolder
+            | UploadKind::MetadataFolder => (None, None),
+        };
+        let entry = UploadLogEntry {
+            cid: cid.to_string(),
+            file: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            kind,
+            sha256,
+            timestamp: record_timestamp(self.deterministic),
+            token_id,
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| anyhow!("Upload log lock poisoned"))?;
+        if file.is_none() {
+            if let Some(results_dir) = self.path.parent() {
+                fs::create_dir_all(results_dir)?;
+            }
+            let opened = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .with_context(|| format!("Failed to open {:?}", self.path))?;
+            *file = Some(opened);
+        }
+        if let Some(file) = file.as_mut() {
+            // 整行一次写入，中途崩溃最多丢失最后一行
+            file.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// `--resume` 读取的上一次运行已完成的单文件上传，按类型和文件名索引
+pub(crate) struct PriorUploads {
+    run_dir: PathBuf,
+    entries: HashMap<(UploadKind, String), UploadLogEntry>,
+}
+
+impl PriorUploads {
+    /// 读取 `<run_dir>/results/results.jsonl`；崩溃时写了一半的最后一行会被跳过
+    pub(crate) fn load(run_dir: &Path) -> Result<Self> {
+        let path = upload_log_file(run_dir);
+        let content = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "Failed to read {:?}, is {:?} the output directory of a batch --per-file run?",
+                path, run_dir
+            )
+        })?;
+        let mut entries = HashMap::new();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<UploadLogEntry>(line) {
+                Ok(entry) if entry.sha256.is_some() => {
+                    entries.insert((entry.kind, entry.file.clone()), entry);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("⚠️  Skipping {:?}:{}: {}", path, index + 1, e),
+            }
+        }
+        Ok(PriorUploads {
+            run_dir: run_dir.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// 确认上一次上传的图片都还在、内容没有变化，且记录的 CID 对当前存储后端有效，
+    /// 否则复用的 CID 会指向与当前素材不一致的内容
+    pub(crate) fn check_images(
+        &self,
+        api: &dyn StorageBackend,
+        image_files: &[PathBuf],
+    ) -> Result<()> {
+        let current: HashMap<String, &PathBuf> = image_files
+            .iter()
+            .filter_map(|path| Some((path.file_name()?.to_string_lossy().into_owned(), path)))
+            .collect();
+        let mut mismatched = Vec::new();
+        for ((kind, file), entry) in &self.entries {
+            if *kind != UploadKind::Image {
+                continue;
+            }
+            let unchanged = match current.get(file) {
+                Some(path) => Some(hash_file(path)?) == entry.sha256,
+                None => false,
+            };
+            if !unchanged {
+                mismatched.push(file.as_str());
+            }
+            api.validate_id(&entry.cid).with_context(|| {
+                format!(
+                    "{:?} has CID {} for {}, which {} does not accept (was it a dry run or another backend?)",
+                    self.run_dir,
+                    entry.cid,
+                    file,
+                    api.name()
+                )
+            })?;
+        }
+        if !mismatched.is_empty() {
+            mismatched.sort_unstable();
+            return Err(anyhow!(
+                "❌ Images in {:?} differ from the current batch (missing or changed: {}), refusing to resume",
+                self.run_dir,
+                mismatched.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// 文件名与内容都与上一次上传一致时返回记录的 CID
+    pub(crate) fn cid(&self, kind: UploadKind, path: &Path) -> Result<Option<&str>> {
+        let Some(file) = path.file_name() else {
+            return Ok(None);
+        };
+        let Some(entry) = self
+            .entries
+            .get(&(kind, file.to_string_lossy().into_owned()))
+        else {
+            return Ok(None);
+        };
+        Ok((entry.sha256 == Some(hash_file(path)?)).then_some(entry.cid.as_str()))
+    }
+}
+
+fn upload_log_file(output_dir: &Path) -> PathBuf {
+    output_dir.join("results").join("results.jsonl")
+}
+
+fn copy_metadata_folder(metadata_src: &Path, output_dir: &Path) -> Result<()> {
+    let metadata_dest = output_dir.join("metadata");
+    if metadata_src.exists() {
+        if metadata_dest.exists() {
+            fs::remove_dir_all(&metadata_dest)?;
+        }
+        fs::create_dir_all(&metadata_dest)?;
+
+        // Copy all files from metadata directory
+        for entry in fs::read_dir(metadata_src)? {
+            let entry = entry?;
+            let src_path = entry.path();
+            let dest_path = metadata_dest.join(src_path.file_name().unwrap());
+
+            if src_path.is_file() {
+                fs::copy(&src_path, &dest_path)?;
+                info!("📄 Copied metadata file: {}", dest_path.to_string_lossy());
+            }
+        }
+        info!("📁 Metadata folder saved to: {:?}", metadata_dest);
+    }
+    Ok(())
+}
+
+pub(crate) fn build_manifest(
+    api: &dyn StorageBackend,
+    image_files: &[PathBuf],
+    images_root: &Path,
+    images_cid: &str,
+    metadata_cid: &str,
+    options: &MetadataOptions,
+    with_suffix: bool,
+) -> Result<Vec<ManifestRow>> {
+    image_files
+        .iter()
+        .map(|image_file| {
+            let token_id = options.token_id(image_file)?;
+            let image_path = image_path_in_folder(images_root, image_file)?;
+            let metadata_cid_path = format!(
+                "{}/{}",
+                metadata_cid,
+                options.metadata_file_name(&token_id, with_suffix, false)
+            );
+
+            Ok(ManifestRow {
+                token_id,
+                image_ipfs_uri: api.uri(&format!("{}/{}", images_cid, image_path)),
+                image_filename: image_path,
+                metadata_ipfs_uri: api.uri(&metadata_cid_path),
+                metadata_cid_path,
+            })
+        })
+        .collect()
+}
+
+/// 从运行目录中保存的元数据副本重建清单：图片文件名取自每个元数据的 `image` 字段
+fn manifest_from_metadata_dir(
+    api: &dyn StorageBackend,
+    metadata_dir: &Path,
+    images_cid: &str,
+    metadata_cid: &str,
+) -> Result<Vec<ManifestRow>> {
+    let mut rows = Vec::new();
+    for entry in fs::read_dir(metadata_dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let metadata_file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid filename"))?;
+        let metadata = read_metadata_file(&path)?;
+        // 子目录中的图片保留 `<images_cid>/` 之后的完整路径
+        let images_prefix = format!("{}/", images_cid);
+        let image_filename = match metadata.image.split_once(&images_prefix) {
+            Some((_, image_path)) => image_path,
+            None => metadata.image.rsplit('/').next().unwrap_or_default(),
+        }
+        .to_string();
+        // 元数据文件名就是 token ID（可能带后缀）
+        let token_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid metadata file name {:?}", path))?
+            .to_string();
+        let metadata_cid_path = format!("{}/{}", metadata_cid, metadata_file_name);
+
+        rows.push(ManifestRow {
+            token_id,
+            image_ipfs_uri: api.uri(&format!("{}/{}", images_cid, image_filename)),
+            image_filename,
+            metadata_ipfs_uri: api.uri(&metadata_cid_path),
+            metadata_cid_path,
+        });
+    }
+    // 与上传时一致：数字 ID 按数值排序，其余按字符串排序
+    rows.sort_by_cached_key(|row| match row.token_id.parse::<u64>() {
+        Ok(id) => (0, id, row.token_id.clone()),
+        Err(_) => (1, 0, row.token_id.clone()),
+    });
+    Ok(rows)
+}
+
+fn write_manifest_csv(output_dir: &Path, manifest: &[ManifestRow]) -> Result<()> {
+    let manifest_file = output_dir.join("manifest.csv");
+    // csv 会自动为包含逗号、引号或换行的字段加引号
+    let mut writer = csv::Writer::from_path(&manifest_file)
+        .with_context(|| format!("Failed to create {:?}", manifest_file))?;
+    for row in manifest {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+
+    info!("📊 Manifest saved to: {:?}", manifest_file);
+    Ok(())
+}
+
+/// 上传时素材内容的清单，格式与 `sha256sum` 相同（`<sha256>  <相对路径>`），在图片目录中可以直接用 `sha256sum -c` 校验
+const ASSET_MANIFEST_FILE: &str = "manifest.sha256";
+
+/// 每张图片相对 `root` 的路径（`/` 分隔）到内容 SHA-256 的映射，按路径排序
+pub(crate) fn hash_assets(
+    root: &Path,
+    image_files: &[PathBuf],
+) -> Result<BTreeMap<String, String>> {
+    image_files
+        .iter()
+        .map(|file| Ok((image_path_in_folder(root, file)?, hash_file(file)?)))
+        .collect()
+}
+
+/// 在结果目录中写入 `manifest.sha256`，记录本次上传的原始素材
+pub(crate) fn write_asset_manifest(
+    output_dir: &Path,
+    root: &Path,
+    image_files: &[PathBuf],
+) -> Result<()> {
+    let content: String = hash_assets(root, image_files)?
+        .iter()
+        .map(|(path, hash)| format!("{}  {}\n", hash, path))
+        .collect();
+    let manifest_file = output_dir.join(ASSET_MANIFEST_FILE);
+    fs::write(&manifest_file, content)
+        .with_context(|| format!("Failed to write {:?}", manifest_file))?;
+    info!("🔏 Asset checksums saved to: {:?}", manifest_file);
+    Ok(())
+}
+
+/// 读取一次运行的 `manifest.sha256`
+pub(crate) fn read_asset_manifest(run_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let manifest_file = run_dir.join(ASSET_MANIFEST_FILE);
+    let content = fs::read_to_string(&manifest_file).with_context(|| {
+        format!(
+            "Failed to read {:?}, is {:?} the output directory of a batch run?",
+            manifest_file, run_dir
+        )
+    })?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            let (hash, path) = line.split_once("  ").ok_or_else(|| {
+                anyhow!(
+                    "{:?}:{}: expected \"<sha256>  <path>\"",
+                    manifest_file,
+                    index + 1
+                )
+            })?;
+            Ok((path.to_string(), hash.to_string()))
+        })
+        .collect()
+}
+
+fn write_batch_readme(
+    api: &dyn StorageBackend,
+    output_dir: &Path,
+    record: &BatchRecord,
+) -> Result<()> {
+    let metadata_with_suffix_cid = record.metadata_with_suffix_cid.as_deref();
+    let metadata_without_suffix_cid = record.metadata_without_suffix_cid.as_deref();
+    let thumbnails = match &record.thumbnails_cid {
+        Some(cid) => format!(
+            "- Thumbnails are available at: `{}/` ({})\n",
+            api.uri(cid),
+            api.gateway_url(cid)
+        ),
+        None => String::new(),
+    };
+    let readme_content = format!(
+        "# {}
+{}
+## Upload Information
+- **Timestamp**: {}
+- **Images CID**: `{}`
+- **Metadata with suffix CID**: `{}`
+- **Metadata without suffix CID**: `{}`
+- **Total files**: {}
+
+## Usage
+- For contracts expecting a file suffix (e.g. `1.json`): Use `{}`
+- For contracts without suffix: Use `{}`
+
+## Files
+- Images are available at: `{}/` ({})
+{}- Metadata files are available at the respective CIDs above.
+- Local metadata files are saved in the `metadata/` folder for reference.
+- `manifest.csv` maps every token ID to its image and metadata URIs.
+",
+        readme_title(record.collection.as_deref(), "Batch Upload Results"),
+        run_notice(record.dry_run, &record.status),
+        record.timestamp,
+        record.images_cid,
+        metadata_with_suffix_cid.unwrap_or("N/A"),
+        metadata_without_suffix_cid.unwrap_or("N/A"),
+        record.total_files,
+        base_uri(
+            api,
+            metadata_with_suffix_cid.unwrap_or(""),
+            record.base_uri_trailing_slash
+        ),
+        base_uri(
+            api,
+            metadata_without_suffix_cid.unwrap_or(""),
+            record.base_uri_trailing_slash
+        ),
+        api.uri(&record.images_cid),
+        api.gateway_url(&record.images_cid),
+        thumbnails
+    );
+    write_readme(output_dir, &readme_content)
+}
+
+pub(crate) async fn save_batch_results(
+    api: &dyn StorageBackend,
+    output_dir: &Path,
+    record: &BatchRecord,
+    manifest: &[ManifestRow],
+    metadata_dir: Option<&Path>,
+) -> Result<()> {
+    write_results_file(output_dir, record)?;
+
+    // 取消时元数据没有上传，也就没有清单
+    if !manifest.is_empty() {
+        write_manifest_csv(output_dir, manifest)?;
+    }
+
+    // Copy metadata folder if provided
+    if let Some(metadata_src) = metadata_dir {
+        copy_metadata_folder(metadata_src, output_dir)?;
+    }
+
+    write_batch_readme(api, output_dir, record)?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    Ok(())
+}
+
+fn write_per_file_readme(
+    api: &dyn StorageBackend,
+    output_dir: &Path,
+    record: &PerFileRecord,
+) -> Result<()> {
+    let token_rows: String = record
+        .tokens
+        .iter()
+        .map(|t| {
+            format!(
+                "| {} | {} | `{}` | {} |\n",
+                t.token_id,
+                t.image_file,
+                api.uri(&t.image_cid),
+                t.metadata_cid.as_deref().map_or_else(
+                    || "not uploaded".to_string(),
+                    |cid| format!("`{}`", api.uri(cid))
+                )
+            )
+        })
+        .collect();
+    let failed_section = if record.failed.is_empty() {
+        String::new()
+    } else {
+        let rows: String = record
+            .failed
+            .iter()
+            .map(|f| {
+                format!(
+                    "| {} | {} | {:?} | {} |\n",
+                    f.token_id,
+                    f.file,
+                    f.kind,
+                    // 错误链可能跨多行，表格中只保留一行
+                    f.error.replace('\n', " ").replace('|', "\\|")
+                )
+            })
+            .collect();
+        format!(
+            "
+## Failed uploads
+| Token ID | File | Kind | Error |
+|----------|------|------|-------|
+{}",
+            rows
+        )
+    };
+
+    let readme_content = format!(
+        "# {}
+{}
+## Upload Information
+- **Timestamp**: {}
+- **Total files**: {}
+
+## Usage
+- Every token was uploaded individually, so each token has its own Token URI (the metadata URI below).
+
+## Tokens
+| Token ID | Image file | Image URI | Token URI |
+|----------|------------|-----------|-----------|
+{}{}
+## Files
+- Local metadata files are saved in the `metadata/` folder for reference.
+",
+        readme_title(record.collection.as_deref(), "Batch Upload Results (per-file)"),
+        run_notice(record.dry_run, &record.status),
+        record.timestamp,
+        record.tokens.len(),
+        token_rows,
+        failed_section
+    );
+    write_readme(output_dir, &readme_content)
+}
+
+pub(crate) async fn save_per_file_results(
+    api: &dyn StorageBackend,
+    output_dir: &Path,
+    record: &PerFileRecord,
+    metadata_dir: Option<&Path>,
+) -> Result<()> {
+    write_results_file(output_dir, record)?;
+
+    if let Some(metadata_src) = metadata_dir {
+        copy_metadata_folder(metadata_src, output_dir)?;
+    }
+
+    write_per_file_readme(api, output_dir, record)?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    Ok(())
+}
+
+fn write_single_readme(
+    api: &dyn StorageBackend,
+    output_dir: &Path,
+    record: &SingleRecord,
+) -> Result<()> {
+    let metadata_id = match &record.metadata_path {
+        Some(path) => format!("{}/{}", record.metadata_cid, path),
+        None => record.metadata_cid.clone(),
+    };
+    let readme_content = format!(
+        "# {}
+{}
+## Upload Information
+- **Timestamp**: {}
+- **Image CID**: `{}`
+- **Metadata CID**: `{}`
+- **Token ID**: {}
+
+## Usage
+- The Token URI for this NFT is: `{}`
+
+## Files
+- Image is available at: {}
+- Metadata is available at: {}
+",
+        readme_title(record.collection.as_deref(), "Single File Upload Results"),
+        run_notice(record.dry_run, &record.status),
+        record.timestamp,
+        record.image_cid,
+        record.metadata_cid,
+        record.token_id,
+        single_token_uri(api, &record.metadata_cid, record.metadata_path.as_deref()),
+        api.gateway_url(&record.image_cid),
+        api.gateway_url(&metadata_id)
+    );
+    write_readme(output_dir, &readme_content)
+}
+
+pub(crate) fn save_single_results(
+    api: &dyn StorageBackend,
+    output_dir: &Path,
+    record: &SingleRecord,
+) -> Result<()> {
+    write_results_file(output_dir, record)?;
+
+    write_single_readme(api, output_dir, record)?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    Ok(())
+}
+
+/// single 工作流的结果
+#[derive(Serialize, Debug, Clone)]
+pub struct SingleResult {
+    pub token_id: u64,
+    pub image_cid: String,
+    /// `--as-folder` 时为只包含这一个元数据文件的文件夹 CID
+    pub metadata_cid: String,
+    /// `--as-folder` 时元数据文件在文件夹中的文件名
+    pub metadata_path: Option<String>,
+    /// 本次运行的结果目录
+    pub output_dir: PathBuf,
+    pub dry_run: bool,
+}
+
+impl SingleResult {
+    /// 合约中设置的 Token URI：直接指向元数据文件，`--as-folder` 时为文件夹 CID 加文件名
+    pub fn token_uri(&self, api: &dyn StorageBackend) -> String {
+        single_token_uri(api, &self.metadata_cid, self.metadata_path.as_deref())
+    }
+}
+
+fn single_token_uri(
+    api: &dyn StorageBackend,
+    metadata_cid: &str,
+    metadata_path: Option<&str>,
+) -> String {
+    match metadata_path {
+        Some(path) => format!("{}/{}", api.uri(metadata_cid), path),
+        None => api.uri(metadata_cid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pinata::PinataClient;
+    use crate::pinata::tests::client_options;
+
+    const IMAGES_CID: &str = "QmImages";
+
+    fn dry_run_api() -> PinataClient {
+        PinataClient::dry_run(client_options("https://gateway.example/ipfs", 0))
+    }
+
+    /// 写入 `image` 指向 `image_uri` 的最小元数据文件
+    fn write_metadata(dir: &Path, file_name: &str, image_uri: &str) {
+        let metadata = serde_json::json!({
+            "name": file_name,
+            "description": "",
+            "image": image_uri,
+            "attributes": []
+        });
+        fs::write(dir.join(file_name), metadata.to_string()).unwrap();
+    }
+
+    fn write_images(dir: &Path, names: &[&str]) -> Vec<PathBuf> {
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, name.as_bytes()).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn metadata_uris(manifest: &[ManifestRow]) -> Vec<&str> {
+        manifest
+            .iter()
+            .map(|row| row.metadata_ipfs_uri.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn manifest_keeps_image_subdirectories_and_metadata_suffix() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = write_images(dir.path(), &["1.png", "rares/2.png"]);
+        let options = MetadataOptions {
+            metadata_file_suffix: ".json".to_string(),
+            ..crate::metadata::tests::options()
+        };
+        let api = dry_run_api();
+
+        let with_suffix = build_manifest(
+            &api,
+            &images,
+            dir.path(),
+            IMAGES_CID,
+            "QmMeta",
+            &options,
+            true,
+        )
+        .unwrap();
+        let without_suffix = build_manifest(
+            &api,
+            &images,
+            dir.path(),
+            IMAGES_CID,
+            "QmMeta",
+            &options,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(with_suffix[1].token_id, "2");
+        assert_eq!(with_suffix[1].image_filename, "rares/2.png");
+        assert_eq!(with_suffix[1].image_ipfs_uri, "ipfs://QmImages/rares/2.png");
+        assert_eq!(
+            metadata_uris(&with_suffix),
+            ["ipfs://QmMeta/1.json", "ipfs://QmMeta/2.json"]
+        );
+        assert_eq!(
+            metadata_uris(&without_suffix),
+            ["ipfs://QmMeta/1", "ipfs://QmMeta/2"]
+        );
+    }
+
+    #[test]
+    fn manifest_from_saved_metadata_is_sorted_by_token_id() {
+        let dir = tempfile::tempdir().unwrap();
+        for token in ["10", "2", "cover", "1"] {
+            write_metadata(
+                dir.path(),
+                token,
+                &format!("ipfs://{}/rares/{}.png", IMAGES_CID, token),
+            );
+        }
+
+        let manifest =
+            manifest_from_metadata_dir(&dry_run_api(), dir.path(), IMAGES_CID, "QmMeta").unwrap();
+
+        let tokens: Vec<&str> = manifest.iter().map(|row| row.token_id.as_str()).collect();
+        assert_eq!(tokens, ["1", "2", "10", "cover"]);
+        assert_eq!(manifest[2].image_filename, "rares/10.png");
+        assert_eq!(manifest[2].metadata_cid_path, "QmMeta/10");
+    }
+
+    #[tokio::test]
+    async fn batch_results_are_saved_and_can_be_loaded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().join("output");
+        fs::create_dir_all(output_dir.join("results")).unwrap();
+        let images_dir = dir.path().join("images");
+        let images = write_images(&images_dir, &["1.png", "2.png"]);
+        let metadata_dir = dir.path().join("metadata");
+        fs::create_dir_all(&metadata_dir).unwrap();
+        for token in ["1", "2"] {
+            write_metadata(
+                &metadata_dir,
+                token,
+                &format!("ipfs://{}/{}.png", IMAGES_CID, token),
+            );
+        }
+        let api = dry_run_api();
+        let options = crate::metadata::tests::options();
+        let manifest = build_manifest(
+            &api,
+            &images,
+            &images_dir,
+            IMAGES_CID,
+            "QmPlain",
+            &options,
+            false,
+        )
+        .unwrap();
+        let record = BatchRecord::new(
+            &api,
+            Some("MetaCore"),
+            IMAGES_CID,
+            Some("QmSuffixed"),
+            Some("QmPlain"),
+            images.len(),
+            false,
+        );
+
+        save_batch_results(&api, &output_dir, &record, &manifest, Some(&metadata_dir))
+            .await
+            .unwrap();
+
+        let RunRecord::Batch(loaded) = RunRecord::load(&output_dir).unwrap() else {
+            panic!("expected a batch results file");
+        };
+        assert_eq!(loaded.images_cid, IMAGES_CID);
+        assert_eq!(
+            loaded.metadata_without_suffix_cid.as_deref(),
+            Some("QmPlain")
+        );
+        assert_eq!(loaded.collection.as_deref(), Some("MetaCore"));
+        assert_eq!(loaded.status, "dry_run");
+        assert_eq!(loaded.total_files, 2);
+
+        let csv = fs::read_to_string(output_dir.join("manifest.csv")).unwrap();
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            [
+                "token_id,image_filename,metadata_cid_path,image_ipfs_uri,metadata_ipfs_uri",
+                "1,1.png,QmPlain/1,ipfs://QmImages/1.png,ipfs://QmPlain/1",
+                "2,2.png,QmPlain/2,ipfs://QmImages/2.png,ipfs://QmPlain/2",
+            ]
+        );
+        assert!(output_dir.join("metadata").join("2").is_file());
+        let readme = fs::read_to_string(output_dir.join("README.md")).unwrap();
+        assert!(readme.contains("Use `ipfs://QmSuffixed/`"), "{}", readme);
+    }
+
+    #[test]
+    fn old_results_files_fall_back_to_pinata_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("results")).unwrap();
+        let load = |json: serde_json::Value| {
+            fs::write(results_file(dir.path()), json.to_string()).unwrap();
+            RunRecord::load(dir.path()).unwrap()
+        };
+
+        // 早期的文件夹模式结果：没有 backend、base_uri_trailing_slash、dry_run 和 status
+        let batch = load(serde_json::json!({
+            "cid_version": 0,
+            "images_cid": IMAGES_CID,
+            "metadata_with_suffix_cid": "QmSuffixed",
+            "metadata_without_suffix_cid": null,
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "total_files": 3
+        }));
+        let RunRecord::Batch(batch) = batch else {
+            panic!("expected a batch record: {:?}", batch);
+        };
+        assert_eq!(batch.backend, "Pinata");
+        assert!(batch.base_uri_trailing_slash);
+        assert!(!batch.dry_run);
+        assert_eq!(batch.status, "");
+
+        let single = load(serde_json::json!({
+            "cid_version": 0,
+            "image_cid": "QmImage",
+            "metadata_cid": "QmMetadata",
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "token_id": 1
+        }));
+        assert!(
+            matches!(&single, RunRecord::Single(record) if record.backend == "Pinata"),
+            "{:?}",
+            single
+        );
+
+        let per_file = load(serde_json::json!({
+            "cid_version": 0,
+            "mode": "per_file",
+            "timestamp": "2024-01-01T00:00:00+00:00",
+            "tokens": [],
+            "total_files": 0
+        }));
+        assert!(
+            matches!(&per_file, RunRecord::PerFile(record) if record.backend == "Pinata"),
+            "{:?}",
+            per_file
+        );
+        assert!(matches!(per_file.backend(), Ok(Backend::Pinata)));
+
+        fs::write(results_file(dir.path()), r#"{"images_cid": 1}"#).unwrap();
+        assert!(RunRecord::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn prior_uploads_skip_a_truncated_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = write_images(&dir.path().join("images"), &["1.png", "2.png"]);
+        let log = UploadLog::new(dir.path(), true);
+        log.append(UploadKind::Image, &images[0], "QmOne").unwrap();
+        log.append(UploadKind::Image, &images[1], "QmTwo").unwrap();
+        log.append(
+            UploadKind::ImagesFolder,
+            &dir.path().join("images"),
+            "QmFolder",
+        )
+        .unwrap();
+        // 进程在写最后一行时崩溃
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(upload_log_file(dir.path()))
+            .unwrap();
+        file.write_all(br#"{"cid":"QmThr"#).unwrap();
+
+        let prior = PriorUploads::load(dir.path()).unwrap();
+
+        assert_eq!(
+            prior.cid(UploadKind::Image, &images[0]).unwrap(),
+            Some("QmOne")
+        );
+        assert_eq!(
+            prior.cid(UploadKind::Image, &images[1]).unwrap(),
+            Some("QmTwo")
+        );
+        assert_eq!(prior.cid(UploadKind::Metadata, &images[0]).unwrap(), None);
+        // 内容变化后不再复用
+        fs::write(&images[1], b"changed").unwrap();
+        assert_eq!(prior.cid(UploadKind::Image, &images[1]).unwrap(), None);
+    }
+
+    #[test]
+    fn asset_manifest_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let images_dir = dir.path().join("images");
+        let images = write_images(&images_dir, &["2.png", "rares/1.png"]);
+
+        write_asset_manifest(dir.path(), &images_dir, &images).unwrap();
+
+        let manifest = read_asset_manifest(dir.path()).unwrap();
+        assert_eq!(manifest, hash_assets(&images_dir, &images).unwrap());
+        assert_eq!(
+            manifest.keys().collect::<Vec<_>>(),
+            ["2.png", "rares/1.png"]
+        );
+    }
+
+    #[test]
+    fn malformed_asset_manifest_lines_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(ASSET_MANIFEST_FILE),
+            "abc123  1.png\n\nnot-a-checksum-line\n",
+        )
+        .unwrap();
+
+        let error = read_asset_manifest(dir.path()).unwrap_err().to_string();
+        assert!(error.contains(":2: expected"), "{}", error);
+    }
+}
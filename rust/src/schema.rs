@@ -0,0 +1,89 @@
+//! 上传前用 JSON Schema 校验生成的元数据，避免把缺少 `image`、`attributes` 格式错误的文件永久固定
+
+use crate::metadata::NftMetadata;
+use anyhow::{Context, Result, anyhow};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use tracing::error;
+
+/// 内置的 ERC-721 / OpenSea 元数据 schema
+const ERC721_SCHEMA: &str = include_str!("../schemas/erc721-metadata.schema.json");
+
+/// 编译好的元数据 schema
+pub struct MetadataSchema {
+    validator: jsonschema::Validator,
+    /// 用于日志的 schema 来源（内置或文件路径）
+    source: String,
+}
+
+impl fmt::Debug for MetadataSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetadataSchema")
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl MetadataSchema {
+    /// 内置的 ERC-721 / OpenSea 兼容 schema（`schemas/erc721-metadata.schema.json`）
+    pub fn erc721() -> Self {
+        let schema = serde_json::from_str(ERC721_SCHEMA).expect("built-in schema is valid JSON");
+        MetadataSchema {
+            validator: jsonschema::validator_for(&schema).expect("built-in schema compiles"),
+            source: "the built-in ERC-721 schema".to_string(),
+        }
+    }
+
+    /// 读取并编译用户提供的 schema 文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read schema file {:?}", path))?;
+        let schema: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Schema file {:?} is not valid JSON", path))?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| anyhow!("Invalid JSON schema {:?}: {}", path, e))?;
+        Ok(MetadataSchema {
+            validator,
+            source: format!("schema {}", path.display()),
+        })
+    }
+
+    /// 校验一组 `(文件名, 元数据)`，逐个输出所有错误；任意文件不符合 schema 时返回错误
+    pub(crate) fn validate(&self, documents: &[(String, NftMetadata)]) -> Result<()> {
+        let mut invalid_files = 0;
+        for (file_name, metadata) in documents {
+            let instance = serde_json::to_value(metadata)?;
+            let errors: Vec<String> = self
+                .validator
+                .iter_errors(&instance)
+                .map(|e| {
+                    let path = e.instance_path().to_string();
+                    let path = if path.is_empty() { "/" } else { path.as_str() };
+                    format!("{}: {}", path, e)
+                })
+                .collect();
+            if errors.is_empty() {
+                continue;
+            }
+
+            invalid_files += 1;
+            for message in &errors {
+                error!(
+                    "❌ {} does not match the metadata schema: {}",
+                    file_name, message
+                );
+            }
+        }
+
+        if invalid_files > 0 {
+            return Err(anyhow!(
+                "❌ {} of {} metadata files failed validation against {}",
+                invalid_files,
+                documents.len(),
+                self.source
+            ));
+        }
+        Ok(())
+    }
+}
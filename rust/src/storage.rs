@@ -0,0 +1,136 @@
+//! 存储后端抽象：元数据生成和结果输出只依赖这里的接口，不关心文件最终存放在哪里
+
+use crate::config::{HttpOptions, RetryPolicy};
+use anyhow::Result;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use pinata_sdk::RegionPolicy;
+use std::path::{Path, PathBuf};
+
+/// `--backend` 可选的存储后端
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// IPFS，通过 Pinata 固定
+    #[default]
+    Pinata,
+    /// Arweave 永久存储，通过 Irys 打包节点上传
+    Arweave,
+    /// IPFS，通过 NFT.Storage 免费固定
+    #[value(name = "nftstorage")]
+    NftStorage,
+    /// IPFS，添加到自己运行的 Kubo 节点（`ipfs daemon`）
+    Kubo,
+}
+
+impl Backend {
+    /// 由 `StorageBackend::name` 记录在结果文件中的名称反查后端
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Pinata" => Some(Backend::Pinata),
+            "Arweave" => Some(Backend::Arweave),
+            "NFT.Storage" => Some(Backend::NftStorage),
+            "Kubo" => Some(Backend::Kubo),
+            _ => None,
+        }
+    }
+}
+
+/// 上传文件和文件夹的存储后端。
+///
+/// 返回的标识在 Pinata 上是 CID，在 Arweave 上是交易 ID；文件夹内的文件统一通过
+/// `<标识>/<文件名>` 引用
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// 用于日志和结果文件的后端名称
+    fn name(&self) -> &'static str;
+
+    /// dry-run 模式下不会真正上传，工作流使用占位标识
+    fn is_dry_run(&self) -> bool;
+
+    fn retry_policy(&self) -> RetryPolicy;
+
+    /// 代理等连接设置，网关回读等不需要凭证的请求用它创建客户端
+    fn http_options(&self) -> &HttpOptions;
+
+    /// 上传返回的 CID 版本；没有 CID 概念的后端返回 `None`
+    fn cid_version(&self) -> Option<u8> {
+        None
+    }
+
+    /// 上传加入的 Pinata 分组 ID，记录到结果文件中；不支持分组的后端返回 `None`
+    fn group_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// 上传使用的自定义 pin 策略（每个区域的副本数），记录到结果文件中；默认策略或其他后端为空
+    fn replication_regions(&self) -> &[RegionPolicy] {
+        &[]
+    }
+
+    /// 上传单个文件，返回其标识（只尝试一次，重试由调用方负责）
+    async fn upload_file(&self, path: &Path) -> Result<String>;
+
+    /// 上传 `dir` 下的指定文件，返回文件夹标识
+    async fn upload_directory(&self, dir: &Path, files: &[PathBuf]) -> Result<String>;
+
+    /// 内容 URI 的协议：IPFS 后端为 `ipfs`，Arweave 为 `ar`
+    fn uri_scheme(&self) -> &'static str;
+
+    /// 写入元数据和合约的 URI，例如 `ipfs://<cid>/1.png` 或 `ar://<manifest_id>/1.png`
+    fn uri(&self, id_path: &str) -> String {
+        format!("{}://{}", self.uri_scheme(), id_path)
+    }
+
+    /// 浏览器可直接打开的网关链接
+    fn gateway_url(&self, id_path: &str) -> String;
+
+    /// 读取内容（`verify`、`show`、`test` 和目录列表）时依次尝试的链接，第一个即 [`Self::gateway_url`]；
+    /// 默认只有这一个，返回空列表时读取直接报错
+    fn fetch_urls(&self, id_path: &str) -> Vec<String> {
+        vec![self.gateway_url(id_path)]
+    }
+
+    /// 在请求网络之前拒绝明显格式错误的标识（如 `--images-cid` 传入的值）
+    fn validate_id(&self, id: &str) -> Result<()>;
+
+    /// 通过网关读取文件夹第一层的条目名，供 `--verify` 校验上传结果
+    async fn list_directory(&self, id: &str) -> Result<Vec<String>>;
+}
+
+/// 主网关之后接着备用网关；网关地址不带末尾的 `/`
+pub(crate) fn gateway_urls(gateway: &str, fallbacks: &[String], id_path: &str) -> Vec<String> {
+    std::iter::once(gateway)
+        .chain(fallbacks.iter().map(String::as_str))
+        .map(|gateway| format!("{}/{}", gateway, id_path))
+        .collect()
+}
+
+/// 按扩展名判断文件的 MIME 类型；元数据文件通常没有扩展名，按 JSON 处理，无法识别时返回 `None`
+pub(crate) fn detect_content_type(path: &Path) -> Option<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    Some(match extension.as_deref() {
+        None | Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("avif") => "image/avif",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        Some("glb") => "model/gltf-binary",
+        Some("html" | "htm") => "text/html",
+        Some("txt") => "text/plain",
+        Some("yaml" | "yml") => "application/yaml",
+        Some(_) => return None,
+    })
+}
+
+/// 上传时使用的 `Content-Type`，无法识别的文件按二进制流上传
+pub(crate) fn content_type(path: &Path) -> &'static str {
+    detect_content_type(path).unwrap_or("application/octet-stream")
+}
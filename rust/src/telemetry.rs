@@ -0,0 +1,73 @@
+//! `--otel-endpoint`：通过 `tracing-opentelemetry` 把上传 span（文件大小、CID、尝试次数、耗时）
+//! 以 OTLP/HTTP 导出到 OpenTelemetry Collector。依赖较重，由默认关闭的 `otel` feature 控制
+
+use anyhow::Result;
+#[cfg(feature = "otel")]
+use anyhow::anyhow;
+use tracing::Subscriber;
+use tracing_subscriber::Layer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// 导出 span 时使用的 `service.name`
+#[cfg(feature = "otel")]
+const SERVICE_NAME: &str = "nft-uploader";
+
+/// 持有 tracer provider；drop 时导出缓冲中剩余的 span，进程退出前不会丢数据
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+/// 创建导出到 `endpoint`（Collector 地址，如 `http://localhost:4318`，自动补上 `/v1/traces`）的 tracing layer
+#[cfg(feature = "otel")]
+pub fn otel_layer<S>(endpoint: &str) -> Result<(impl Layer<S>, Telemetry)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let endpoint = endpoint.trim_end_matches('/');
+    let endpoint = if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| anyhow!("Failed to create the OTLP exporter: {}", e))?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(SERVICE_NAME)
+                .build(),
+        )
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+    Ok((layer, Telemetry { provider }))
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn otel_layer<S>(_endpoint: &str) -> Result<(impl Layer<S>, Telemetry)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    Err::<(tracing_subscriber::layer::Identity, Telemetry), _>(anyhow::anyhow!(
+        "--otel-endpoint is not available: this binary was built without the otel feature"
+    ))
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!(
+                "⚠️  Failed to export the remaining OpenTelemetry spans: {}",
+                e
+            );
+        }
+    }
+}
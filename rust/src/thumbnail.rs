@@ -0,0 +1,125 @@
+//! `--generate-thumbnails`：为每张图片生成长边不超过指定像素的缩略图，作为单独的文件夹上传，
+//! 元数据的 `thumbnail` 字段指向其中对应的文件。依赖 `image`，由默认开启的 `thumbnails` feature 控制
+
+use anyhow::Result;
+#[cfg(feature = "thumbnails")]
+use anyhow::{Context, anyhow};
+use std::collections::HashMap;
+#[cfg(feature = "thumbnails")]
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "thumbnails")]
+use tracing::{debug, info, warn};
+
+/// 缩略图文件夹的名称，出现在 pin 名称和 `results.jsonl` 中，不使用临时目录的随机名称
+#[cfg(feature = "thumbnails")]
+const THUMBNAILS_DIR: &str = "thumbnails";
+
+/// 生成的缩略图，位于临时目录下的 `thumbnails/` 中，结构与原来的图片目录一致；临时目录随该值一起删除
+pub(crate) struct Thumbnails {
+    /// 只用于在丢弃时删除整个临时目录
+    _temp: tempfile::TempDir,
+    dir: PathBuf,
+    pub(crate) files: Vec<PathBuf>,
+    /// 原图 → 缩略图在缩略图文件夹中的路径（如 `sub/42.png`）；无法解码的图片不在其中
+    pub(crate) paths: HashMap<PathBuf, String>,
+}
+
+impl Thumbnails {
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// 把 `image` 缩小到长边不超过 `max_dim`，按原图的格式写入 `target`；原图已经足够小时直接复制
+#[cfg(feature = "thumbnails")]
+fn write_thumbnail(source: &Path, target: &Path, max_dim: u32) -> Result<()> {
+    let format = image::ImageFormat::from_path(source)
+        .map_err(|e| anyhow!("unsupported image format: {}", e))?;
+    let image = image::ImageReader::open(source)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| anyhow!("cannot be decoded: {}", e))?;
+    if image.width() <= max_dim && image.height() <= max_dim {
+        fs::copy(source, target)?;
+        return Ok(());
+    }
+    let thumbnail = image.thumbnail(max_dim, max_dim);
+    debug!(
+        "🖼️  {}: {}x{} → {}x{}",
+        source.display(),
+        image.width(),
+        image.height(),
+        thumbnail.width(),
+        thumbnail.height()
+    );
+    thumbnail
+        .save_with_format(target, format)
+        .map_err(|e| anyhow!("cannot be encoded: {}", e))
+}
+
+/// 为 `image_files`（位于 `root` 下）生成缩略图；无法解码或编码的图片跳过并给出警告，对应的 token 没有 `thumbnail`
+#[cfg(feature = "thumbnails")]
+pub(crate) fn generate_thumbnails(
+    image_files: &[PathBuf],
+    root: &Path,
+    max_dim: u32,
+) -> Result<Thumbnails> {
+    use crate::metadata::image_path_in_folder;
+
+    info!(
+        "🖼️  Generating thumbnails for {} images (at most {}px on the longest side)...",
+        image_files.len(),
+        max_dim
+    );
+    let temp = tempfile::Builder::new().prefix("thumbnails-").tempdir()?;
+    let dir = temp.path().join(THUMBNAILS_DIR);
+    let mut files = Vec::with_capacity(image_files.len());
+    let mut paths = HashMap::with_capacity(image_files.len());
+    for path in image_files {
+        let relative = path
+            .strip_prefix(root)
+            .with_context(|| format!("{:?} is not inside {:?}", path, root))?;
+        let target = dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Err(e) = write_thumbnail(path, &target, max_dim) {
+            warn!(
+                "⚠️  Skipping the thumbnail of {}: {:#}",
+                relative.display(),
+                e
+            );
+            continue;
+        }
+        paths.insert(path.clone(), image_path_in_folder(root, path)?);
+        files.push(target);
+    }
+
+    let skipped = image_files.len() - files.len();
+    if skipped > 0 {
+        warn!(
+            "⚠️  {} of {} images have no thumbnail, their metadata will not include one",
+            skipped,
+            image_files.len()
+        );
+    }
+    info!("✅ Generated {} thumbnails", files.len());
+    Ok(Thumbnails {
+        _temp: temp,
+        dir,
+        files,
+        paths,
+    })
+}
+
+#[cfg(not(feature = "thumbnails"))]
+pub(crate) fn generate_thumbnails(
+    _image_files: &[PathBuf],
+    _root: &Path,
+    _max_dim: u32,
+) -> Result<Thumbnails> {
+    Err(anyhow::anyhow!(
+        "--generate-thumbnails is not available: this binary was built without the thumbnails feature"
+    ))
+}
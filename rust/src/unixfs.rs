@@ -0,0 +1,389 @@
+//! 在本地计算文件夹的 IPFS CID（UnixFS / DAG-PB），结果与 Kubo 默认参数下 `ipfs add -r` 一致，
+//! 用来核对固定服务返回的 CID：256 KiB 定长分块、平衡 DAG、CIDv1 时叶子使用 raw 块。
+//! 同一套 DAG 也可以打包成 CAR 文件上传，固定服务直接导入这些块
+
+use crate::car::write_car;
+use anyhow::{Context, Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Kubo 默认的分块大小（`size-262144`）
+const CHUNK_SIZE: usize = 256 * 1024;
+/// 平衡 DAG 中每个节点最多的子节点数
+const MAX_LINKS: usize = 174;
+/// 链接的估算大小达到该值后 Kubo 会把文件夹转为 HAMT 分片目录，这里不支持分片目录
+const HAMT_SHARDING_SIZE: usize = 256 * 1024;
+
+/// multicodec：DAG-PB 节点与 raw 块
+const DAG_PB: u8 = 0x70;
+const RAW: u8 = 0x55;
+/// multihash：sha2-256，32 字节
+const SHA2_256: u8 = 0x12;
+
+/// UnixFS `Data.Type`
+const UNIXFS_DIRECTORY: u64 = 1;
+const UNIXFS_FILE: u64 = 2;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+struct Cid {
+    version: u8,
+    codec: u8,
+    digest: [u8; 32],
+}
+
+impl Cid {
+    fn of(version: u8, codec: u8, block: &[u8]) -> Self {
+        Cid {
+            version,
+            codec,
+            digest: Sha256::digest(block).into(),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(36);
+        if self.version == 1 {
+            // 两个 codec 都小于 0x80，varint 只占一个字节
+            bytes.extend([1, self.codec]);
+        }
+        bytes.extend([SHA2_256, 32]);
+        bytes.extend(self.digest);
+        bytes
+    }
+
+    /// CIDv0 使用 base58btc（`Qm...`），CIDv1 使用小写 base32（`b...`）
+    fn encode(&self) -> String {
+        let bytes = self.to_bytes();
+        if self.version == 0 {
+            return bs58::encode(bytes).into_string();
+        }
+        let mut encoded = String::from("b");
+        for chunk in bytes.chunks(5) {
+            let mut buffer = [0u8; 5];
+            buffer[..chunk.len()].copy_from_slice(chunk);
+            let value = buffer.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+            // 不足 5 字节时只输出覆盖有效位的字符，不加 `=` 填充
+            let chars = (chunk.len() * 8).div_ceil(5);
+            for i in 0..chars {
+                let index = (value >> (35 - i * 5)) & 0x1f;
+                encoded.push(BASE32_ALPHABET[index as usize] as char);
+            }
+        }
+        encoded
+    }
+}
+
+/// 已生成的 DAG 节点
+struct DagNode {
+    cid: Cid,
+    /// 节点及其所有子节点的序列化大小之和，即父节点链接中的 `Tsize`
+    total_size: u64,
+    /// 文件内容的字节数，文件夹为 0
+    file_size: u64,
+}
+
+struct Link<'a> {
+    name: &'a str,
+    node: &'a DagNode,
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u8, bytes: &[u8]) {
+    buf.push((field << 3) | 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_uint(buf: &mut Vec<u8>, field: u8, value: u64) {
+    buf.push(field << 3);
+    put_varint(buf, value);
+}
+
+/// UnixFS `Data` 消息：Type、Data、filesize、blocksizes（未压缩的 repeated 字段）
+fn unixfs_data(kind: u64, data: &[u8], file_size: Option<u64>, block_sizes: &[u64]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_uint(&mut buf, 1, kind);
+    if !data.is_empty() {
+        put_bytes(&mut buf, 2, data);
+    }
+    if let Some(file_size) = file_size {
+        put_uint(&mut buf, 3, file_size);
+    }
+    for &size in block_sizes {
+        put_uint(&mut buf, 4, size);
+    }
+    buf
+}
+
+/// 生成 DAG 的参数和状态；`blocks` 为 `Some` 时收集每个块的 `(CID 字节, 内容)`，用于写出 CAR
+struct DagBuilder {
+    version: u8,
+    blocks: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+}
+
+impl DagBuilder {
+    fn keep(&mut self, cid: &Cid, block: &[u8]) {
+        if let Some(blocks) = &mut self.blocks {
+            blocks.push((cid.to_bytes(), block.to_vec()));
+        }
+    }
+}
+
+/// 编码 DAG-PB 节点并计算 CID；按规范链接写在 Data 之前，链接的 Name 即使为空也写出
+fn dag_pb_node(builder: &mut DagBuilder, links: &[Link<'_>], data: &[u8]) -> DagNode {
+    let mut block = Vec::new();
+    for link in links {
+        let mut encoded = Vec::new();
+        put_bytes(&mut encoded, 1, &link.node.cid.to_bytes());
+        put_bytes(&mut encoded, 2, link.name.as_bytes());
+        put_uint(&mut encoded, 3, link.node.total_size);
+        put_bytes(&mut block, 2, &encoded);
+    }
+    put_bytes(&mut block, 1, data);
+
+    let cid = Cid::of(builder.version, DAG_PB, &block);
+    builder.keep(&cid, &block);
+    DagNode {
+        cid,
+        total_size: block.len() as u64 + links.iter().map(|l| l.node.total_size).sum::<u64>(),
+        file_size: links.iter().map(|l| l.node.file_size).sum(),
+    }
+}
+
+fn file_node(builder: &mut DagBuilder, content: &[u8]) -> DagNode {
+    let mut layer: Vec<DagNode> = if content.is_empty() {
+        vec![leaf_node(builder, &[])]
+    } else {
+        content
+            .chunks(CHUNK_SIZE)
+            .map(|chunk| leaf_node(builder, chunk))
+            .collect()
+    };
+
+    // 平衡布局：逐层把最多 MAX_LINKS 个子节点合并为一个父节点，直到只剩根节点
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(MAX_LINKS)
+            .map(|children| {
+                let links: Vec<Link<'_>> = children
+                    .iter()
+                    .map(|node| Link { name: "", node })
+                    .collect();
+                let block_sizes: Vec<u64> = children.iter().map(|c| c.file_size).collect();
+                let file_size = block_sizes.iter().sum();
+                let data = unixfs_data(UNIXFS_FILE, &[], Some(file_size), &block_sizes);
+                dag_pb_node(builder, &links, &data)
+            })
+            .collect();
+    }
+    layer.remove(0)
+}
+
+/// CIDv1 的叶子是 raw 块（`--raw-leaves`），CIDv0 的叶子是包含数据的 UnixFS 文件节点
+fn leaf_node(builder: &mut DagBuilder, chunk: &[u8]) -> DagNode {
+    if builder.version == 1 {
+        let cid = Cid::of(1, RAW, chunk);
+        builder.keep(&cid, chunk);
+        return DagNode {
+            cid,
+            total_size: chunk.len() as u64,
+            file_size: chunk.len() as u64,
+        };
+    }
+    let data = unixfs_data(UNIXFS_FILE, chunk, Some(chunk.len() as u64), &[]);
+    let node = dag_pb_node(builder, &[], &data);
+    DagNode {
+        file_size: chunk.len() as u64,
+        ..node
+    }
+}
+
+/// 相对路径组成的文件夹树；`BTreeMap` 按字节序排列，与 Kubo 中链接的顺序一致
+#[derive(Default)]
+struct Tree {
+    files: BTreeMap<String, PathBuf>,
+    dirs: BTreeMap<String, Tree>,
+}
+
+impl Tree {
+    fn insert(&mut self, components: &[String], path: &Path) {
+        match components {
+            [name] => {
+                self.files.insert(name.clone(), path.to_path_buf());
+            }
+            [dir, rest @ ..] => self.dirs.entry(dir.clone()).or_default().insert(rest, path),
+            [] => {}
+        }
+    }
+
+    fn build(&self, builder: &mut DagBuilder) -> Result<DagNode> {
+        let mut children: BTreeMap<&str, DagNode> = BTreeMap::new();
+        for (name, path) in &self.files {
+            let content =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            children.insert(name, file_node(builder, &content));
+        }
+        for (name, tree) in &self.dirs {
+            children.insert(name, tree.build(builder)?);
+        }
+
+        let estimated_size: usize = children
+            .iter()
+            .map(|(name, node)| name.len() + node.cid.to_bytes().len())
+            .sum();
+        if estimated_size >= HAMT_SHARDING_SIZE {
+            return Err(anyhow!(
+                "the folder has {} entries and would be stored as a HAMT-sharded directory, which the local CID calculation does not support",
+                children.len()
+            ));
+        }
+
+        let links: Vec<Link<'_>> = children
+            .iter()
+            .map(|(name, node)| Link { name, node })
+            .collect();
+        let data = unixfs_data(UNIXFS_DIRECTORY, &[], None, &[]);
+        let node = dag_pb_node(builder, &links, &data);
+        Ok(DagNode {
+            file_size: 0,
+            ..node
+        })
+    }
+}
+
+fn directory_tree(dir: &Path, files: &[PathBuf], cid_version: u8) -> Result<Tree> {
+    if cid_version > 1 {
+        return Err(anyhow!("Unsupported CID version {}", cid_version));
+    }
+    let mut tree = Tree::default();
+    for path in files {
+        let components: Vec<String> = path
+            .strip_prefix(dir)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        tree.insert(&components, path);
+    }
+    Ok(tree)
+}
+
+/// 计算由 `dir` 中的 `files` 组成的文件夹 CID（文件按相对 `dir` 的路径放入文件夹）
+pub fn directory_cid(dir: &Path, files: &[PathBuf], cid_version: u8) -> Result<String> {
+    let mut builder = DagBuilder {
+        version: cid_version,
+        blocks: None,
+    };
+    let root = directory_tree(dir, files, cid_version)?.build(&mut builder)?;
+    Ok(root.cid.encode())
+}
+
+/// 把同样的文件夹 DAG 打包成 CARv1 写入 `car_path`，返回根 CID（与 `directory_cid` 一致）；
+/// 所有块在写出前保存在内存中
+pub fn pack_directory_car(
+    dir: &Path,
+    files: &[PathBuf],
+    cid_version: u8,
+    car_path: &Path,
+) -> Result<String> {
+    let mut builder = DagBuilder {
+        version: cid_version,
+        blocks: Some(Vec::new()),
+    };
+    let root = directory_tree(dir, files, cid_version)?.build(&mut builder)?;
+    write_car(
+        car_path,
+        &root.cid.to_bytes(),
+        builder.blocks.as_deref().unwrap_or_default(),
+    )?;
+    Ok(root.cid.encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ipfs add -r` 对空文件夹给出的 CID
+    const EMPTY_DIRECTORY_V0: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+    const EMPTY_DIRECTORY_V1: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+
+    /// 在 `dir` 中写入文件，返回它们的路径（`name` 可以包含子目录）
+    fn write_files(dir: &Path, files: &[(&str, Vec<u8>)]) -> Vec<PathBuf> {
+        files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, content).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_directory_matches_kubo() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            directory_cid(dir.path(), &[], 0).unwrap(),
+            EMPTY_DIRECTORY_V0
+        );
+        assert_eq!(
+            directory_cid(dir.path(), &[], 1).unwrap(),
+            EMPTY_DIRECTORY_V1
+        );
+    }
+
+    #[test]
+    fn cid_depends_on_content_and_names_but_not_file_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(
+            dir.path(),
+            &[
+                ("1.png", b"first".to_vec()),
+                ("2.png", b"second".to_vec()),
+                ("rares/3.png", b"third".to_vec()),
+            ],
+        );
+        let cid = directory_cid(dir.path(), &files, 0).unwrap();
+        assert!(cid.starts_with("Qm"), "{}", cid);
+
+        let reversed: Vec<PathBuf> = files.iter().rev().cloned().collect();
+        assert_eq!(directory_cid(dir.path(), &reversed, 0).unwrap(), cid);
+
+        fs::write(&files[0], b"changed").unwrap();
+        assert_ne!(directory_cid(dir.path(), &files, 0).unwrap(), cid);
+    }
+
+    #[test]
+    fn car_root_matches_directory_cid() {
+        let dir = tempfile::tempdir().unwrap();
+        // 超过一个分块的文件会生成带子节点的平衡 DAG
+        let large: Vec<u8> = (0..CHUNK_SIZE * 3 + 17).map(|i| (i % 251) as u8).collect();
+        let files = write_files(
+            dir.path().join("images").as_path(),
+            &[("1.png", large), ("2.png", b"small".to_vec())],
+        );
+        let images = dir.path().join("images");
+        for version in [0, 1] {
+            let car = dir.path().join(format!("images-v{}.car", version));
+            let root = pack_directory_car(&images, &files, version, &car).unwrap();
+            assert_eq!(root, directory_cid(&images, &files, version).unwrap());
+            assert!(fs::metadata(&car).unwrap().len() > CHUNK_SIZE as u64 * 3);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_cid_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(directory_cid(dir.path(), &[], 2).is_err());
+    }
+}
@@ -0,0 +1,847 @@
+//! 带重试和超时的上传、进度显示以及网关回读校验
+
+use crate::config::RetryPolicy;
+use crate::error::{BudgetExhausted, UploadError};
+use crate::pinata::HttpError;
+use crate::results::{UploadKind, UploadLog};
+use crate::storage::{StorageBackend, detect_content_type};
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use reqwest::header::ACCEPT;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span, debug, error, field, info, info_span, warn};
+use tracing_subscriber::fmt::MakeWriter;
+
+// --- Dry run ---
+/// 根据文件名和内容生成确定性的占位 CID；`DRYRUN` 大写字母保证它不可能被误认为真实 CID
+pub(crate) fn dry_run_cid(root: &Path, files: &[PathBuf]) -> Result<String> {
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.strip_prefix(root).unwrap_or(file).hash(&mut hasher);
+        fs::read(file)?.hash(&mut hasher);
+    }
+    Ok(format!("bafyDRYRUN{:016x}", hasher.finish()))
+}
+
+// --- 取消 ---
+/// 用户按下 Ctrl-C 后，正在进行的步骤被中止时返回的错误
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cancelled by Ctrl-C")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// `cancel` 被触发时丢弃 `work`（连同进行中的请求）并返回 `Cancelled`
+pub async fn cancellable<T>(
+    cancel: &CancellationToken,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(Cancelled.into()),
+        result = work => result,
+    }
+}
+
+// --- 进度显示 ---
+const PROGRESS_TEMPLATE: &str = "{spinner} [{elapsed_precise}] [{bar:40}] {msg} {binary_bytes}/{binary_total_bytes} ({binary_bytes_per_sec}, ETA {eta})";
+
+/// 进度条容器：`--no-progress` 或 stdout 不是终端时不绘制任何内容
+pub fn progress_container(no_progress: bool) -> MultiProgress {
+    if no_progress || !io::stdout().is_terminal() {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
+
+/// tracing 日志输出：写入前暂时隐藏进度条，日志始终显示在进度条上方
+#[derive(Clone)]
+pub struct ProgressLogWriter(pub MultiProgress);
+
+impl Write for ProgressLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.suspend(|| io::stdout().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for ProgressLogWriter {
+    type Writer = ProgressLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+// --- 核心上传函数 (带重试和超时) ---
+/// 重试暂时性的失败；放弃重试时返回 [`UploadError`]，原始错误保留在它的 `source` 中。
+/// 例外是 `--max-total-failures` 的预算用尽：错误为 [`BudgetExhausted`]（最后一次失败时，它作为上下文包着那次的
+/// `UploadError`），调用方应先用 `is::<BudgetExhausted>()` 判断
+pub(crate) async fn retry_with_timeout<T, F, Fut>(policy: RetryPolicy, mut action: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delays = policy.backoff_delays();
+    let upload_timeout = Duration::from_secs(policy.upload_timeout_seconds);
+    let budget = policy.budget.as_deref();
+    let mut attempts = 0usize;
+    loop {
+        if let Some(budget) = budget {
+            budget.start_attempt()?;
+        }
+        attempts += 1;
+        // 上传 span 声明了 `attempts` 字段，其他 span 忽略
+        Span::current().record("attempts", attempts);
+        if let Some(limiter) = &policy.rate_limiter {
+            limiter.acquire().await;
+        }
+        let e = match timeout(upload_timeout, action()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) => UploadError::classify(e),
+            Err(elapsed) => UploadError::Timeout(anyhow::Error::from(elapsed).context(format!(
+                "no response within {}s",
+                policy.upload_timeout_seconds
+            ))),
+        };
+        if let Some(budget) = budget
+            && let Err(exhausted) = budget.record_failure(&e)
+        {
+            return Err(anyhow::Error::from(e).context(exhausted));
+        }
+        if !e.is_transient() {
+            let e = anyhow::Error::from(e);
+            warn!("⛔ Permanent error, not retrying: {:#}", e);
+            return Err(e);
+        }
+        let Some(mut delay) = delays.next() else {
+            return Err(e.into());
+        };
+        if let UploadError::RateLimited(source) = &e
+            && let Some(http) = source
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<HttpError>())
+        {
+            // 服务端给出的等待时间优先于（更短的）退避间隔，不受 `max_backoff_ms` 限制
+            delay = delay.max(http.retry_after.unwrap_or_default());
+            warn!(
+                "🚦 Rate limited (HTTP 429), waiting {:.1}s before retrying",
+                delay.as_secs_f64()
+            );
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+pub(crate) async fn upload_directory_with_retry(
+    api: &dyn StorageBackend,
+    dir_path: &Path,
+    files: &[PathBuf],
+) -> Result<String> {
+    if api.is_dry_run() {
+        let cid = dry_run_cid(dir_path, files)?;
+        info!(
+            "🧪 [dry-run] Skipping folder upload of {}, placeholder CID: {}",
+            dir_path.display(),
+            cid
+        );
+        return Ok(cid);
+    }
+
+    let size_bytes = total_size(files)?;
+    let retry = api.retry_policy();
+    info!(
+        "🔄 Starting upload with retry mechanism (max {} attempts, timeout per attempt: {})",
+        retry.max_attempts(),
+        retry.describe_upload_timeout(size_bytes)
+    );
+    let span = info_span!(
+        "upload_folder",
+        folder = %dir_path.display(),
+        files = files.len(),
+        size_bytes,
+        cid = field::Empty,
+        attempts = field::Empty,
+    );
+    let result = retry_with_timeout(retry.for_upload(size_bytes), || {
+        upload_directory_to_storage(api, dir_path, files)
+    })
+    .instrument(span.clone())
+    .await;
+    match result {
+        Ok(cid) => {
+            span.record("cid", cid.as_str());
+            info!("✅ Upload completed successfully after retries");
+            Ok(cid)
+        }
+        Err(e) => {
+            error!("❌ {:#}", e);
+            Err(e)
+        }
+    }
+}
+
+/// 所有文件的字节数之和
+pub(crate) fn total_size(files: &[PathBuf]) -> io::Result<u64> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).map(|m| m.len()))
+        .sum()
+}
+
+pub(crate) async fn upload_single_file_with_retry(
+    api: &dyn StorageBackend,
+    file_path: &Path,
+) -> Result<String> {
+    let retry = api.retry_policy();
+    let size_bytes = fs::metadata(file_path)?.len();
+    debug!(
+        "⏱️  Timeout per attempt: {}",
+        retry.describe_upload_timeout(size_bytes)
+    );
+    let result = retry_with_timeout(retry.for_upload(size_bytes), || {
+        upload_single_file_to_storage(api, file_path)
+    })
+    .await;
+    match &result {
+        Ok(cid) => {
+            Span::current().record("cid", cid.as_str());
+        }
+        Err(e) => error!("❌ {:#}", e),
+    }
+    result
+}
+
+/// 单个文件上传的 span：日志按文件区分，`--otel-endpoint` 时导出为 OpenTelemetry span，
+/// 上传完成后记录 CID 和尝试次数
+pub(crate) fn upload_span(file: &Path) -> Span {
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    info_span!(
+        "upload",
+        file = %file_name,
+        size_bytes = fs::metadata(file).map_or(0, |m| m.len()),
+        cid = field::Empty,
+        attempts = field::Empty,
+    )
+}
+
+/// 逐文件并发上传的调度方式
+#[derive(Clone, Copy)]
+pub(crate) struct UploadControl<'a> {
+    /// 最多同时进行的上传数
+    pub(crate) concurrency: NonZeroUsize,
+    /// 触发后不再开始新的上传
+    pub(crate) cancel: &'a CancellationToken,
+    /// 单个文件失败（重试用尽）后继续上传其余文件，而不是整体失败
+    pub(crate) continue_on_error: bool,
+}
+
+/// 逐文件上传的结果：成功文件的 CID，以及 `continue_on_error` 时失败的文件
+#[derive(Default)]
+pub(crate) struct UploadedFiles {
+    pub(crate) cids: HashMap<PathBuf, String>,
+    pub(crate) failed: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// 并发上传多个文件，默认任意一个失败即整体失败；
+/// 每个文件上传完成后立即写入 `log`，失败前已完成的 CID 不会丢失
+pub(crate) async fn upload_files_concurrently(
+    api: &dyn StorageBackend,
+    files: &[PathBuf],
+    control: UploadControl<'_>,
+    progress: &MultiProgress,
+    log: &UploadLog,
+    kind: UploadKind,
+) -> Result<UploadedFiles> {
+    let total = files.len();
+    let completed = AtomicUsize::new(0);
+
+    let total_bytes = total_size(files)?;
+    if let Some(largest) = files
+        .iter()
+        .filter_map(|file| fs::metadata(file).ok())
+        .map(|m| m.len())
+        .max()
+        && !api.is_dry_run()
+    {
+        info!(
+            "⏱️  Timeout per attempt for the largest file: {}",
+            api.retry_policy().describe_upload_timeout(largest)
+        );
+    }
+    let bar = progress.add(ProgressBar::new(total_bytes));
+    bar.set_style(ProgressStyle::with_template(PROGRESS_TEMPLATE)?.progress_chars("=> "));
+    bar.set_message(format!("0/{} files", total));
+
+    let uploads = stream::iter(files)
+        .map(|file| {
+            let completed = &completed;
+            let bar = &bar;
+            async move {
+                let file_name = file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let result = async {
+                    // 每个上传在独立的 span 中执行，并发时日志可按文件区分
+                    let cid = upload_single_file_with_retry(api, file)
+                        .instrument(upload_span(file))
+                        .await?;
+                    log.append(kind, file, &cid)?;
+                    if !api.is_dry_run() {
+                        log.record_transfer(std::slice::from_ref(file))?;
+                    }
+                    Ok::<_, anyhow::Error>(cid)
+                }
+                .await;
+                let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                bar.inc(fs::metadata(file).map_or(0, |m| m.len()));
+                bar.set_message(format!("{}/{} files", done, total));
+                match &result {
+                    Ok(cid) => info!("📦 [{}/{}] {} → {}", done, total, file_name, cid),
+                    Err(e) if control.continue_on_error && !e.is::<BudgetExhausted>() => {
+                        warn!("⚠️  [{}/{}] {} failed, continuing", done, total, file_name)
+                    }
+                    Err(_) => {}
+                }
+                (file.clone(), result)
+            }
+        })
+        .buffer_unordered(control.concurrency.get())
+        // 取消后不再开始新的上传，进行中的上传随 stream 一起被丢弃，只返回已完成的部分
+        .take_until(control.cancel.cancelled());
+
+    let result = if control.continue_on_error {
+        uploads
+            .map(Ok)
+            .try_fold(
+                UploadedFiles::default(),
+                |mut uploaded, (file, result)| async move {
+                    match result {
+                        Ok(cid) => {
+                            uploaded.cids.insert(file, cid);
+                        }
+                        // 失败预算用完说明服务整体不可用，继续上传其余文件没有意义
+                        Err(e) if e.is::<BudgetExhausted>() => return Err(e),
+                        Err(e) => uploaded.failed.push((file, e)),
+                    }
+                    Ok(uploaded)
+                },
+            )
+            .await
+    } else {
+        uploads
+            .map(|(file, result)| result.map(|cid| (file, cid)))
+            .try_collect()
+            .await
+            .map(|cids| UploadedFiles {
+                cids,
+                failed: Vec::new(),
+            })
+    };
+
+    bar.finish_and_clear();
+    result
+}
+
+async fn upload_directory_to_storage(
+    api: &dyn StorageBackend,
+    dir_path: &Path,
+    files: &[PathBuf],
+) -> Result<String> {
+    let path_str = dir_path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid folder path"))?;
+
+    let upload_start = std::time::Instant::now();
+    info!("--- Uploading folder to {}: {} ---", api.name(), path_str);
+    info!(
+        "⏱️  Upload started at: {}",
+        chrono::Utc::now().format("%H:%M:%S")
+    );
+
+    let cid = api
+        .upload_directory(dir_path, files)
+        .await
+        .with_context(|| format!("Upload of folder {} failed", path_str))?;
+
+    let upload_duration = upload_start.elapsed();
+
+    info!("✅ Folder uploaded successfully! CID: {}", cid);
+    info!(
+        "⏱️  Upload completed in: {:.2} seconds",
+        upload_duration.as_secs_f64()
+    );
+
+    Ok(cid)
+}
+
+pub(crate) async fn upload_single_file_to_storage(
+    api: &dyn StorageBackend,
+    file_path: &Path,
+) -> Result<String> {
+    let path_str = file_path
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid file path"))?;
+
+    if api.is_dry_run() {
+        let root = file_path.parent().unwrap_or(Path::new(""));
+        let cid = dry_run_cid(root, &[file_path.to_path_buf()])?;
+        info!(
+            "🧪 [dry-run] Skipping upload of {}, placeholder CID: {}",
+            path_str, cid
+        );
+        return Ok(cid);
+    }
+
+    let upload_start = std::time::Instant::now();
+    let file_size = fs::metadata(file_path)?.len();
+    let file_size_mb = file_size as f64 / 1024.0 / 1024.0;
+
+    info!(
+        "--- Uploading single file to {}: {} ---",
+        api.name(),
+        path_str
+    );
+    info!(
+        "⏱️  Upload started at: {}",
+        chrono::Utc::now().format("%H:%M:%S")
+    );
+    info!("📁 File size: {:.2} MB", file_size_mb);
+    match detect_content_type(file_path) {
+        Some(content_type) => info!("🏷️  Content type: {}", content_type),
+        None => warn!(
+            "⚠️  Unrecognized file type for {}, uploading as application/octet-stream",
+            path_str
+        ),
+    }
+
+    let cid = api
+        .upload_file(file_path)
+        .await
+        .with_context(|| format!("Upload of {} failed", path_str))?;
+
+    let upload_duration = upload_start.elapsed();
+    let upload_speed = file_size_mb / upload_duration.as_secs_f64();
+
+    info!("✅ File uploaded successfully! CID: {}", cid);
+    info!(
+        "⏱️  Upload completed in: {:.2} seconds",
+        upload_duration.as_secs_f64()
+    );
+    info!("📊 Upload speed: {:.2} MB/s", upload_speed);
+
+    Ok(cid)
+}
+
+/// 通过网关下载 `cid`（可带路径，如 `<cid>/1.png`）的内容，非 2xx 响应作为 `HttpError` 返回
+/// 按 [`StorageBackend::fetch_urls`] 的顺序读取 `id_path`：每轮各网关请求一次，第一个成功的结果立即返回，
+/// 单个网关最多占用一次尝试超时的 1/n。整轮失败时，只要有网关是暂时性失败就按重试策略再来一轮
+async fn fetch_with_fallback<T, F, Fut>(
+    api: &dyn StorageBackend,
+    id_path: &str,
+    fetch: F,
+) -> Result<T>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let urls = api.fetch_urls(id_path);
+    let Ok(gateways) = u32::try_from(urls.len()) else {
+        return Err(anyhow!("{} returned too many gateway URLs", api.name()));
+    };
+    if gateways == 0 {
+        return Err(anyhow!(
+            "{} has no gateway to fetch {} from",
+            api.name(),
+            id_path
+        ));
+    }
+    let policy = api.retry_policy();
+    let per_gateway =
+        (Duration::from_secs(policy.upload_timeout_seconds) / gateways).max(Duration::from_secs(1));
+    retry_with_timeout(policy, || async {
+        let mut errors = Vec::with_capacity(urls.len());
+        for url in &urls {
+            let result = match timeout(per_gateway, fetch(url.clone())).await {
+                Ok(result) => result,
+                Err(elapsed) => Err(anyhow::Error::from(elapsed).context(format!(
+                    "no response within {:.0}s",
+                    per_gateway.as_secs_f64()
+                ))),
+            };
+            match result {
+                Ok(value) => {
+                    if urls.len() > 1 {
+                        info!("🌐 Served by {}", url);
+                    }
+                    return Ok(value);
+                }
+                Err(e) => {
+                    let e = e.context(format!("{} failed", url));
+                    if urls.len() > 1 {
+                        warn!("⚠️  {:#}, trying the next gateway", e);
+                    }
+                    errors.push(UploadError::classify(e));
+                }
+            }
+        }
+        // 有任一网关是暂时性失败时返回它的错误，让重试策略再试一轮；否则返回最后一个网关的错误
+        let index = errors
+            .iter()
+            .position(UploadError::is_transient)
+            .unwrap_or(errors.len() - 1);
+        Err(errors.swap_remove(index).into())
+    })
+    .await
+}
+
+pub(crate) async fn fetch_from_gateway(api: &dyn StorageBackend, cid: &str) -> Result<Vec<u8>> {
+    let url = api.gateway_url(cid);
+    info!("🌐 Fetching from gateway: {}", url);
+
+    let client = api.http_options().client();
+    fetch_with_fallback(api, cid, |url| {
+        let client = client.clone();
+        async move {
+            let response = client.get(&url).send().await?;
+            if !response.status().is_success() {
+                return Err(HttpError::new(
+                    response.status(),
+                    response.headers(),
+                    format!("Gateway returned HTTP {}", response.status()),
+                )
+                .into());
+            }
+            Ok(response.bytes().await?.to_vec())
+        }
+    })
+    .await
+    .with_context(|| format!("Failed to download {}", url))
+}
+
+/// dag-json 形式的 UnixFS 目录节点，只关心其中的链接和节点类型
+#[derive(Deserialize, Debug)]
+struct DirectoryNode {
+    #[serde(rename = "Data", default)]
+    data: Option<DagJsonBytes>,
+    #[serde(rename = "Links", default)]
+    links: Vec<DirectoryLink>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DirectoryLink {
+    #[serde(rename = "Name", default)]
+    name: Option<String>,
+    #[serde(rename = "Hash", default)]
+    hash: Option<DagJsonLink>,
+}
+
+/// dag-json 中的字节串：`{"/": {"bytes": "<base64>"}}`
+#[derive(Deserialize, Debug)]
+struct DagJsonBytes {
+    #[serde(rename = "/")]
+    inner: DagJsonBytesInner,
+}
+
+#[derive(Deserialize, Debug)]
+struct DagJsonBytesInner {
+    bytes: String,
+}
+
+/// dag-json 中的 CID 链接：`{"/": "<cid>"}`
+#[derive(Deserialize, Debug)]
+struct DagJsonLink {
+    #[serde(rename = "/")]
+    cid: String,
+}
+
+/// UnixFS `Data.Type` 中的 HAMTShard：条目很多的目录会被 Kubo / Pinata 分片成多层节点
+const UNIXFS_HAMT_SHARD: u64 = 5;
+/// 未记录分片扇出时 Kubo 使用的默认值
+const DEFAULT_HAMT_FANOUT: u64 = 256;
+
+/// 目录中的一个条目及其 CID
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub name: String,
+    pub cid: String,
+}
+
+impl DirectoryNode {
+    /// 分片节点返回其扇出（决定链接名前缀的长度），普通目录返回 `None`
+    fn hamt_fanout(&self) -> Option<u64> {
+        let data = self.data.as_ref()?;
+        let bytes = STANDARD_NO_PAD
+            .decode(data.inner.bytes.trim_end_matches('='))
+            .ok()?;
+        let (mut node_type, mut fanout) = (None, None);
+        let mut rest = bytes.as_slice();
+        // UnixFS Data 是 protobuf：1 = Type，6 = fanout，其余字段跳过
+        while let Some(key) = read_varint(&mut rest) {
+            match (key >> 3, key & 7) {
+                (1, 0) => node_type = read_varint(&mut rest),
+                (6, 0) => fanout = read_varint(&mut rest),
+                (_, 0) => {
+                    read_varint(&mut rest)?;
+                }
+                (_, 2) => {
+                    let len = usize::try_from(read_varint(&mut rest)?).ok()?;
+                    rest = rest.get(len..)?;
+                }
+                _ => return None,
+            }
+        }
+        (node_type == Some(UNIXFS_HAMT_SHARD)).then(|| fanout.unwrap_or(DEFAULT_HAMT_FANOUT))
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+async fn fetch_directory_node(
+    api: &dyn StorageBackend,
+    client: &reqwest::Client,
+    cid: &str,
+) -> Result<DirectoryNode> {
+    fetch_with_fallback(api, cid, |url| async move {
+        let response = client
+            .get(format!("{}?format=dag-json", url))
+            .header(ACCEPT, "application/vnd.ipld.dag-json")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(HttpError::new(
+                response.status(),
+                response.headers(),
+                format!("Gateway returned HTTP {}", response.status()),
+            )
+            .into());
+        }
+        Ok(response.json().await?)
+    })
+    .await
+    .with_context(|| format!("Failed to fetch directory node {}", api.gateway_url(cid)))
+}
+
+/// 通过 IPFS 网关读取目录 CID 下的条目（名称和 CID）；分片目录逐个读取子分片，
+/// 条目按名称排序
+pub(crate) async fn fetch_directory_entries(
+    api: &dyn StorageBackend,
+    cid: &str,
+) -> Result<Vec<DirectoryEntry>> {
+    info!(
+        "🌐 Fetching directory listing from gateway: {}",
+        api.gateway_url(cid)
+    );
+
+    let client = api.http_options().client();
+    let mut entries = Vec::new();
+    let mut pending = vec![cid.to_string()];
+    let mut shards = 0usize;
+    while let Some(node_cid) = pending.pop() {
+        let node = fetch_directory_node(api, &client, &node_cid).await?;
+        // 分片节点的链接名带有十六进制的桶前缀，只有前缀的链接指向下一层分片
+        let prefix_len = node
+            .hamt_fanout()
+            .map(|fanout| format!("{:X}", fanout.saturating_sub(1)).len());
+        if prefix_len.is_some() {
+            shards += 1;
+        }
+        for link in node.links {
+            let (Some(name), Some(hash)) = (link.name, link.hash) else {
+                continue;
+            };
+            match prefix_len {
+                Some(len) if name.len() == len => pending.push(hash.cid),
+                Some(len) => entries.push(DirectoryEntry {
+                    name: name.get(len..).unwrap_or_default().to_string(),
+                    cid: hash.cid,
+                }),
+                None => entries.push(DirectoryEntry {
+                    name,
+                    cid: hash.cid,
+                }),
+            }
+        }
+    }
+    if shards > 0 {
+        info!(
+            "🧩 {} is a sharded directory: read {} shards, {} entries",
+            cid,
+            shards,
+            entries.len()
+        );
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// 通过 IPFS 网关读取目录 CID 下的文件名列表
+pub(crate) async fn fetch_directory_listing(
+    api: &dyn StorageBackend,
+    cid: &str,
+) -> Result<Vec<String>> {
+    Ok(fetch_directory_entries(api, cid)
+        .await?
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect())
+}
+
+/// 校验目录 CID 中的条目与本地上传的文件一一对应，发现缺失文件时报错
+pub(crate) async fn verify_directory_upload(
+    api: &dyn StorageBackend,
+    cid: &str,
+    dir: &Path,
+    files: &[PathBuf],
+) -> Result<()> {
+    if api.is_dry_run() {
+        info!("🧪 [dry-run] Skipping verification of {}", cid);
+        return Ok(());
+    }
+
+    info!("🔍 Verifying uploaded folder {} ...", cid);
+    // 只比较目录第一层的条目
+    let expected = files
+        .iter()
+        .map(|file| {
+            file.strip_prefix(dir)?
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .ok_or_else(|| anyhow!("Invalid file path: {}", file.display()))
+        })
+        .collect::<Result<BTreeSet<_>>>()?;
+    let listed: HashSet<String> = api.list_directory(cid).await?.into_iter().collect();
+
+    let missing: Vec<&str> = expected
+        .iter()
+        .filter(|name| !listed.contains(*name))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() || listed.len() != expected.len() {
+        return Err(anyhow!(
+            "❌ Verification failed for {}: expected {} entries, gateway lists {}; missing: [{}]",
+            cid,
+            expected.len(),
+            listed.len(),
+            missing.join(", ")
+        ));
+    }
+
+    info!(
+        "✅ Verified {}: all {} entries are present",
+        cid,
+        expected.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::error::tests::http_error;
+
+    /// 最多重试 `max_retries` 次、几乎不等待的策略
+    pub(crate) fn fast_policy(max_retries: usize) -> RetryPolicy {
+        Config {
+            max_retries,
+            retry_delay_ms: 1,
+            max_backoff_ms: 1,
+            ..Config::default()
+        }
+        .retry_policy()
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let attempts = AtomicUsize::new(0);
+        let result: Result<()> = retry_with_timeout(fast_policy(3), || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err(http_error(401))
+        })
+        .await;
+
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        let error = result.unwrap_err();
+        assert!(
+            matches!(
+                error.downcast_ref::<UploadError>(),
+                Some(UploadError::AuthFailed(_))
+            ),
+            "{:#}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_until_success() {
+        let attempts = AtomicUsize::new(0);
+        let result = retry_with_timeout(fast_policy(3), || async {
+            match attempts.fetch_add(1, Ordering::Relaxed) {
+                0 | 1 => Err(http_error(503)),
+                _ => Ok("cid"),
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "cid");
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn transient_errors_give_up_after_max_retries() {
+        let attempts = AtomicUsize::new(0);
+        let result: Result<()> = retry_with_timeout(fast_policy(2), || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err(http_error(503))
+        })
+        .await;
+
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<UploadError>(),
+            Some(UploadError::Network(_))
+        ));
+    }
+}
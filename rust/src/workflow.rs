@@ -0,0 +1,2759 @@
+//! batch / single / upload-dir / upload-car / contract-meta / pin / unpin / unpin-batch / queue / list / usage / verify / test 子命令的工作流
+
+use crate::cache::{CACHE_FILE, CidCache, hash_bytes, hash_directory, hash_file};
+use crate::car::inspect_car;
+use crate::cid_display::same_content;
+use crate::cli::{ListSort, QueueStatus};
+use crate::config::format_size;
+use crate::image_check::{ImageRules, check_images};
+use crate::metadata::{
+    ContractMetadata, ImageSource, MetadataOptions, NftMetadata, TokenIds, calculate_folder_size,
+    create_metadata_files, metadata_format, serialize_metadata, validate_before_upload,
+    write_file_atomically,
+};
+use crate::optimize::optimize_images;
+use crate::pinata::{PinataClient, validate_cid};
+use crate::results::{
+    BatchRecord, BatchResult, FailedUpload, PerFileRecord, PriorUploads, SingleRecord,
+    SingleResult, TokenUploadResult, UploadKind, UploadLog, base_uri, build_manifest, hash_assets,
+    read_asset_manifest, record_timestamp, save_batch_results, save_per_file_results,
+    save_single_results, write_asset_manifest,
+};
+use crate::storage::StorageBackend;
+use crate::thumbnail::generate_thumbnails;
+use crate::unixfs::{directory_cid, pack_directory_car};
+use crate::upload::{
+    Cancelled, UploadControl, UploadedFiles, cancellable, fetch_from_gateway, retry_with_timeout,
+    total_size, upload_directory_with_retry, upload_files_concurrently,
+    upload_single_file_to_storage, upload_single_file_with_retry, upload_span,
+    verify_directory_upload,
+};
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use indicatif::MultiProgress;
+use pinata_sdk::{
+    JobStatus, PinJobsFilterBuilder, PinListFilterBuilder, PinListFilterStatus, PinListItem,
+    SortDirection,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, error, info, warn};
+use walkdir::WalkDir;
+
+// --- 工作流 ---
+/// 列出目录中的图片文件，并按 token ID 排序；`recursive` 时同时列出子目录中的图片。
+///
+/// 隐藏文件（如 `.DS_Store`、`.gitkeep`）、属性侧车文件和扩展名不在 `--include-ext` 列表中的文件（如 `Thumbs.db`）都会被跳过，
+/// 递归时隐藏目录（如 `.git`）整个跳过
+fn list_image_files(
+    dir: &Path,
+    options: &MetadataOptions,
+    recursive: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut image_files = Vec::new();
+    let mut hidden = 0usize;
+    let mut sidecars = 0usize;
+    let mut unsupported = Vec::new();
+
+    let max_depth = if recursive { usize::MAX } else { 1 };
+    let mut entries = WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .into_iter();
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type().is_dir() {
+            if file_name.starts_with('.') {
+                entries.skip_current_dir();
+            }
+            continue;
+        }
+        let path = entry.into_path();
+        if !path.is_file() {
+            continue;
+        }
+        if file_name.starts_with('.') {
+            hidden += 1;
+        } else if options.is_sidecar(&path) {
+            sidecars += 1;
+        } else if !options.is_image(&path) {
+            unsupported.push(file_name);
+        } else {
+            image_files.push(path);
+        }
+    }
+
+    if hidden > 0 {
+        info!("⏭️  Skipped {} hidden files in {:?}", hidden, dir);
+    }
+    if sidecars > 0 {
+        info!(
+            "⏭️  Skipped {} attribute sidecar files in {:?}",
+            sidecars, dir
+        );
+    }
+    if !unsupported.is_empty() {
+        unsupported.sort();
+        warn!(
+            "⚠️  Skipped {} files with unsupported extensions in {:?} (accepted: {}): {}",
+            unsupported.len(),
+            dir,
+            options.image_extensions.join(", "),
+            unsupported.join(", ")
+        );
+    }
+
+    sort_image_files(&mut image_files, options);
+    if recursive {
+        let subdirs = image_files
+            .iter()
+            .filter_map(|path| path.parent())
+            .filter(|parent| *parent != dir)
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        info!(
+            "📂 Found {} images in {:?} and {} subdirectories",
+            image_files.len(),
+            dir,
+            subdirs
+        );
+    }
+    Ok(image_files)
+}
+
+/// `--file-list` 指定的图片，代替扫描 `batch_images/`。
+///
+/// 相对路径相对于当前目录；文件夹上传以所有文件最近的公共目录为根，图片文件夹中保留相对于它的路径
+#[derive(Debug, Clone)]
+pub struct FileList {
+    pub root: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+impl FileList {
+    /// 逐行读取路径（`-` 表示 stdin），忽略空行；任何一个路径不存在或不是文件时报错
+    pub fn read(source: &Path) -> Result<Self> {
+        let content = if source == Path::new("-") {
+            std::io::read_to_string(std::io::stdin())
+                .context("Failed to read the file list from stdin")?
+        } else {
+            fs::read_to_string(source)
+                .with_context(|| format!("Failed to read the file list {:?}", source))?
+        };
+
+        let mut files = Vec::new();
+        let mut missing = Vec::new();
+        for line in content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            let path = std::path::absolute(line)?;
+            if !path.is_file() {
+                missing.push(line.to_string());
+            } else if files.contains(&path) {
+                warn!("⚠️  {} is listed more than once, uploading it once", line);
+            } else {
+                files.push(path);
+            }
+        }
+        if !missing.is_empty() {
+            let more = match missing.len().saturating_sub(MAX_LISTED_ENTRIES) {
+                0 => String::new(),
+                n => format!(" and {} more", n),
+            };
+            return Err(anyhow!(
+                "❌ {} listed paths are not existing files: {}{}",
+                missing.len(),
+                missing[..missing.len().min(MAX_LISTED_ENTRIES)].join(", "),
+                more
+            ));
+        }
+        let Some(first) = files.first() else {
+            return Err(anyhow!("❌ The file list {:?} is empty", source));
+        };
+
+        let mut root = first.parent().unwrap_or(Path::new("/")).to_path_buf();
+        for file in &files {
+            while !file.starts_with(&root) {
+                root = root.parent().unwrap_or(Path::new("/")).to_path_buf();
+            }
+        }
+        Ok(FileList { root, files })
+    }
+}
+
+/// 上传前的 token ID 检查：重复 ID 会导致元数据文件互相覆盖，属于硬错误；
+/// 序列中的缺号默认只给出警告
+fn validate_token_ids(
+    image_files: &[PathBuf],
+    allow_gaps: bool,
+    options: &MetadataOptions,
+) -> Result<()> {
+    if options.string_ids {
+        return validate_string_token_ids(image_files, options);
+    }
+
+    let mut seen: BTreeMap<u64, &Path> = BTreeMap::new();
+    let mut duplicates = Vec::new();
+
+    for image_file in image_files {
+        let token_id_str = options.token_id(image_file)?;
+        let token_id: u64 = token_id_str.parse().with_context(|| {
+            format!(
+                "❌ Filename {:?} is not a numeric token ID (use --string-ids for non-numeric filenames)",
+                image_file
+            )
+        })?;
+        if let Some(previous) = seen.insert(token_id, image_file) {
+            duplicates.push(format!(
+                "{} ({} and {})",
+                token_id,
+                previous.display(),
+                image_file.display()
+            ));
+        }
+    }
+
+    if !duplicates.is_empty() {
+        return Err(anyhow!(
+            "❌ Duplicate token IDs found: {}",
+            duplicates.join(", ")
+        ));
+    }
+
+    if !allow_gaps {
+        let ids: Vec<u64> = seen.keys().copied().collect();
+        let gaps: Vec<String> = ids
+            .windows(2)
+            .filter(|pair| pair[1] - pair[0] > 1)
+            .map(|pair| match (pair[0] + 1, pair[1] - 1) {
+                (start, end) if start == end => start.to_string(),
+                (start, end) => format!("{}-{}", start, end),
+            })
+            .collect();
+        if !gaps.is_empty() {
+            warn!(
+                "⚠️  Token ID sequence has gaps: missing {} (use --allow-gaps to silence)",
+                gaps.join(", ")
+            );
+        }
+    }
+
+    info!("🔢 Validated {} unique token IDs", seen.len());
+    Ok(())
+}
+
+/// `--string-ids` 模式下只需保证文件名（不含扩展名）唯一，缺号检查没有意义
+fn validate_string_token_ids(image_files: &[PathBuf], options: &MetadataOptions) -> Result<()> {
+    let mut seen: HashMap<String, &Path> = HashMap::new();
+    let mut duplicates = Vec::new();
+
+    for image_file in image_files {
+        let token_id_str = options.token_id(image_file)?;
+        if let Some(previous) = seen.insert(token_id_str.clone(), image_file) {
+            duplicates.push(format!(
+                "{} ({} and {})",
+                token_id_str,
+                previous.display(),
+                image_file.display()
+            ));
+        }
+    }
+
+    if !duplicates.is_empty() {
+        return Err(anyhow!(
+            "❌ Duplicate token IDs found: {}",
+            duplicates.join(", ")
+        ));
+    }
+
+    info!("🔢 Validated {} unique string token IDs", seen.len());
+    Ok(())
+}
+
+/// 目录遍历的顺序由操作系统决定；按文件名数值排序（`2.png` 在 `10.png` 之前），
+/// 非数字文件名排在数字之后并按字典序排列，保证日志和 single 模式的选择可复现。
+/// 按相对路径生成 token ID 时先按所在目录分组
+fn sort_image_files(image_files: &mut [PathBuf], options: &MetadataOptions) {
+    image_files.sort_by_cached_key(|path| {
+        let group = match options.token_ids {
+            TokenIds::RelativePath(_) => path.parent().map(Path::to_path_buf),
+            TokenIds::FileStem | TokenIds::Sequential(_) => None,
+        };
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match stem.parse::<u64>() {
+            Ok(id) => (group, 0, id, stem, path.clone()),
+            Err(_) => (group, 1, 0, stem, path.clone()),
+        }
+    });
+}
+
+/// 拒绝覆盖目录时最多列出的条目数
+const MAX_LISTED_ENTRIES: usize = 10;
+
+/// 输入/输出目录
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// 素材根目录，batch 读取 `batch_images/`，single 读取 `image/`
+    pub assets_dir: PathBuf,
+    /// 结果输出的根目录，各次运行在其下创建带时间戳的子目录
+    pub output_dir: PathBuf,
+    /// 允许删除已存在且非空的元数据工作目录
+    pub overwrite: bool,
+    /// `--deterministic`：代替目录名中时间戳的固定名称；设置后结果文件中的时间也是固定值
+    pub run_name: Option<String>,
+    /// 大批量上传开始前的确认
+    pub confirmation: UploadConfirmation,
+}
+
+/// 超过任一阈值的上传开始前在终端中询问用户，避免误把整个大目录固定到账户上消耗额度
+#[derive(Debug, Clone, Copy)]
+pub struct UploadConfirmation {
+    pub max_bytes: u64,
+    pub max_files: usize,
+    /// `--yes`：不询问，直接上传
+    pub assume_yes: bool,
+}
+
+impl Workspace {
+    pub fn batch_images_dir(&self) -> PathBuf {
+        self.assets_dir.join("batch_images")
+    }
+
+    fn single_image_dir(&self) -> PathBuf {
+        self.assets_dir.join("image")
+    }
+
+    fn output_path(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.output_dir.join(name)
+    }
+
+    /// 本次运行的结果目录 `<prefix>-<时间戳>`；名称固定时目录可能已经存在，
+    /// 按 `--overwrite` 清空，避免上一次运行留下的文件混进结果
+    fn run_output_dir(&self, prefix: &str) -> Result<PathBuf> {
+        let Some(name) = &self.run_name else {
+            let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S-%3fZ");
+            return Ok(self.output_path(format!("{}-{}", prefix, timestamp)));
+        };
+        let dir = self.output_path(format!("{}-{}", prefix, name));
+        self.prepare_empty_dir(&dir)?;
+        Ok(dir)
+    }
+
+    /// 元数据工作目录名中的时间戳：秒级时间加随机后缀，脚本在同一秒内并行启动多次时目录也不会相撞
+    fn work_dir_stamp(&self) -> String {
+        match &self.run_name {
+            Some(name) => name.clone(),
+            None => format!(
+                "{}_{:08x}",
+                Utc::now().format("%Y%m%d_%H%M%S"),
+                rand::random::<u32>()
+            ),
+        }
+    }
+
+    /// 创建名为 `name` 的元数据工作目录，保留时的位置 `output/<name>` 按 `--overwrite` 预先检查
+    fn create_work_dir(&self, name: String) -> Result<WorkDir> {
+        let target = self.output_path(&name);
+        self.prepare_empty_dir(&target)?;
+        fs::create_dir_all(&self.output_dir)?;
+        // 临时目录与最终位置在同一个文件系统中，保留时只需重命名
+        let temp = tempfile::Builder::new()
+            .prefix(".work-")
+            .tempdir_in(&self.output_dir)?;
+        // 上传的文件夹名（pin 名称、results.jsonl）仍然是 `name`，而不是临时目录的随机名称
+        let path = temp.path().join(&name);
+        fs::create_dir(&path)?;
+        Ok(WorkDir { temp, path, target })
+    }
+
+    /// 写入结果文件的时间
+    fn timestamp(&self) -> String {
+        record_timestamp(self.run_name.is_some())
+    }
+
+    /// 上传 `files` 之前按阈值确认；`--yes`、dry run 或 stdin / stdout 不是终端时直接继续，用户拒绝时返回错误
+    fn confirm_upload(
+        &self,
+        api: &dyn StorageBackend,
+        source: &Path,
+        files: usize,
+        size: u64,
+    ) -> Result<()> {
+        let limits = &self.confirmation;
+        if size <= limits.max_bytes && files <= limits.max_files {
+            return Ok(());
+        }
+        if limits.assume_yes || api.is_dry_run() {
+            return Ok(());
+        }
+        if !is_interactive() {
+            info!(
+                "⚠️  Large upload ({} files, {}), not asking for confirmation outside a terminal",
+                files,
+                format_size(size)
+            );
+            return Ok(());
+        }
+
+        let question = format!(
+            "⚠️  About to upload {} {} ({}, {} bytes) from {} to {}.",
+            files,
+            if files == 1 { "file" } else { "files" },
+            format_size(size),
+            size,
+            source.display(),
+            api.name()
+        );
+        if ask_yes_no(&question)? {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "❌ Upload of {} not confirmed (pass --yes to skip this prompt)",
+            source.display()
+        ))
+    }
+
+    pub fn assume_yes(&self) -> bool {
+        self.confirmation.assume_yes
+    }
+
+    fn cache_file(&self) -> PathBuf {
+        self.output_dir.join(CACHE_FILE)
+    }
+
+    /// 准备一个空目录（元数据工作目录或固定名称的结果目录）；目录已存在且非空时，只有传入 `--overwrite` 才会删除
+    fn prepare_empty_dir(&self, dir: &Path) -> Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        if !self.overwrite {
+            entries.sort();
+            let shown = entries
+                .iter()
+                .take(MAX_LISTED_ENTRIES)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let more = match entries.len().saturating_sub(MAX_LISTED_ENTRIES) {
+                0 => String::new(),
+                n => format!(" and {} more", n),
+            };
+            return Err(anyhow!(
+                "Refusing to replace existing directory {} ({} entries would be removed: {}{}), pass --overwrite to replace it",
+                dir.display(),
+                entries.len(),
+                shown,
+                more
+            ));
+        }
+        warn!(
+            "🗑️  Removing existing directory {} ({} entries, --overwrite)",
+            dir.display(),
+            entries.len()
+        );
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
+
+/// batch 子命令的运行参数
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    pub both_versions: bool,
+    pub per_file: bool,
+    pub concurrency: NonZeroUsize,
+    /// 复用之前上传的图片文件夹 CID，跳过图片上传
+    pub images_cid: Option<String>,
+    pub allow_gaps: bool,
+    /// 文件夹上传后通过网关校验目录内容
+    pub verify: bool,
+    /// 忽略图片文件夹 CID 缓存，强制重新上传图片
+    pub no_cache: bool,
+    /// 上传元数据文件夹前在本地计算 CID，并与存储后端返回的 CID 比对
+    pub local_cid: bool,
+    /// 触发后中止进行中的上传，保存已完成部分的结果
+    pub cancel: CancellationToken,
+    /// 逐文件模式下从该运行目录的 `results.jsonl` 继续，跳过内容未变化的已上传文件
+    pub resume: Option<PathBuf>,
+    /// 上传前解码图片并按规则检查，`None` 时不检查
+    pub image_rules: Option<ImageRules>,
+    /// 同时上传 `batch_images/` 子目录中的图片，图片文件夹 CID 保留目录结构
+    pub recursive: bool,
+    /// 按排序后的顺序从该值开始分配 token ID，而不是使用文件名
+    pub start_token_id: Option<u64>,
+    /// 上传成功后如何处理元数据工作目录
+    pub working_dirs: WorkingDirs,
+    /// 逐文件模式下单个文件失败后继续上传其余文件，失败的文件记录在结果中
+    pub continue_on_error: bool,
+    /// README 中建议的 Base URI 是否以 `/` 结尾
+    pub base_uri_trailing_slash: bool,
+    /// 上传优化后的图片副本，值为 JPEG 重新编码的质量；`None` 时上传原图
+    pub optimize_images: Option<u8>,
+    /// 上传前与该运行目录的 `manifest.sha256` 比对，素材有任何变化时中止
+    pub verify_manifest: Option<PathBuf>,
+    /// 只上传列表中的图片，不扫描 `batch_images/`
+    pub file_list: Option<FileList>,
+    /// 额外上传长边不超过该像素数的缩略图文件夹，元数据中写入 `thumbnail`
+    pub generate_thumbnails: Option<u32>,
+    /// 要上传的图片中有超过该字节数的文件时，在上传任何内容之前中止
+    pub max_file_size: Option<u64>,
+}
+
+impl BatchOptions {
+    /// 图片所在的根目录：`--file-list` 时为列表中文件的公共目录，否则为 `batch_images/`
+    fn images_dir(&self, workspace: &Workspace) -> PathBuf {
+        match &self.file_list {
+            Some(list) => list.root.clone(),
+            None => workspace.batch_images_dir(),
+        }
+    }
+
+    /// `--max-file-size`：只检查真正要上传的文件，复用的 CID（`--images-cid`、缓存、`--resume`）不受限制
+    fn check_file_sizes(&self, files: &[PathBuf], root: &Path) -> Result<()> {
+        match self.max_file_size {
+            Some(max_file_size) => check_file_sizes(files, root, max_file_size),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 上传成功后如何处理 `output/` 下的元数据工作目录；结果目录 `output/<run>/` 中的元数据副本总是保留
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkingDirs {
+    /// 默认：保留元数据工作目录，双版本时删除带后缀的版本
+    #[default]
+    KeepMetadata,
+    /// `--prune-local`：结果保存后删除所有工作目录
+    Prune,
+    /// `--keep-working`：保留所有工作目录，便于调试
+    KeepAll,
+}
+
+/// 本次运行的元数据工作目录，位于 `output/` 下名称唯一的临时目录中，并行的运行和双版本的两个目录互不干扰。
+/// 出错、取消（随持有它的 future 一起丢弃）或 panic 时连同临时目录一起删除，只有 [`WorkDir::keep`] 会把它移到 `output/`
+struct WorkDir {
+    /// 丢弃时删除整个临时目录
+    temp: tempfile::TempDir,
+    path: PathBuf,
+    /// 保留时的位置：`output/<name>`
+    target: PathBuf,
+}
+
+impl WorkDir {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 移到 `output/<name>` 保留；失败时只给出警告，结果目录中已经有元数据副本
+    fn keep(self) {
+        let moved = match fs::remove_dir(&self.target) {
+            // `create_work_dir` 已确认同名目录为空或不存在
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => fs::rename(&self.path, &self.target),
+        };
+        if let Err(e) = moved {
+            warn!(
+                "⚠️  Failed to keep working directory {}: {}",
+                self.target.display(),
+                e
+            );
+        }
+    }
+
+    /// `--prune-local`：结果保存后删除
+    fn remove(self) {
+        let target = self.target;
+        match self.temp.close() {
+            Ok(()) => info!("🧹 Removed working directory {}", target.display()),
+            Err(e) => warn!(
+                "⚠️  Failed to remove working directory {}: {}",
+                target.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// 上传前校验元数据时代替尚未得到的图片 CID
+const PENDING_UPLOAD_ID: &str = "pending";
+
+pub async fn process_batch_collection(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    batch: &BatchOptions,
+    options: &MetadataOptions,
+    progress: &MultiProgress,
+) -> Result<BatchResult> {
+    info!("==============================================");
+    info!(
+        "🚀 Starting batch NFT collection processing ({})...",
+        api.name()
+    );
+    info!("==============================================");
+
+    let images_input_dir = batch.images_dir(workspace);
+    if batch.file_list.is_none() && !images_input_dir.exists() {
+        return Err(anyhow!(
+            "❌ Input directory does not exist: {:?} (assets dir can be changed with --assets-dir or ASSETS_DIR)",
+            images_input_dir
+        ));
+    }
+
+    if batch.resume.is_some() && matches!(options.token_ids, TokenIds::RelativePath(_)) {
+        return Err(anyhow!(
+            "❌ --resume matches images by file name and cannot be combined with --token-ids path"
+        ));
+    }
+    let image_files = match &batch.file_list {
+        Some(list) => {
+            let unsupported: Vec<String> = list
+                .files
+                .iter()
+                .filter(|path| !options.is_image(path))
+                .map(|path| path.display().to_string())
+                .collect();
+            if !unsupported.is_empty() {
+                return Err(anyhow!(
+                    "❌ The file list contains files with unsupported extensions (accepted: {}): {}",
+                    options.image_extensions.join(", "),
+                    unsupported.join(", ")
+                ));
+            }
+            let mut image_files = list.files.clone();
+            sort_image_files(&mut image_files, options);
+            info!(
+                "📋 {} images from the file list, root folder {:?}",
+                image_files.len(),
+                images_input_dir
+            );
+            image_files
+        }
+        None => list_image_files(&images_input_dir, options, batch.recursive)?,
+    };
+    let numbered;
+    let options = match batch.start_token_id {
+        Some(start) => {
+            numbered = options.numbered_from(start, &image_files)?;
+            info!(
+                "🔢 Numbering {} images in sorted order from token ID {}",
+                image_files.len(),
+                start
+            );
+            &numbered
+        }
+        None => options,
+    };
+    validate_token_ids(&image_files, batch.allow_gaps, options)?;
+    if let Some(rules) = &batch.image_rules {
+        check_images(&image_files, rules)?;
+    }
+    validate_before_upload(
+        &image_files,
+        &images_input_dir,
+        &api.uri(PENDING_UPLOAD_ID),
+        options,
+    )?;
+
+    if let Some(run_dir) = &batch.verify_manifest {
+        check_asset_drift(run_dir, &images_input_dir, &image_files)?;
+    }
+
+    // 优化后的副本只用于上传，元数据、侧车文件和 token ID 仍然来自原图
+    let optimized = batch
+        .optimize_images
+        .map(|quality| optimize_images(&image_files, &images_input_dir, quality))
+        .transpose()?;
+    let (upload_dir, upload_files) = match &optimized {
+        Some(optimized) => (optimized.dir(), optimized.files.as_slice()),
+        None => (images_input_dir.as_path(), image_files.as_slice()),
+    };
+    let bytes_saved = optimized.as_ref().map(|optimized| optimized.bytes_saved);
+    // 缩略图从原图生成，而不是从 --optimize-images 的副本
+    let thumbnails = batch
+        .generate_thumbnails
+        .map(|max_dim| generate_thumbnails(&image_files, &images_input_dir, max_dim))
+        .transpose()?;
+
+    if batch.per_file {
+        let mut result = process_batch_per_file(
+            api,
+            workspace,
+            &image_files,
+            upload_files,
+            batch,
+            options,
+            progress,
+        )
+        .await?;
+        result.bytes_saved = bytes_saved;
+        return Ok(result);
+    }
+    if batch.concurrency.get() > 1 {
+        warn!(
+            "⚠️  --concurrency only applies to --per-file uploads, folders are uploaded one at a time"
+        );
+    }
+
+    let output_dir = workspace.run_output_dir("batch-upload")?;
+    let log = UploadLog::new(&output_dir, workspace.run_name.is_some());
+
+    let images_folder_cid = cancellable(
+        &batch.cancel,
+        upload_images_folder(api, workspace, upload_dir, upload_files, batch, &log),
+    )
+    .await?;
+    let thumbnails_cid = match &thumbnails {
+        Some(thumbnails) if !thumbnails.files.is_empty() => {
+            info!("📁 Uploading thumbnails folder...");
+            let cid = cancellable(
+                &batch.cancel,
+                upload_directory_with_retry(api, thumbnails.dir(), &thumbnails.files),
+            )
+            .await?;
+            log.append(UploadKind::ThumbnailsFolder, thumbnails.dir(), &cid)?;
+            if !api.is_dry_run() {
+                log.record_transfer(&thumbnails.files)?;
+            }
+            info!("🖼️  Thumbnails folder CID obtained: {}", cid);
+            Some(cid)
+        }
+        _ => None,
+    };
+    let thumbnail_uris = match (&thumbnails, &thumbnails_cid) {
+        (Some(thumbnails), Some(cid)) => Some(
+            thumbnails
+                .paths
+                .iter()
+                .map(|(image, path)| (image.clone(), api.uri(&format!("{}/{}", cid, path))))
+                .collect::<HashMap<_, _>>(),
+        ),
+        _ => None,
+    };
+    let images_folder_uri = api.uri(&images_folder_cid);
+    let images = ImageSource::Folder {
+        uri: &images_folder_uri,
+        root: &images_input_dir,
+        thumbnails: thumbnail_uris.as_ref(),
+    };
+
+    let results_dir = output_dir.join("results");
+    fs::create_dir_all(&results_dir)?;
+    write_asset_manifest(&output_dir, &images_input_dir, &image_files)?;
+
+    // 单版本生成时，根据配置的后缀决定是否带后缀
+    let should_use_suffix = !options.metadata_file_suffix.is_empty();
+    let metadata_stage = cancellable(&batch.cancel, async {
+        if batch.both_versions {
+            let (cid_with, cid_without, dir) = generate_and_upload_both_versions(
+                api,
+                workspace,
+                &image_files,
+                &images,
+                options,
+                batch,
+                &log,
+            )
+            .await?;
+            Ok((Some(cid_with), Some(cid_without), Some(dir)))
+        } else {
+            let (cid, dir) = generate_and_upload_single_version(
+                api,
+                workspace,
+                &image_files,
+                &images,
+                options,
+                batch,
+                &log,
+            )
+            .await?;
+            Ok((None, Some(cid), Some(dir)))
+        }
+    })
+    .await;
+    // 元数据上传中途取消时，仍然记录已经上传的图片文件夹
+    let (metadata_with_suffix_cid, metadata_without_suffix_cid, metadata_dir, cancelled) =
+        match metadata_stage {
+            Ok((with, without, dir)) => (with, without, dir, false),
+            Err(e) if e.is::<Cancelled>() => (None, None, None, true),
+            Err(e) => return Err(e),
+        };
+
+    // 清单与本地保存的元数据一致：双版本时对应不带后缀的版本
+    let manifest = match &metadata_without_suffix_cid {
+        Some(metadata_cid) => build_manifest(
+            api,
+            &image_files,
+            &images_input_dir,
+            &images_folder_cid,
+            metadata_cid,
+            options,
+            should_use_suffix && !batch.both_versions,
+        )?,
+        None => Vec::new(),
+    };
+
+    let throughput = log.throughput();
+    let record = BatchRecord {
+        base_uri_trailing_slash: batch.base_uri_trailing_slash,
+        throughput,
+        thumbnails_cid: thumbnails_cid.clone(),
+        timestamp: workspace.timestamp(),
+        ..BatchRecord::new(
+            api,
+            options.collection_name.as_deref(),
+            &images_folder_cid,
+            metadata_with_suffix_cid.as_deref(),
+            metadata_without_suffix_cid.as_deref(),
+            image_files.len(),
+            cancelled,
+        )
+    };
+    save_batch_results(
+        api,
+        &output_dir,
+        &record,
+        &manifest,
+        metadata_dir.as_ref().map(WorkDir::path),
+    )
+    .await?;
+    if let Some(dir) = metadata_dir {
+        match batch.working_dirs {
+            WorkingDirs::Prune => dir.remove(),
+            _ => dir.keep(),
+        }
+    }
+
+    Ok(BatchResult {
+        images_cid: Some(images_folder_cid),
+        thumbnails_cid,
+        metadata_with_suffix_cid,
+        metadata_without_suffix_cid,
+        tokens: Vec::new(),
+        total_files: image_files.len(),
+        output_dir,
+        dry_run: api.is_dry_run(),
+        failed: Vec::new(),
+        bytes_saved,
+        throughput,
+        cancelled,
+    })
+}
+
+/// `upload_files` 与 `image_files` 一一对应，是实际上传的文件（`--optimize-images` 时为优化后的副本）
+async fn process_batch_per_file(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    image_files: &[PathBuf],
+    upload_files: &[PathBuf],
+    batch: &BatchOptions,
+    options: &MetadataOptions,
+    progress: &MultiProgress,
+) -> Result<BatchResult> {
+    let collection = options.collection_name.as_deref();
+    info!(
+        "📦 Uploading {} images individually (concurrency: {})",
+        image_files.len(),
+        batch.concurrency
+    );
+    // 先读取 --resume 的记录：固定名称的结果目录可能就是要恢复的那次运行
+    let prior = match &batch.resume {
+        Some(run_dir) => {
+            let prior = PriorUploads::load(run_dir)?;
+            prior.check_images(api, upload_files)?;
+            info!("🔁 Resuming from {:?}", run_dir);
+            Some(prior)
+        }
+        None => None,
+    };
+    if batch.max_file_size.is_some() {
+        let mut pending = Vec::with_capacity(upload_files.len());
+        for file in upload_files {
+            if let Some(prior) = &prior
+                && prior.cid(UploadKind::Image, file)?.is_some()
+            {
+                continue;
+            }
+            pending.push(file.clone());
+        }
+        batch.check_file_sizes(&pending, &batch.images_dir(workspace))?;
+    }
+    workspace.confirm_upload(
+        api,
+        &batch.images_dir(workspace),
+        upload_files.len(),
+        total_size(upload_files)?,
+    )?;
+    let output_dir = workspace.run_output_dir("batch-upload")?;
+    let log = UploadLog::new(&output_dir, workspace.run_name.is_some());
+    let uploaded_images = upload_or_reuse(
+        api,
+        upload_files,
+        batch,
+        progress,
+        &log,
+        UploadKind::Image,
+        prior.as_ref(),
+    )
+    .await?;
+    let original: HashMap<&PathBuf, &PathBuf> = upload_files.iter().zip(image_files).collect();
+    let image_cids: HashMap<PathBuf, String> = uploaded_images
+        .cids
+        .into_iter()
+        .map(|(file, cid)| (original[&file].clone(), cid))
+        .collect();
+    let mut failed = uploaded_images
+        .failed
+        .into_iter()
+        .map(|(file, e)| {
+            let image_file = original[&file];
+            failed_upload(options, image_file, image_file, e)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let results_dir = output_dir.join("results");
+    fs::create_dir_all(&results_dir)?;
+    write_asset_manifest(&output_dir, &batch.images_dir(workspace), image_files)?;
+
+    // 图片上传阶段被取消：只记录已上传的图片，不生成元数据
+    if batch.cancel.is_cancelled() {
+        let tokens = image_files
+            .iter()
+            .filter_map(|image_file| {
+                let image_cid = image_cids.get(image_file)?.clone();
+                Some(token_result(options, image_file, image_cid, None))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let record = PerFileRecord {
+            throughput: log.throughput(),
+            timestamp: workspace.timestamp(),
+            ..PerFileRecord::new(api, collection, &tokens, &failed, true)
+        };
+        save_per_file_results(api, &output_dir, &record, None).await?;
+        return Ok(per_file_result(
+            api, tokens, output_dir, failed, &record, true,
+        ));
+    }
+
+    // 只为上传成功的图片生成并上传元数据
+    let image_files: Vec<PathBuf> = image_files
+        .iter()
+        .filter(|image_file| image_cids.contains_key(*image_file))
+        .cloned()
+        .collect();
+    if !failed.is_empty() {
+        warn!(
+            "⚠️  {} images failed to upload, generating metadata for the remaining {}",
+            failed.len(),
+            image_files.len()
+        );
+    }
+
+    let metadata_dir = workspace.create_work_dir(format!(
+        "batch_images-metadata-{}",
+        workspace.work_dir_stamp()
+    ))?;
+    let should_use_suffix = !options.metadata_file_suffix.is_empty();
+    log_metadata_suffix(options);
+    let image_uris = image_cids
+        .iter()
+        .map(|(path, cid)| (path.clone(), api.uri(cid)))
+        .collect();
+    let metadata_files = create_metadata_files(
+        &image_files,
+        metadata_dir.path(),
+        &ImageSource::PerFile(&image_uris),
+        options,
+        should_use_suffix,
+        false,
+    )
+    .await?;
+
+    info!(
+        "📦 Uploading {} metadata files individually (concurrency: {})",
+        metadata_files.len(),
+        batch.concurrency
+    );
+    let UploadedFiles {
+        cids: metadata_cids,
+        failed: failed_metadata,
+    } = upload_or_reuse(
+        api,
+        &metadata_files,
+        batch,
+        progress,
+        &log,
+        UploadKind::Metadata,
+        prior.as_ref(),
+    )
+    .await?;
+    let cancelled = batch.cancel.is_cancelled();
+    let image_by_metadata: HashMap<&PathBuf, &PathBuf> =
+        metadata_files.iter().zip(&image_files).collect();
+    for (metadata_file, e) in failed_metadata {
+        let image_file = image_by_metadata[&metadata_file];
+        failed.push(failed_upload(options, image_file, &metadata_file, e)?);
+    }
+
+    let tokens = image_files
+        .iter()
+        .zip(&metadata_files)
+        .map(|(image_file, metadata_file)| {
+            token_result(
+                options,
+                image_file,
+                image_cids[image_file].clone(),
+                metadata_cids.get(metadata_file).cloned(),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let record = PerFileRecord {
+        throughput: log.throughput(),
+        timestamp: workspace.timestamp(),
+        ..PerFileRecord::new(api, collection, &tokens, &failed, cancelled)
+    };
+    save_per_file_results(api, &output_dir, &record, Some(metadata_dir.path())).await?;
+    // 取消时结果目录中已有元数据副本，工作目录不再保留
+    if !cancelled {
+        if failed.is_empty() && batch.working_dirs == WorkingDirs::Prune {
+            metadata_dir.remove();
+        } else {
+            metadata_dir.keep();
+        }
+    }
+
+    Ok(per_file_result(
+        api, tokens, output_dir, failed, &record, cancelled,
+    ))
+}
+
+/// `--verify-manifest`：列出自记录的那次上传以来内容变化、新增和删除的图片，有任何差异时返回错误
+fn check_asset_drift(run_dir: &Path, root: &Path, image_files: &[PathBuf]) -> Result<()> {
+    let recorded = read_asset_manifest(run_dir)?;
+    let current = hash_assets(root, image_files)?;
+
+    let changed: Vec<&str> = current
+        .iter()
+        .filter(|(path, hash)| recorded.get(*path).is_some_and(|old| old != *hash))
+        .map(|(path, _)| path.as_str())
+        .collect();
+    let added: Vec<&str> = current
+        .keys()
+        .filter(|path| !recorded.contains_key(*path))
+        .map(String::as_str)
+        .collect();
+    let removed: Vec<&str> = recorded
+        .keys()
+        .filter(|path| !current.contains_key(*path))
+        .map(String::as_str)
+        .collect();
+
+    if changed.is_empty() && added.is_empty() && removed.is_empty() {
+        info!(
+            "✅ All {} images match the checksums recorded in {:?}",
+            current.len(),
+            run_dir
+        );
+        return Ok(());
+    }
+    for (label, paths) in [
+        ("Changed", &changed),
+        ("Added", &added),
+        ("Removed", &removed),
+    ] {
+        if !paths.is_empty() {
+            error!(
+                "❌ {} since the recorded upload: {}",
+                label,
+                paths.join(", ")
+            );
+        }
+    }
+    Err(anyhow!(
+        "❌ Assets differ from {:?}: {} changed, {} added, {} removed",
+        run_dir,
+        changed.len(),
+        added.len(),
+        removed.len()
+    ))
+}
+
+/// 逐文件上传；`--resume` 时文件名和内容与上一次记录一致的文件直接复用 CID（同样写入本次的 `results.jsonl`），其余文件正常上传
+async fn upload_or_reuse(
+    api: &dyn StorageBackend,
+    files: &[PathBuf],
+    batch: &BatchOptions,
+    progress: &MultiProgress,
+    log: &UploadLog,
+    kind: UploadKind,
+    prior: Option<&PriorUploads>,
+) -> Result<UploadedFiles> {
+    let mut cids = HashMap::new();
+    let mut pending = Vec::new();
+    for file in files {
+        match prior
+            .map(|prior| prior.cid(kind, file))
+            .transpose()?
+            .flatten()
+        {
+            Some(cid) => {
+                log.append(kind, file, cid)?;
+                cids.insert(file.clone(), cid.to_string());
+            }
+            None => pending.push(file.clone()),
+        }
+    }
+    if !cids.is_empty() {
+        info!(
+            "⏭️  Reusing {} CIDs from the previous run, uploading the remaining {}",
+            cids.len(),
+            pending.len()
+        );
+    }
+
+    let control = UploadControl {
+        concurrency: batch.concurrency,
+        cancel: &batch.cancel,
+        continue_on_error: batch.continue_on_error,
+    };
+    let mut uploaded =
+        upload_files_concurrently(api, &pending, control, progress, log, kind).await?;
+    uploaded.cids.extend(cids);
+    Ok(uploaded)
+}
+
+/// 记录一个重试用尽的文件；`image_file` 用于确定 token ID，`file` 是实际上传失败的文件
+fn failed_upload(
+    options: &MetadataOptions,
+    image_file: &Path,
+    file: &Path,
+    error: anyhow::Error,
+) -> Result<FailedUpload> {
+    let kind = if image_file == file {
+        UploadKind::Image
+    } else {
+        UploadKind::Metadata
+    };
+    Ok(FailedUpload {
+        error: format!("{:#}", error),
+        file: file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        kind,
+        token_id: options.token_id(image_file)?,
+    })
+}
+
+fn token_result(
+    options: &MetadataOptions,
+    image_file: &Path,
+    image_cid: String,
+    metadata_cid: Option<String>,
+) -> Result<TokenUploadResult> {
+    Ok(TokenUploadResult {
+        token_id: options.token_id(image_file)?,
+        image_file: image_file
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow!("Invalid filename"))?
+            .to_string(),
+        image_cid,
+        metadata_cid,
+    })
+}
+
+fn per_file_result(
+    api: &dyn StorageBackend,
+    tokens: Vec<TokenUploadResult>,
+    output_dir: PathBuf,
+    failed: Vec<FailedUpload>,
+    record: &PerFileRecord,
+    cancelled: bool,
+) -> BatchResult {
+    BatchResult {
+        images_cid: None,
+        thumbnails_cid: None,
+        metadata_with_suffix_cid: None,
+        metadata_without_suffix_cid: None,
+        total_files: tokens.len(),
+        tokens,
+        output_dir,
+        dry_run: api.is_dry_run(),
+        cancelled,
+        failed,
+        bytes_saved: None,
+        throughput: record.throughput,
+    }
+}
+
+async fn generate_and_upload_both_versions(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    image_files: &[PathBuf],
+    images: &ImageSource<'_>,
+    options: &MetadataOptions,
+    batch: &BatchOptions,
+    log: &UploadLog,
+) -> Result<(String, String, WorkDir)> {
+    info!(
+        "📝 Generating two metadata versions: with suffix \"{}\" (<token_id>{}) and without suffix (<token_id>)",
+        options.dual_version_suffix(),
+        options.dual_version_suffix()
+    );
+
+    // Create separate directories for each version
+    let timestamp = workspace.work_dir_stamp();
+    let metadata_dir_with_suffix =
+        workspace.create_work_dir(format!("batch_images-metadata-with-suffix-{}", timestamp))?;
+    let metadata_dir_without_suffix = workspace.create_work_dir(format!(
+        "batch_images-metadata-without-suffix-{}",
+        timestamp
+    ))?;
+
+    // Create version with suffix
+    let metadata_files_with_suffix = create_metadata_files(
+        image_files,
+        metadata_dir_with_suffix.path(),
+        images,
+        options,
+        true, // with suffix
+        true, // is_dual_version
+    )
+    .await?;
+
+    info!("📁 Uploading metadata folder with suffix...");
+    let cid_with = upload_metadata_folder(
+        api,
+        metadata_dir_with_suffix.path(),
+        &metadata_files_with_suffix,
+        batch,
+        log,
+    )
+    .await?;
+
+    // Create version without suffix
+    let metadata_files_without_suffix = create_metadata_files(
+        image_files,
+        metadata_dir_without_suffix.path(),
+        images,
+        options,
+        false, // without suffix
+        true,  // is_dual_version
+    )
+    .await?;
+
+    info!("📁 Uploading metadata folder without suffix...");
+    let cid_without = upload_metadata_folder(
+        api,
+        metadata_dir_without_suffix.path(),
+        &metadata_files_without_suffix,
+        batch,
+        log,
+    )
+    .await?;
+
+    // 带后缀的版本只用于上传，默认随临时目录删除；不带后缀的版本保存到结果目录后按 --prune-local 处理
+    if batch.working_dirs == WorkingDirs::KeepAll {
+        metadata_dir_with_suffix.keep();
+    }
+
+    Ok((cid_with, cid_without, metadata_dir_without_suffix))
+}
+
+fn log_metadata_suffix(options: &MetadataOptions) {
+    let suffix = &options.metadata_file_suffix;
+    let format = metadata_format(Path::new(&format!("<token_id>{}", suffix)));
+    if suffix.is_empty() {
+        info!(
+            "📝 Metadata files have no suffix (<token_id>), written as {}",
+            format
+        );
+    } else {
+        info!(
+            "📝 Metadata files use suffix \"{}\" (<token_id>{}), written as {}",
+            suffix, suffix, format
+        );
+    }
+}
+
+async fn generate_and_upload_single_version(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    image_files: &[PathBuf],
+    images: &ImageSource<'_>,
+    options: &MetadataOptions,
+    batch: &BatchOptions,
+    log: &UploadLog,
+) -> Result<(String, WorkDir)> {
+    let with_suffix = !options.metadata_file_suffix.is_empty();
+    log_metadata_suffix(options);
+    let metadata_dir = workspace.create_work_dir(format!(
+        "batch_images-metadata-{}",
+        workspace.work_dir_stamp()
+    ))?;
+
+    let metadata_files = create_metadata_files(
+        image_files,
+        metadata_dir.path(),
+        images,
+        options,
+        with_suffix,
+        false,
+    )
+    .await?;
+
+    info!("📁 Uploading metadata folder...");
+    let cid = upload_metadata_folder(api, metadata_dir.path(), &metadata_files, batch, log).await?;
+
+    // Don't remove the directory, we'll save it
+    Ok((cid, metadata_dir))
+}
+
+/// 取得图片文件夹 CID：`--images-cid` 指定的、缓存中的或新上传的；`--verify` 时通过网关回读目录
+async fn upload_images_folder(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    images_input_dir: &Path,
+    image_files: &[PathBuf],
+    batch: &BatchOptions,
+    log: &UploadLog,
+) -> Result<String> {
+    let images_folder_cid = match batch.images_cid.as_deref() {
+        Some(cid) => {
+            api.validate_id(cid)?;
+            info!(
+                "⏭️  Skipping image upload, reusing images folder CID: {}",
+                cid
+            );
+            cid.to_string()
+        }
+        // dry-run 的占位 CID 不读写缓存
+        None if api.is_dry_run() => {
+            batch.check_file_sizes(image_files, images_input_dir)?;
+            let cid = upload_directory_with_retry(api, images_input_dir, image_files).await?;
+            log.append(UploadKind::ImagesFolder, images_input_dir, &cid)?;
+            cid
+        }
+        None => {
+            let hash =
+                hash_directory(images_input_dir, image_files, api.name(), api.cid_version())?;
+            let mut cache = CidCache::load(workspace.cache_file());
+            match cache.get(&hash).filter(|_| !batch.no_cache) {
+                Some(cid) => {
+                    info!(
+                        "♻️  Images unchanged since a previous upload, reusing cached images folder CID: {} (use --no-cache to re-upload)",
+                        cid
+                    );
+                    cid.to_string()
+                }
+                None => {
+                    batch.check_file_sizes(image_files, images_input_dir)?;
+                    workspace.confirm_upload(
+                        api,
+                        images_input_dir,
+                        image_files.len(),
+                        total_size(image_files)?,
+                    )?;
+                    // 只上传图片文件，属性侧车文件不会进入图片文件夹 CID
+                    let cid =
+                        upload_directory_with_retry(api, images_input_dir, image_files).await?;
+                    log.append(UploadKind::ImagesFolder, images_input_dir, &cid)?;
+                    log.record_transfer(image_files)?;
+                    info!("\n🖼️  Images folder CID obtained: {}", cid);
+                    if let Err(e) = cache.insert(hash, cid.clone()) {
+                        warn!("⚠️  Failed to update CID cache: {:#}", e);
+                    }
+                    cid
+                }
+            }
+        }
+    };
+    if batch.verify {
+        verify_directory_upload(api, &images_folder_cid, images_input_dir, image_files).await?;
+    }
+    Ok(images_folder_cid)
+}
+
+/// 上传一个元数据文件夹；`--local-cid` 时先在本地计算预期的 CID 并与返回的 CID 比对，`--verify` 时再通过网关回读目录
+async fn upload_metadata_folder(
+    api: &dyn StorageBackend,
+    dir: &Path,
+    files: &[PathBuf],
+    batch: &BatchOptions,
+    log: &UploadLog,
+) -> Result<String> {
+    let expected_cid = if batch.local_cid {
+        local_folder_cid(api, dir, files)
+    } else {
+        None
+    };
+
+    let cid = upload_directory_with_retry(api, dir, files).await?;
+    log.append(UploadKind::MetadataFolder, dir, &cid)?;
+    if !api.is_dry_run() {
+        log.record_transfer(files)?;
+    }
+    if let Some(expected_cid) = expected_cid
+        && !api.is_dry_run()
+    {
+        if same_content(&expected_cid, &cid) {
+            info!(
+                "✅ {} returned the locally computed CID {}",
+                api.name(),
+                cid
+            );
+        } else {
+            warn!(
+                "⚠️  {} returned CID {} for {}, but the locally computed CID is {}: the stored folder may differ from the local files",
+                api.name(),
+                cid,
+                dir.display(),
+                expected_cid
+            );
+        }
+    }
+    if batch.verify {
+        verify_directory_upload(api, &cid, dir, files).await?;
+    }
+    Ok(cid)
+}
+
+/// 按后端的 CID 版本在本地计算文件夹 CID；非 IPFS 后端或无法计算时给出提示并返回 `None`
+fn local_folder_cid(api: &dyn StorageBackend, dir: &Path, files: &[PathBuf]) -> Option<String> {
+    let Some(cid_version) = api.cid_version() else {
+        warn!(
+            "⚠️  {} does not store content by IPFS CID, skipping --local-cid",
+            api.name()
+        );
+        return None;
+    };
+    match directory_cid(dir, files, cid_version) {
+        Ok(cid) => {
+            info!(
+                "🔐 Locally computed folder CID for {}: {}",
+                dir.display(),
+                cid
+            );
+            Some(cid)
+        }
+        Err(e) => {
+            warn!("⚠️  Cannot compute the folder CID locally: {:#}", e);
+            None
+        }
+    }
+}
+
+/// single 子命令的运行参数
+#[derive(Debug, Clone, Default)]
+pub struct SingleOptions {
+    pub token_id: Option<u64>,
+    /// 直接指定要上传的图片，而不是从 `image/` 目录中选择
+    pub file: Option<PathBuf>,
+    /// 把元数据文件放进只有一个文件的文件夹上传，Token URI 与 batch 一样是 `<cid>/<token_id>`
+    pub as_folder: bool,
+    /// 图片超过该字节数时不上传
+    pub max_file_size: Option<u64>,
+}
+
+/// 文件名（不含扩展名）对应的数字 token ID，例如 `5.png`、`005.png` 都是 5
+fn stem_token_id(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// 选择 single 模式要上传的图片及其 token ID：`--file` 直接指定文件；
+/// `--token-id` 按文件名匹配 `image/` 下的图片，找不到时报错；都未指定时使用排序后的第一张图片
+fn select_single_image(
+    workspace: &Workspace,
+    single: &SingleOptions,
+    options: &MetadataOptions,
+) -> Result<(PathBuf, u64)> {
+    if let Some(file) = &single.file {
+        if !file.is_file() {
+            return Err(anyhow!("❌ Image file does not exist: {:?}", file));
+        }
+        let token_id = single
+            .token_id
+            .or_else(|| stem_token_id(file))
+            .ok_or_else(|| {
+                anyhow!(
+                    "❌ Cannot derive a token ID from {:?}, pass --token-id",
+                    file
+                )
+            })?;
+        return Ok((file.clone(), token_id));
+    }
+
+    let image_dir = workspace.single_image_dir();
+    if !image_dir.exists() {
+        return Err(anyhow!(
+            "❌ Image directory does not exist: {:?} (assets dir can be changed with --assets-dir or ASSETS_DIR)",
+            image_dir
+        ));
+    }
+
+    let image_files = list_image_files(&image_dir, options, false)?;
+
+    if image_files.is_empty() {
+        return Err(anyhow!("❌ No image files found in {:?}", image_dir));
+    }
+
+    match single.token_id {
+        Some(token_id) => {
+            let image_file = image_files
+                .into_iter()
+                .find(|path| stem_token_id(path) == Some(token_id))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "❌ No image for token ID {} in {:?} (expected a file such as {}.png)",
+                        token_id,
+                        image_dir,
+                        token_id
+                    )
+                })?;
+            Ok((image_file, token_id))
+        }
+        None => {
+            let image_file = image_files.into_iter().next().unwrap();
+            let token_id = stem_token_id(&image_file).unwrap_or(1);
+            Ok((image_file, token_id))
+        }
+    }
+}
+
+pub async fn process_single_file(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    single: &SingleOptions,
+    options: &MetadataOptions,
+) -> Result<SingleResult> {
+    info!("==============================================");
+    info!("🚀 Starting single file processing ({})...", api.name());
+    info!("==============================================");
+
+    let (image_file, token_id) = select_single_image(workspace, single, options)?;
+    if let Some(max_file_size) = single.max_file_size {
+        let root = image_file.parent().unwrap_or(Path::new(""));
+        check_file_sizes(std::slice::from_ref(&image_file), root, max_file_size)?;
+    }
+    let token_id_str = token_id.to_string();
+    let metadata_file_name = format!("{}.json", token_id);
+    // 先用占位的图片 URI 生成元数据，侧车文件或模板有误时在固定图片之前就报错
+    let mut metadata =
+        options.build_for_image(&image_file, &token_id_str, api.uri(PENDING_UPLOAD_ID))?;
+    if let Some(schema) = &options.schema {
+        schema.validate(&[(metadata_file_name.clone(), metadata.clone())])?;
+    }
+    // 在上传之前创建输出目录：目录已存在（如 --deterministic 未加 --overwrite）时不会固定任何内容
+    let output_dir = workspace.run_output_dir("single-upload")?;
+    let results_dir = output_dir.join("results");
+    fs::create_dir_all(&results_dir)?;
+
+    info!(
+        "📁 Uploading image file: {} (token ID {})",
+        image_file.display(),
+        token_id
+    );
+    let span = upload_span(&image_file);
+    let image_cid = upload_single_file_to_storage(api, &image_file)
+        .instrument(span.clone())
+        .await?;
+    span.record("cid", image_cid.as_str());
+    info!("✅ Image uploaded successfully! CID: {}", image_cid);
+
+    metadata.image = api.uri(&image_cid);
+    if let Some(schema) = &options.schema {
+        schema.validate(&[(metadata_file_name, metadata.clone())])?;
+    }
+
+    // 简化：只创建和上传一个元数据文件
+    let base_filename = image_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow!("Invalid filename"))?;
+
+    // 为了便于管理，我们给本地备份文件一个 .json 后缀，但上传时可以指定不带后缀的名字
+    let local_metadata_path = output_dir.join(format!("{}.json", base_filename));
+    write_file_atomically(
+        &local_metadata_path,
+        serde_json::to_string_pretty(&metadata)?.as_bytes(),
+    )?;
+
+    info!(
+        "📄 Created local metadata file: {}",
+        local_metadata_path.display()
+    );
+    let (metadata_cid, metadata_path) = if single.as_folder {
+        // 文件名与 batch 的元数据文件夹一致，合约的 baseURI 拼接 token ID 即可
+        let metadata_path = options.metadata_file_name(&token_id_str, true, false);
+        let folder = output_dir.join("metadata");
+        fs::create_dir_all(&folder)?;
+        let folder_file = folder.join(&metadata_path);
+        // 本地备份总是 JSON，文件夹中的文件按后缀（如 `.yaml`）写出对应的格式
+        write_file_atomically(
+            &folder_file,
+            serialize_metadata(&folder_file, &metadata)?.as_bytes(),
+        )?;
+        info!("📁 Uploading metadata folder ({})...", metadata_path);
+        let cid = upload_directory_with_retry(api, &folder, &[folder_file]).await?;
+        info!("✅ Metadata folder uploaded successfully! CID: {}", cid);
+        (cid, Some(metadata_path))
+    } else {
+        info!("📁 Uploading metadata file...");
+        // 上传这个文件，并获得其最终的、唯一的CID
+        let span = upload_span(&local_metadata_path);
+        let cid = upload_single_file_to_storage(api, &local_metadata_path)
+            .instrument(span.clone())
+            .await?;
+        span.record("cid", cid.as_str());
+        info!("✅ Metadata uploaded successfully! CID: {}", cid);
+        (cid, None)
+    };
+
+    let record = SingleRecord {
+        timestamp: workspace.timestamp(),
+        ..SingleRecord::new(
+            api,
+            options.collection_name.as_deref(),
+            token_id,
+            &image_cid,
+            &metadata_cid,
+            metadata_path.as_deref(),
+        )
+    };
+    save_single_results(api, &output_dir, &record)?;
+
+    Ok(SingleResult {
+        token_id,
+        image_cid,
+        metadata_cid,
+        metadata_path,
+        output_dir,
+        dry_run: api.is_dry_run(),
+    })
+}
+
+/// `dir` 下按文件名排序的所有文件，跳过隐藏文件和隐藏目录；没有文件时报错
+fn visible_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut entries = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.file_type().is_dir() {
+            if hidden {
+                entries.skip_current_dir();
+            }
+        } else if !hidden && entry.path().is_file() {
+            files.push(entry.into_path());
+        }
+    }
+    if files.is_empty() {
+        return Err(anyhow!("❌ No files to upload in {:?}", dir));
+    }
+    Ok(files)
+}
+
+/// 把现成的目录（如其他工具生成的元数据）作为一个文件夹上传，不生成任何元数据，返回文件夹 CID；
+/// 复用文件夹上传的重试、超时和 `--verify` 校验，结果保存到 `output/upload-dir-<timestamp>/result.json`
+pub async fn upload_dir(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    dir: &Path,
+    verify: bool,
+    trailing_slash: bool,
+) -> Result<String> {
+    info!("==============================================");
+    info!("📁 Uploading directory {}...", dir.display());
+    info!("==============================================");
+
+    if !dir.exists() {
+        return Err(anyhow!("❌ Directory does not exist: {:?}", dir));
+    }
+    if !dir.is_dir() {
+        return Err(anyhow!("❌ Not a directory: {:?}", dir));
+    }
+
+    let files = visible_files(dir)?;
+    let folder_size = calculate_folder_size(dir)?;
+    info!(
+        "📁 {} files to upload, folder size: {} ({} bytes)",
+        files.len(),
+        format_size(folder_size),
+        folder_size
+    );
+    // 结果目录无法使用时在固定之前退出，否则上传成功的 CID 没有地方记录
+    let output_dir = workspace.run_output_dir("upload-dir")?;
+    fs::create_dir_all(&output_dir)?;
+    workspace.confirm_upload(api, dir, files.len(), folder_size)?;
+
+    let cid = upload_directory_with_retry(api, dir, &files).await?;
+    info!("\n📁 Folder CID obtained: {}", cid);
+    if verify {
+        verify_directory_upload(api, &cid, dir, &files).await?;
+    }
+
+    let result = serde_json::json!({
+        "backend": api.name(),
+        "cid": cid,
+        "cid_version": api.cid_version(),
+        "dry_run": api.is_dry_run(),
+        "group_id": api.group_id(),
+        "path": dir,
+        "replication_regions": api.replication_regions(),
+        "status": if api.is_dry_run() { "dry_run" } else { "completed" },
+        "timestamp": workspace.timestamp(),
+        "total_files": files.len(),
+        "total_size": folder_size
+    });
+
+    let result_file = output_dir.join("result.json");
+    let mut file = File::create(&result_file)?;
+    file.write_all(serde_json::to_string_pretty(&result)?.as_bytes())?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    info!("\n--- ✨ Directory upload completed ✨ ---");
+    info!("Base URI: {}", base_uri(api, &cid, trailing_slash));
+
+    Ok(cid)
+}
+
+/// 上传 CAR 文件；传入文件夹时先按 `--cid-version` 在本地打包成 CAR（跳过隐藏文件，与 upload-dir 相同）。
+/// 上传前校验根 CID 和每个块，结果保存到 `output/upload-car-<timestamp>/result.json`
+pub async fn upload_car(
+    api: &PinataClient,
+    workspace: &Workspace,
+    path: &Path,
+    trailing_slash: bool,
+) -> Result<String> {
+    info!("==============================================");
+    info!("📦 Uploading CAR {}...", path.display());
+    info!("==============================================");
+
+    if !path.exists() {
+        return Err(anyhow!("❌ Path does not exist: {:?}", path));
+    }
+    let output_dir = workspace.run_output_dir("upload-car")?;
+    fs::create_dir_all(&output_dir)?;
+    let (car_path, file_count) = if path.is_dir() {
+        let files = visible_files(path)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "folder".to_string());
+        let car_path = output_dir.join(format!("{}.car", name));
+        let root = pack_directory_car(path, &files, api.cid_version, &car_path)?;
+        info!(
+            "📦 Packed {} files into {} (root {})",
+            files.len(),
+            car_path.display(),
+            root
+        );
+        (car_path, files.len())
+    } else {
+        (path.to_path_buf(), 1)
+    };
+
+    let summary = inspect_car(&car_path)?;
+    info!(
+        "🔍 CAR is valid: root {}, {} blocks, {}",
+        summary.root,
+        summary.blocks,
+        format_size(summary.size)
+    );
+    workspace.confirm_upload(api, path, file_count, summary.size)?;
+
+    info!(
+        "⏱️  Timeout per attempt: {}",
+        api.retry.describe_upload_timeout(summary.size)
+    );
+    let span = upload_span(&car_path);
+    let cid = retry_with_timeout(api.retry.for_upload(summary.size), || async {
+        api.upload_car(&car_path).await.context("CAR upload failed")
+    })
+    .instrument(span.clone())
+    .await?;
+    span.record("cid", cid.as_str());
+    if !same_content(&cid, &summary.root) {
+        warn!(
+            "⚠️  Pinata returned {} but the CAR root is {}",
+            cid, summary.root
+        );
+    }
+    info!("✅ CAR uploaded successfully! CID: {}", cid);
+
+    let result = serde_json::json!({
+        "backend": api.name(),
+        "blocks": summary.blocks,
+        "car": car_path,
+        "cid": cid,
+        "group_id": api.group_id(),
+        "packed_from": path.is_dir().then_some(path),
+        "root_cid": summary.root,
+        "status": "completed",
+        "timestamp": workspace.timestamp(),
+        "total_size": summary.size
+    });
+    let result_file = output_dir.join("result.json");
+    let mut file = File::create(&result_file)?;
+    file.write_all(serde_json::to_string_pretty(&result)?.as_bytes())?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    info!("\n--- ✨ CAR upload completed ✨ ---");
+    info!("Base URI: {}", base_uri(api, &cid, trailing_slash));
+
+    Ok(cid)
+}
+
+/// `contract-meta`：上传集合级元数据，返回写入合约的 `contractURI`；`image` 是本地文件时先上传图片并替换为其 URI。
+/// 元数据副本和结果保存到 `output/contract-meta-<timestamp>/`
+pub async fn upload_contract_metadata(
+    api: &dyn StorageBackend,
+    workspace: &Workspace,
+    mut metadata: ContractMetadata,
+) -> Result<String> {
+    info!("==============================================");
+    info!("📜 Uploading contract metadata ({})...", api.name());
+    info!("==============================================");
+
+    metadata.validate()?;
+    let output_dir = workspace.run_output_dir("contract-meta")?;
+    fs::create_dir_all(&output_dir)?;
+
+    let mut image_cid = None;
+    if let Some(image) = &metadata.image
+        && !image.contains("://")
+    {
+        let image_file = PathBuf::from(image);
+        if !image_file.is_file() {
+            return Err(anyhow!(
+                "❌ Collection image not found: {:?} (pass a local file or a URI)",
+                image_file
+            ));
+        }
+        info!("🖼️  Uploading collection image {}...", image_file.display());
+        let cid = upload_single_file_with_retry(api, &image_file)
+            .instrument(upload_span(&image_file))
+            .await?;
+        info!("✅ Collection image uploaded successfully! CID: {}", cid);
+        metadata.image = Some(api.uri(&cid));
+        image_cid = Some(cid);
+    }
+
+    let metadata_file = output_dir.join("contract.json");
+    write_file_atomically(
+        &metadata_file,
+        serde_json::to_string_pretty(&metadata)?.as_bytes(),
+    )?;
+    info!(
+        "📄 Created contract metadata file: {}",
+        metadata_file.display()
+    );
+    let cid = upload_single_file_with_retry(api, &metadata_file)
+        .instrument(upload_span(&metadata_file))
+        .await?;
+    let contract_uri = api.uri(&cid);
+
+    let result = serde_json::json!({
+        "backend": api.name(),
+        "cid": cid,
+        "contract_uri": contract_uri,
+        "dry_run": api.is_dry_run(),
+        "group_id": api.group_id(),
+        "image": metadata.image,
+        "image_cid": image_cid,
+        "name": metadata.name,
+        "status": if api.is_dry_run() { "dry_run" } else { "completed" },
+        "timestamp": workspace.timestamp()
+    });
+    let result_file = output_dir.join("result.json");
+    let mut file = File::create(&result_file)?;
+    file.write_all(serde_json::to_string_pretty(&result)?.as_bytes())?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    info!("\n--- ✨ Contract metadata uploaded ✨ ---");
+    info!("Contract URI: {}", contract_uri);
+    info!("🔗 Gateway: {}", api.gateway_url(&cid));
+
+    Ok(contract_uri)
+}
+
+pub async fn pin_by_hash(api: &PinataClient, workspace: &Workspace, cid: &str) -> Result<()> {
+    info!("==============================================");
+    info!("📌 Pinning existing content by CID (Pinata)...");
+    info!("==============================================");
+
+    validate_cid(cid)?;
+
+    // 提交 pin 请求后任务已经存在，记录任务 ID 的目录要事先准备好
+    let output_dir = workspace.run_output_dir("pin")?;
+    fs::create_dir_all(&output_dir)?;
+
+    info!(
+        "🔄 Starting pin request with retry mechanism (max {} attempts)",
+        api.retry.max_attempts()
+    );
+    let result = retry_with_timeout(api.retry.clone(), || async {
+        api.pin_by_hash(cid).await.context("Pin by hash failed")
+    })
+    .await;
+    let res = match result {
+        Ok(res) => res,
+        Err(e) => {
+            error!("❌ Pin request failed: {:#}", e);
+            return Err(e);
+        }
+    };
+
+    info!("✅ Pin request accepted! CID: {}", res.ipfs_hash);
+    info!("🆔 Pin job ID: {}", res.id);
+    info!("📊 Pin status: {:?}", res.status);
+
+    let result = serde_json::json!({
+        "cid": res.ipfs_hash,
+        "job_id": res.id,
+        "name": res.name,
+        "pin_status": res.status,
+        "replication_regions": api.replication_regions(),
+        "status": "submitted",
+        "timestamp": workspace.timestamp()
+    });
+
+    let result_file = output_dir.join("result.json");
+    let mut file = File::create(&result_file)?;
+    file.write_all(serde_json::to_string_pretty(&result)?.as_bytes())?;
+
+    info!("✅ Results saved to: {:?}", output_dir);
+    info!("\n--- ✨ Pin process completed ✨ ---");
+
+    Ok(())
+}
+
+pub async fn unpin_by_hash(api: &PinataClient, cid: &str) -> Result<()> {
+    info!("==============================================");
+    info!("🧹 Unpinning content by CID (Pinata)...");
+    info!("==============================================");
+
+    validate_cid(cid)?;
+
+    info!(
+        "🔄 Starting unpin request with retry mechanism (max {} attempts)",
+        api.retry.max_attempts()
+    );
+    let result = retry_with_timeout(api.retry.clone(), || async {
+        api.unpin(cid).await.context("Unpin failed")
+    })
+    .await;
+    match result {
+        Ok(true) => info!("✅ Unpinned successfully! CID: {}", cid),
+        Ok(false) => warn!(
+            "⚠️  CID {} is not pinned by this account (already removed?), nothing to do",
+            cid
+        ),
+        Err(e) => {
+            error!("❌ Unpin request failed: {:#}", e);
+            return Err(e);
+        }
+    }
+
+    info!("\n--- ✨ Unpin process completed ✨ ---");
+    Ok(())
+}
+
+/// 确认前列出的待取消 CID 数量
+const MAX_UNPIN_PREVIEW: usize = 10;
+
+/// `unpin-batch` 要取消固定的内容，`--name-prefix` 与 `--cid-file` 二选一
+#[derive(Debug, Clone, Copy)]
+pub enum UnpinTargets<'a> {
+    /// 名称以该前缀开头（区分大小写）的所有 pin
+    NamePrefix(&'a str),
+    /// 每行一个 CID 的列表文件
+    CidFile(&'a Path),
+}
+
+/// `unpin-batch`：按名称前缀（从 pin 列表中筛选）或 CID 列表文件批量取消固定。
+/// 删除前确认数量（`--yes` 跳过，非终端环境必须传入 `--yes`），单个失败不中断，最后汇总失败的 CID
+pub async fn unpin_batch(
+    api: &PinataClient,
+    source: UnpinTargets<'_>,
+    assume_yes: bool,
+) -> Result<()> {
+    info!("==============================================");
+    info!("🧹 Batch unpinning content (Pinata)...");
+    info!("==============================================");
+
+    // (CID, 名称)；CID 列表文件中的条目没有名称
+    let mut targets: Vec<(String, Option<String>)> = Vec::new();
+    match source {
+        UnpinTargets::CidFile(path) => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read CID file {}", path.display()))?;
+            for (index, line) in content.lines().enumerate() {
+                let cid = line.trim();
+                if cid.is_empty() || cid.starts_with('#') {
+                    continue;
+                }
+                validate_cid(cid).with_context(|| format!("{}:{}", path.display(), index + 1))?;
+                targets.push((cid.to_string(), None));
+            }
+        }
+        UnpinTargets::NamePrefix(prefix) => {
+            if prefix.is_empty() {
+                return Err(anyhow!(
+                    "❌ --name-prefix must not be empty (it would match every pin)"
+                ));
+            }
+            targets.extend(
+                fetch_pinned(api)
+                    .await?
+                    .into_iter()
+                    .filter(|pin| {
+                        pin.metadata
+                            .name
+                            .as_deref()
+                            .is_some_and(|name| name.starts_with(prefix))
+                    })
+                    .map(|pin| (pin.ipfs_pin_hash, pin.metadata.name)),
+            );
+        }
+    }
+    // 列表文件中重复的行、以多个匹配的名称固定的同一内容都只取消一次
+    let mut seen = HashSet::new();
+    targets.retain(|(cid, _)| seen.insert(cid.clone()));
+
+    if targets.is_empty() {
+        info!("📭 Nothing to unpin");
+        return Ok(());
+    }
+    for (cid, name) in targets.iter().take(MAX_UNPIN_PREVIEW) {
+        info!("  {} {}", cid, name.as_deref().unwrap_or(""));
+    }
+    if targets.len() > MAX_UNPIN_PREVIEW {
+        info!("  ... and {} more", targets.len() - MAX_UNPIN_PREVIEW);
+    }
+
+    if !assume_yes {
+        if !is_interactive() {
+            return Err(anyhow!(
+                "❌ Refusing to unpin {} pins without confirmation outside a terminal (pass --yes)",
+                targets.len()
+            ));
+        }
+        let question = format!(
+            "⚠️  About to unpin {} {} from Pinata.",
+            targets.len(),
+            if targets.len() == 1 { "pin" } else { "pins" }
+        );
+        if !ask_yes_no(&question)? {
+            return Err(anyhow!(
+                "❌ Batch unpin not confirmed, nothing was unpinned"
+            ));
+        }
+    }
+
+    let (mut unpinned, mut not_pinned) = (0usize, 0usize);
+    let mut failures = Vec::new();
+    for (index, (cid, _)) in targets.iter().enumerate() {
+        let result = retry_with_timeout(api.retry.clone(), || async {
+            api.unpin(cid).await.context("Unpin failed")
+        })
+        .await;
+        match result {
+            Ok(true) => {
+                unpinned += 1;
+                info!("✅ [{}/{}] Unpinned {}", index + 1, targets.len(), cid);
+            }
+            Ok(false) => {
+                not_pinned += 1;
+                warn!(
+                    "⚠️  [{}/{}] {} is not pinned by this account, skipping",
+                    index + 1,
+                    targets.len(),
+                    cid
+                );
+            }
+            Err(e) => {
+                error!("❌ [{}/{}] {}: {:#}", index + 1, targets.len(), cid, e);
+                failures.push(cid.as_str());
+            }
+        }
+    }
+
+    info!(
+        "📊 {} unpinned, {} not pinned, {} failed",
+        unpinned,
+        not_pinned,
+        failures.len()
+    );
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "❌ {} of {} unpins failed: {}",
+            failures.len(),
+            targets.len(),
+            failures.join(", ")
+        ));
+    }
+    info!("\n--- ✨ Batch unpin completed ✨ ---");
+    Ok(())
+}
+
+/// 每页请求的 pin job 数量（pinJobs 接口允许的最大值）
+const QUEUE_PAGE_SIZE: u16 = 1000;
+
+pub async fn check_pin_queue(
+    api: &PinataClient,
+    status: Option<QueueStatus>,
+    since: Option<DateTime<Utc>>,
+    limit: Option<usize>,
+) -> Result<()> {
+    info!("==============================================");
+    info!("📋 Checking pin queue status (Pinata)...");
+    info!("==============================================");
+
+    let mut rows = Vec::new();
+    let mut offset = 0u64;
+    let total = loop {
+        let mut builder = PinJobsFilterBuilder::default();
+        builder.set_sort(SortDirection::ASC);
+        if let Some(status) = status {
+            builder.set_status(JobStatus::from(status));
+        }
+        builder.set_limit(QUEUE_PAGE_SIZE);
+        builder.set_offset(offset);
+        let filters = builder
+            .build()
+            .map_err(|e| anyhow!("Invalid pin queue filter: {}", e))?;
+
+        let result = retry_with_timeout(api.retry.clone(), || async {
+            api.get_pin_jobs(filters.clone())
+                .await
+                .context("Pin queue request failed")
+        })
+        .await;
+        let page = match result {
+            Ok(page) => page,
+            Err(e) => {
+                error!("❌ Pin queue request failed: {:#}", e);
+                return Err(e);
+            }
+        };
+
+        let page_len = page.rows.len() as u64;
+        offset += page_len;
+        for job in page.rows {
+            let queued_at = DateTime::parse_from_rfc3339(&job.date_queued)
+                .ok()
+                .map(|time| time.with_timezone(&Utc));
+            // 无法解析队列时间的记录不做时间过滤，避免被静默丢弃
+            if let (Some(since), Some(queued_at)) = (since, queued_at)
+                && queued_at < since
+            {
+                continue;
+            }
+            rows.push((queued_at, job));
+        }
+
+        let limit_reached = limit.is_some_and(|limit| rows.len() >= limit);
+        if page_len == 0 || offset >= page.count || limit_reached {
+            break page.count;
+        }
+        info!(
+            "📄 Fetched {}/{} jobs, requesting next page...",
+            offset, page.count
+        );
+    };
+
+    // 稳定排序：队列时间相同的记录保持 API 返回的顺序
+    rows.sort_by_key(|(queued_at, _)| *queued_at);
+    if let Some(limit) = limit {
+        rows.truncate(limit);
+    }
+
+    if rows.is_empty() {
+        info!("📭 No jobs in queue");
+        return Ok(());
+    }
+
+    info!(
+        "{:<48} {:<18} {:<26} {}",
+        "CID", "STATUS", "QUEUED AT", "NAME"
+    );
+    for (_, job) in &rows {
+        info!(
+            "{:<48} {:<18} {:<26} {}",
+            job.ipfs_pin_hash,
+            format!("{:?}", job.status),
+            job.date_queued,
+            job.name.as_deref().unwrap_or("-")
+        );
+    }
+    match since {
+        Some(since) => info!(
+            "📊 Showing {} jobs queued since {} ({} jobs in queue)",
+            rows.len(),
+            since.to_rfc3339(),
+            total
+        ),
+        None => info!("📊 Showing {} of {} jobs in queue", rows.len(), total),
+    }
+
+    Ok(())
+}
+
+const PIN_LIST_PAGE_SIZE: u64 = 1000;
+
+/// stdin 和 stdout 都是终端时才能询问用户
+fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// 在 stderr 上询问 `question Continue? [y/N]`（`--quiet` 时 stdout 只保留结果），只有 `y` / `yes` 算同意
+fn ask_yes_no(question: &str) -> io::Result<bool> {
+    eprint!("{} Continue? [y/N] ", question);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// 翻页拉取账户中所有已固定的内容
+async fn fetch_pinned(api: &PinataClient) -> Result<Vec<PinListItem>> {
+    let mut pins = Vec::new();
+    let mut offset = 0u64;
+    loop {
+        let filters = PinListFilterBuilder::default()
+            .set_status(PinListFilterStatus::Pinned)
+            .set_page_limit(PIN_LIST_PAGE_SIZE.to_string())
+            .set_page_offset(offset.to_string())
+            .build()
+            .map_err(|e| anyhow!("Invalid pin list filter: {}", e))?;
+
+        let page = retry_with_timeout(api.retry.clone(), || async {
+            api.get_pin_list(&filters)
+                .await
+                .context("Pin list request failed")
+        })
+        .await?;
+
+        let page_len = page.rows.len() as u64;
+        offset += page_len;
+        pins.extend(page.rows);
+        if page_len == 0 || u128::from(offset) >= page.count {
+            return Ok(pins);
+        }
+        info!(
+            "📄 Fetched {}/{} pins, requesting next page...",
+            offset, page.count
+        );
+    }
+}
+
+/// `--max-file-size`：列出每个超过上限的文件（路径相对于 `root`）后返回错误，不会上传任何内容
+fn check_file_sizes(files: &[PathBuf], root: &Path, max_file_size: u64) -> Result<()> {
+    let mut oversized = 0;
+    for path in files {
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .len();
+        if size > max_file_size {
+            oversized += 1;
+            error!(
+                "❌ {}: {} exceeds --max-file-size",
+                path.strip_prefix(root).unwrap_or(path).display(),
+                format_size(size)
+            );
+        }
+    }
+    if oversized > 0 {
+        return Err(anyhow!(
+            "❌ {} of {} files are larger than --max-file-size {}, nothing was uploaded",
+            oversized,
+            files.len(),
+            format_size(max_file_size)
+        ));
+    }
+    info!(
+        "✅ No file exceeds --max-file-size {}",
+        format_size(max_file_size)
+    );
+    Ok(())
+}
+
+pub async fn list_pins(
+    api: &PinataClient,
+    name_contains: Option<&str>,
+    sort: ListSort,
+) -> Result<()> {
+    info!("==============================================");
+    info!("📌 Listing pinned content (Pinata)...");
+    info!("==============================================");
+
+    let mut pins = fetch_pinned(api).await?;
+
+    // 名称过滤在本地进行：pinList 的 metadata[name] 参数需要嵌套的查询格式
+    if let Some(needle) = name_contains {
+        let needle = needle.to_lowercase();
+        pins.retain(|pin| {
+            pin.metadata
+                .name
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase().contains(&needle))
+        });
+    }
+    match sort {
+        // RFC3339 时间戳可以直接按字符串比较
+        ListSort::Date => pins.sort_by(|a, b| b.date_pinned.cmp(&a.date_pinned)),
+        ListSort::Size => pins.sort_by_key(|pin| std::cmp::Reverse(pin.size)),
+    }
+
+    if pins.is_empty() {
+        info!("📭 No pinned content found");
+        return Ok(());
+    }
+
+    info!(
+        "{:<62} {:>12} {:<26} {}",
+        "CID", "SIZE", "PINNED AT", "NAME"
+    );
+    for pin in &pins {
+        info!(
+            "{:<62} {:>12} {:<26} {}",
+            pin.ipfs_pin_hash,
+            format_size(pin.size as u64),
+            pin.date_pinned,
+            pin.metadata.name.as_deref().unwrap_or("-")
+        );
+    }
+    let total_size: u64 = pins.iter().map(|pin| pin.size as u64).sum();
+    info!(
+        "📊 {} pins, {} in total",
+        pins.len(),
+        format_size(total_size)
+    );
+
+    Ok(())
+}
+
+/// Pinata 免费套餐的额度；API 不返回账户套餐，付费账户的上限更高
+const FREE_TIER_PIN_LIMIT: u128 = 500;
+const FREE_TIER_SIZE_LIMIT: u64 = 1024 * 1024 * 1024;
+/// 用量达到该比例时给出警告
+const USAGE_WARN_RATIO: f64 = 0.8;
+
+pub async fn show_usage(api: &PinataClient) -> Result<()> {
+    info!("==============================================");
+    info!("📊 Checking account usage (Pinata)...");
+    info!("==============================================");
+
+    let usage = retry_with_timeout(api.retry.clone(), || async {
+        api.get_total_pinned_data()
+            .await
+            .context("Usage request failed")
+    })
+    .await?;
+    let size_total: u64 = usage
+        .pin_size_total
+        .parse()
+        .with_context(|| format!("Invalid pin_size_total: {}", usage.pin_size_total))?;
+    let size_with_replications: u64 = usage
+        .pin_size_with_replications_total
+        .parse()
+        .unwrap_or(size_total);
+
+    let pin_ratio = usage.pin_count as f64 / FREE_TIER_PIN_LIMIT as f64;
+    let size_ratio = size_total as f64 / FREE_TIER_SIZE_LIMIT as f64;
+    info!(
+        "📌 Pins: {} / {} ({:.1}% of the free tier)",
+        usage.pin_count,
+        FREE_TIER_PIN_LIMIT,
+        pin_ratio * 100.0
+    );
+    info!(
+        "💾 Pinned size: {} / {} ({:.1}% of the free tier)",
+        format_size(size_total),
+        format_size(FREE_TIER_SIZE_LIMIT),
+        size_ratio * 100.0
+    );
+    if size_with_replications > size_total {
+        info!(
+            "🔁 Including replications: {}",
+            format_size(size_with_replications)
+        );
+    }
+
+    if pin_ratio >= 1.0 || size_ratio >= 1.0 {
+        warn!(
+            "⚠️  Usage exceeds the Pinata free tier, uploads will fail unless the account is on a paid plan"
+        );
+    } else if pin_ratio >= USAGE_WARN_RATIO || size_ratio >= USAGE_WARN_RATIO {
+        warn!(
+            "⚠️  Usage is above {:.0}% of the Pinata free tier, check the remaining quota before a large upload",
+            USAGE_WARN_RATIO * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+/// 通过网关下载 `cid` 并与本地文件比较 SHA-256，不一致时返回错误
+pub async fn verify_file(api: &dyn StorageBackend, cid: &str, file: &Path) -> Result<()> {
+    info!("==============================================");
+    info!("🔍 Verifying {} against {}...", cid, file.display());
+    info!("==============================================");
+
+    if !file.is_file() {
+        return Err(anyhow!("❌ Local file does not exist: {:?}", file));
+    }
+    let root = cid.split('/').next().unwrap_or_default();
+    api.validate_id(root)?;
+
+    let expected = hash_file(file)?;
+    let local_size = fs::metadata(file)?.len();
+    let downloaded = fetch_from_gateway(api, cid).await?;
+    let actual = hash_bytes(&downloaded);
+
+    info!("📄 Local:   {} ({})", expected, format_size(local_size));
+    info!(
+        "🌐 Gateway: {} ({})",
+        actual,
+        format_size(downloaded.len() as u64)
+    );
+    if actual != expected {
+        return Err(anyhow!(
+            "❌ Mismatch: the content of {} differs from {}",
+            cid,
+            file.display()
+        ));
+    }
+    info!(
+        "✅ Match: {} has the same content as {}",
+        cid,
+        file.display()
+    );
+    Ok(())
+}
+
+/// 非 JSON 内容在错误信息中最多显示的字符数
+const MAX_CONTENT_PREVIEW: usize = 80;
+
+/// `show`：通过网关读取元数据，返回要输出的内容；`token_id` 为文件夹中的文件名，`raw` 时原样返回
+pub async fn show_metadata(
+    api: &dyn StorageBackend,
+    cid: &str,
+    token_id: Option<&str>,
+    raw: bool,
+) -> Result<String> {
+    let root = cid.split('/').next().unwrap_or_default();
+    api.validate_id(root)?;
+    let target = match token_id {
+        Some(token_id) => format!("{}/{}", cid.trim_end_matches('/'), token_id),
+        None => cid.to_string(),
+    };
+    info!("🔍 Showing metadata at {}", api.uri(&target));
+
+    let content = fetch_from_gateway(api, &target).await?;
+    let text = String::from_utf8(content).map_err(|e| {
+        anyhow!(
+            "❌ {} is binary content ({} bytes), not a metadata JSON (is it an image?)",
+            target,
+            e.as_bytes().len()
+        )
+    })?;
+    let value: serde_json::Value = serde_json::from_str(&text).map_err(|e| {
+        let preview: String = text.trim().chars().take(MAX_CONTENT_PREVIEW).collect();
+        let hint = if token_id.is_none() && preview.starts_with('<') {
+            " (a folder CID? pass --token-id)"
+        } else {
+            ""
+        };
+        anyhow!(
+            "❌ {} is not JSON{}: {} (content starts with {:?})",
+            target,
+            hint,
+            e,
+            preview
+        )
+    })?;
+    if raw {
+        return Ok(text);
+    }
+
+    let metadata: NftMetadata = serde_json::from_value(value.clone()).map_err(|e| {
+        anyhow!(
+            "❌ {} is JSON but not NFT metadata: {} (use --raw to print it anyway)",
+            target,
+            e
+        )
+    })?;
+    // 解析成 NftMetadata 时会丢掉未知字段，列出来避免误以为固定的内容只有这些
+    let known = serde_json::to_value(&metadata)?;
+    if let (Some(all), Some(known)) = (value.as_object(), known.as_object()) {
+        let unknown: Vec<&str> = all
+            .keys()
+            .filter(|key| !known.contains_key(*key))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            warn!(
+                "⚠️  Fields not shown (use --raw to see them): {}",
+                unknown.join(", ")
+            );
+        }
+    }
+    info!(
+        "✅ {}: {} attributes, image {}",
+        metadata.name,
+        metadata.attributes.len(),
+        metadata.image
+    );
+    Ok(serde_json::to_string_pretty(&metadata)?)
+}
+
+pub async fn run_smoke_test(api: &PinataClient, skip_fetch: bool) -> Result<()> {
+    info!("==============================================");
+    info!("🧪 Starting Pinata smoke test...");
+    info!("==============================================");
+
+    // 认证已在 main 中完成
+    let mut steps: Vec<(&str, bool)> = vec![("Authentication", true)];
+
+    // 内容带时间戳，保证 CID 唯一，避免误删用户已固定的相同内容
+    let temp_dir = tempfile::tempdir()?;
+    let test_file = temp_dir.path().join("pinata-smoke-test.txt");
+    let content = format!(
+        "polyglot-pinata-uploader smoke test {}",
+        Utc::now().to_rfc3339()
+    );
+    fs::write(&test_file, content.as_bytes())?;
+
+    let cid = match upload_single_file_to_storage(api, &test_file).await {
+        Ok(cid) => {
+            steps.push(("Upload test file", true));
+            Some(cid)
+        }
+        Err(e) => {
+            error!("❌ Test upload failed: {}", e);
+            steps.push(("Upload test file", false));
+            None
+        }
+    };
+
+    if let Some(cid) = &cid {
+        if !skip_fetch {
+            let fetched = match fetch_from_gateway(api, cid).await {
+                Ok(bytes) if bytes == content.as_bytes() => {
+                    info!("✅ Gateway content matches uploaded bytes");
+                    true
+                }
+                Ok(bytes) => {
+                    error!(
+                        "❌ Gateway content mismatch: expected {} bytes (sha256 {}), got {} bytes (sha256 {})",
+                        content.len(),
+                        hash_bytes(content.as_bytes()),
+                        bytes.len(),
+                        hash_bytes(&bytes)
+                    );
+                    false
+                }
+                Err(e) => {
+                    error!("❌ Gateway fetch failed: {:#}", e);
+                    false
+                }
+            };
+            steps.push(("Fetch via gateway", fetched));
+        }
+
+        let result = retry_with_timeout(api.retry.clone(), || async {
+            api.unpin(cid).await.context("Unpin failed")
+        })
+        .await;
+        let unpinned = match result {
+            Ok(true) => {
+                info!("🧹 Unpinned test file: {}", cid);
+                true
+            }
+            // 刚上传的文件不在当前账户的 pin 中，说明上传并没有真正固定下来
+            Ok(false) => {
+                error!(
+                    "❌ Test file {} is not pinned by this account right after uploading it",
+                    cid
+                );
+                false
+            }
+            Err(e) => {
+                error!("❌ Failed to unpin test file {}: {}", cid, e);
+                false
+            }
+        };
+        steps.push(("Unpin test file", unpinned));
+    }
+
+    info!("\n--- 🧪 Smoke test summary ---");
+    for (step, passed) in &steps {
+        if *passed {
+            info!("✅ PASS  {}", step);
+        } else {
+            error!("❌ FAIL  {}", step);
+        }
+    }
+
+    if steps.iter().all(|(_, passed)| *passed) {
+        info!("\n--- ✨ All smoke test steps passed ✨ ---");
+        Ok(())
+    } else {
+        Err(anyhow!("❌ Smoke test failed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pinata::tests::MockServer;
+
+    fn workspace(root: &Path) -> Workspace {
+        Workspace {
+            assets_dir: root.join("assets"),
+            output_dir: root.join("output"),
+            overwrite: false,
+            run_name: None,
+            confirmation: UploadConfirmation {
+                max_bytes: u64::MAX,
+                max_files: usize::MAX,
+                assume_yes: true,
+            },
+        }
+    }
+
+    #[test]
+    fn work_dirs_created_in_the_same_second_do_not_collide() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = workspace(root.path());
+
+        let first = workspace
+            .create_work_dir(format!(
+                "batch_images-metadata-{}",
+                workspace.work_dir_stamp()
+            ))
+            .unwrap();
+        let second = workspace
+            .create_work_dir(format!(
+                "batch_images-metadata-{}",
+                workspace.work_dir_stamp()
+            ))
+            .unwrap();
+
+        assert_ne!(first.target, second.target);
+        assert_ne!(first.path, second.path);
+        assert!(first.path.is_dir());
+        assert!(second.path.is_dir());
+    }
+
+    fn file_names(files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| f.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn image_files_are_sorted_numerically() {
+        let mut files: Vec<PathBuf> = ["10.png", "cover.png", "2.png", "1.png", "banner.png"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        sort_image_files(&mut files, &crate::metadata::tests::options());
+
+        assert_eq!(
+            file_names(&files),
+            ["1.png", "2.png", "10.png", "banner.png", "cover.png"]
+        );
+    }
+
+    /// pin 列表中的一行
+    fn pin_row(cid: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": format!("pin-{}", name),
+            "ipfs_pin_hash": cid,
+            "size": 1,
+            "user_id": "user",
+            "date_pinned": "2026-01-01T00:00:00.000Z",
+            "date_unpinned": null,
+            "metadata": { "name": name, "keyvalues": null },
+            "regions": []
+        })
+    }
+
+    #[tokio::test]
+    async fn unpin_batch_unpins_each_matching_cid_once() {
+        const CID: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+        let pin_list = serde_json::json!({
+            "count": 3,
+            "rows": [
+                pin_row(CID, "test-run/images"),
+                pin_row(CID, "test-run/images-again"),
+                pin_row("QmOther", "production/images"),
+            ]
+        })
+        .to_string();
+        let server = MockServer::start(vec![(200, pin_list.as_str()), (200, "OK")]).await;
+
+        unpin_batch(
+            &server.client(0),
+            UnpinTargets::NamePrefix("test-run/"),
+            true,
+        )
+        .await
+        .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2, "{:?}", requests);
+        assert!(
+            requests[0].starts_with("GET /data/pinList?"),
+            "{:?}",
+            requests
+        );
+        assert_eq!(requests[1], format!("DELETE /pinning/unpin/{}", CID));
+    }
+
+    #[tokio::test]
+    async fn smoke_test_fails_when_the_uploaded_file_is_not_pinned() {
+        let server = MockServer::start(vec![
+            (
+                200,
+                r#"{"IpfsHash":"QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn","PinSize":1,"Timestamp":"2026-01-01T00:00:00.000Z"}"#,
+            ),
+            (400, r#"{"error":{"reason":"NOT_PINNED","details":"not pinned"}}"#),
+        ])
+        .await;
+
+        assert!(run_smoke_test(&server.client(0), true).await.is_err());
+        assert_eq!(
+            server.requests(),
+            [
+                "POST /pinning/pinFileToIPFS",
+                "DELETE /pinning/unpin/QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn"
+            ]
+        );
+    }
+}